@@ -1,4 +1,5 @@
 use serde::{Deserialize};
 
+#[allow(dead_code)]
 #[derive(Debug, PartialEq, Deserialize)]
 pub struct General {}
\ No newline at end of file
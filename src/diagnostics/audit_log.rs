@@ -0,0 +1,48 @@
+use std::{rc::Rc, cell::RefCell, collections::VecDeque, time::SystemTime};
+
+#[derive(Clone, Debug)]
+pub struct AuditEntry {
+    time: SystemTime,
+    source: String,
+    message: String,
+}
+
+impl AuditEntry {
+    pub fn get_time(&self) -> SystemTime { self.time }
+    pub fn get_source(&self) -> &str { &self.source }
+    pub fn get_message(&self) -> &str { &self.message }
+}
+
+#[derive(Clone)]
+pub struct AuditLog {
+    entries: Rc<RefCell<VecDeque<AuditEntry>>>,
+    capacity: usize,
+}
+
+impl AuditLog {
+    pub fn new(capacity: usize) -> Self {
+        Self { entries: Rc::new(RefCell::new(VecDeque::with_capacity(capacity))), capacity }
+    }
+
+    pub fn record(&self, source: &str, message: String) {
+        let mut entries = self.entries.borrow_mut();
+
+        if entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+
+        entries.push_back(AuditEntry { time: SystemTime::now(), source: source.to_string(), message });
+    }
+
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        self.entries.borrow().iter().cloned().collect()
+    }
+
+    pub fn drain(&self) -> Vec<AuditEntry> {
+        self.entries.borrow_mut().drain(..).collect()
+    }
+
+    pub fn len(&self) -> usize { self.entries.borrow().len() }
+
+    pub fn is_empty(&self) -> bool { self.entries.borrow().is_empty() }
+}
@@ -1,10 +1,13 @@
 pub mod task;
 pub mod pls_std;
 pub mod system_prog;
+pub mod memory;
+pub mod diagnostics;
 
 mod config;
 
 pub use rmodbus::server::context::ModbusContext;
+pub use memory::{DataMemory, PlcMemory};
 
 use ansi_term::Color::Red;
 use ansi_term::ANSIGenericString;
@@ -13,14 +16,14 @@ use std::{result, error};
 pub struct Plc<'a> {
     task_event: Vec<task::Task<'a>>,
     bacground: Vec<task::Task<'a>>,
-    context: ModbusContext,
+    context: Box<dyn DataMemory>,
     call_stack: Vec<task::Task<'a>>,
 }
 
 impl<'a> Plc<'a> {
-    pub fn new<const N: usize>(
+    pub fn new<const N: usize, M: DataMemory + 'static>(
         tasks: [task::Task<'a>; N],
-        context: ModbusContext,
+        context: M,
     ) -> Self {
         let mut task_event: Vec<task::Task> = Vec::new();
         let mut bacground: Vec<task::Task> = Vec::new();
@@ -35,7 +38,7 @@ impl<'a> Plc<'a> {
 
         bacground.sort_unstable();
 
-        Self { task_event, bacground, context, call_stack: Vec::new() }
+        Self { task_event, bacground, context: Box::new(context), call_stack: Vec::new() }
     }
 
     pub fn run(&mut self) {    
@@ -72,7 +75,7 @@ impl<'a> Plc<'a> {
 
         for i in 0..self.task_event.len() {
 
-            let need_run = self.task_event[i].need_run(&self.context)?;
+            let need_run = self.task_event[i].need_run(&*self.context)?;
 
             if need_run {
                 need_run_index.push(i);
@@ -83,7 +86,7 @@ impl<'a> Plc<'a> {
             self.call_stack.push(self.task_event.swap_remove(i));
         }
 
-        if self.call_stack.is_empty() && self.bacground.get(0).is_some() {
+        if self.call_stack.is_empty() && !self.bacground.is_empty() {
             self.call_stack.push(self.bacground.remove(0));
         }
 
@@ -94,7 +97,7 @@ impl<'a> Plc<'a> {
 
     fn call_task(&mut self) -> result::Result<u8, Box<dyn error::Error>> {
         let result = match self.call_stack.first_mut() {
-            Some(task) => task.run(&mut self.context)?,
+            Some(task) => task.run(&mut *self.context)?,
             None => u8::MAX,
         };
 
@@ -102,7 +105,7 @@ impl<'a> Plc<'a> {
     }
 
     fn return_task(&mut self) {
-        if self.call_stack.get(0).is_none() {
+        if self.call_stack.is_empty() {
             return;
         }
 
@@ -123,6 +126,6 @@ fn error_log(e:  Box<dyn error::Error>, task: Option<&str>) {
     }
 }
 
-fn fail_strig(e: & dyn error::Error) -> ANSIGenericString<str> {
+fn fail_strig(e: & dyn error::Error) -> ANSIGenericString<'_, str> {
     Red.paint(format!("err: {}", e))
 }
@@ -0,0 +1,13 @@
+mod data_memory;
+mod plc_memory;
+mod forces;
+mod force_error;
+mod forced_memory;
+mod watch_table;
+
+pub use data_memory::{DataMemory, MemoryArea};
+pub use plc_memory::PlcMemory;
+pub use forces::{Forces, Force, Value, ValueKind};
+pub use force_error::ForceErr;
+pub use forced_memory::ForcedMemory;
+pub use watch_table::{WatchTable, WatchEntry, WatchValue};
@@ -0,0 +1,224 @@
+use rmodbus::ErrorKind;
+use rmodbus::server::context::{ModbusContext, CONTEXT_SIZE};
+use serde::{Serialize, Deserialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryArea {
+    Coils,
+    Discretes,
+    Inputs,
+    Holdings,
+    Markers,
+}
+
+pub trait DataMemory {
+    fn get_size(&self, area: MemoryArea) -> usize;
+
+    fn get_coil(&self, reg: u16) -> Result<bool, ErrorKind>;
+    fn get_discrete(&self, reg: u16) -> Result<bool, ErrorKind>;
+    fn get_input(&self, reg: u16) -> Result<u16, ErrorKind>;
+    fn get_holding(&self, reg: u16) -> Result<u16, ErrorKind>;
+
+    fn set_coil(&mut self, reg: u16, value: bool) -> Result<(), ErrorKind>;
+    fn set_discrete(&mut self, reg: u16, value: bool) -> Result<(), ErrorKind>;
+    fn set_input(&mut self, reg: u16, value: u16) -> Result<(), ErrorKind>;
+    fn set_holding(&mut self, reg: u16, value: u16) -> Result<(), ErrorKind>;
+
+    fn get_marker(&self, _reg: u16) -> Result<u16, ErrorKind> {
+        Err(ErrorKind::OOBContext)
+    }
+
+    fn set_marker(&mut self, _reg: u16, _value: u16) -> Result<(), ErrorKind> {
+        Err(ErrorKind::OOBContext)
+    }
+
+    fn get_word(&self, area: MemoryArea, reg: u16) -> Result<u16, ErrorKind> {
+        match area {
+            MemoryArea::Coils => self.get_coil(reg).map(u16::from),
+            MemoryArea::Discretes => self.get_discrete(reg).map(u16::from),
+            MemoryArea::Inputs => self.get_input(reg),
+            MemoryArea::Holdings => self.get_holding(reg),
+            MemoryArea::Markers => self.get_marker(reg),
+        }
+    }
+
+    fn set_word(&mut self, area: MemoryArea, reg: u16, value: u16) -> Result<(), ErrorKind> {
+        match area {
+            MemoryArea::Coils => self.set_coil(reg, value != 0),
+            MemoryArea::Discretes => self.set_discrete(reg, value != 0),
+            MemoryArea::Inputs => self.set_input(reg, value),
+            MemoryArea::Holdings => self.set_holding(reg, value),
+            MemoryArea::Markers => self.set_marker(reg, value),
+        }
+    }
+
+    fn get_coils_bulk(&self, reg: u16, count: u16, result: &mut Vec<bool>) -> Result<(), ErrorKind> {
+        check_range(self.get_size(MemoryArea::Coils), reg, count as usize)?;
+        for i in 0..count {
+            result.push(self.get_coil(reg + i)?);
+        }
+        Ok(())
+    }
+
+    fn get_discretes_bulk(&self, reg: u16, count: u16, result: &mut Vec<bool>) -> Result<(), ErrorKind> {
+        check_range(self.get_size(MemoryArea::Discretes), reg, count as usize)?;
+        for i in 0..count {
+            result.push(self.get_discrete(reg + i)?);
+        }
+        Ok(())
+    }
+
+    fn get_inputs_bulk(&self, reg: u16, count: u16, result: &mut Vec<u16>) -> Result<(), ErrorKind> {
+        check_range(self.get_size(MemoryArea::Inputs), reg, count as usize)?;
+        for i in 0..count {
+            result.push(self.get_input(reg + i)?);
+        }
+        Ok(())
+    }
+
+    fn get_holdings_bulk(&self, reg: u16, count: u16, result: &mut Vec<u16>) -> Result<(), ErrorKind> {
+        check_range(self.get_size(MemoryArea::Holdings), reg, count as usize)?;
+        for i in 0..count {
+            result.push(self.get_holding(reg + i)?);
+        }
+        Ok(())
+    }
+
+    fn set_coils_bulk(&mut self, reg: u16, values: &[bool]) -> Result<(), ErrorKind> {
+        check_range(self.get_size(MemoryArea::Coils), reg, values.len())?;
+        for (i, value) in values.iter().enumerate() {
+            self.set_coil(reg + i as u16, *value)?;
+        }
+        Ok(())
+    }
+
+    fn set_discretes_bulk(&mut self, reg: u16, values: &[bool]) -> Result<(), ErrorKind> {
+        check_range(self.get_size(MemoryArea::Discretes), reg, values.len())?;
+        for (i, value) in values.iter().enumerate() {
+            self.set_discrete(reg + i as u16, *value)?;
+        }
+        Ok(())
+    }
+
+    fn set_inputs_bulk(&mut self, reg: u16, values: &[u16]) -> Result<(), ErrorKind> {
+        check_range(self.get_size(MemoryArea::Inputs), reg, values.len())?;
+        for (i, value) in values.iter().enumerate() {
+            self.set_input(reg + i as u16, *value)?;
+        }
+        Ok(())
+    }
+
+    fn set_holdings_bulk(&mut self, reg: u16, values: &[u16]) -> Result<(), ErrorKind> {
+        check_range(self.get_size(MemoryArea::Holdings), reg, values.len())?;
+        for (i, value) in values.iter().enumerate() {
+            self.set_holding(reg + i as u16, *value)?;
+        }
+        Ok(())
+    }
+
+    fn get_inputs_as_u32(&self, reg: u16) -> Result<u32, ErrorKind> {
+        let hi = self.get_input(reg)? as u32;
+        let lo = self.get_input(reg.checked_add(1).ok_or(ErrorKind::OOBContext)?)? as u32;
+        Ok(hi << 16 | lo)
+    }
+
+    fn get_holdings_as_u32(&self, reg: u16) -> Result<u32, ErrorKind> {
+        let hi = self.get_holding(reg)? as u32;
+        let lo = self.get_holding(reg.checked_add(1).ok_or(ErrorKind::OOBContext)?)? as u32;
+        Ok(hi << 16 | lo)
+    }
+
+    fn set_inputs_from_u32(&mut self, reg: u16, value: u32) -> Result<(), ErrorKind> {
+        self.set_inputs_bulk(reg, &[(value >> 16) as u16, value as u16])
+    }
+
+    fn set_holdings_from_u32(&mut self, reg: u16, value: u32) -> Result<(), ErrorKind> {
+        self.set_holdings_bulk(reg, &[(value >> 16) as u16, value as u16])
+    }
+
+    fn get_inputs_as_f32(&self, reg: u16) -> Result<f32, ErrorKind> {
+        Ok(f32::from_bits(self.get_inputs_as_u32(reg)?))
+    }
+
+    fn get_holdings_as_f32(&self, reg: u16) -> Result<f32, ErrorKind> {
+        Ok(f32::from_bits(self.get_holdings_as_u32(reg)?))
+    }
+
+    fn set_inputs_from_f32(&mut self, reg: u16, value: f32) -> Result<(), ErrorKind> {
+        self.set_inputs_from_u32(reg, value.to_bits())
+    }
+
+    fn set_holdings_from_f32(&mut self, reg: u16, value: f32) -> Result<(), ErrorKind> {
+        self.set_holdings_from_u32(reg, value.to_bits())
+    }
+}
+
+pub(crate) fn check_range(size: usize, reg: u16, count: usize) -> Result<(), ErrorKind> {
+    match reg as usize + count <= size {
+        true => Ok(()),
+        false => Err(ErrorKind::OOBContext),
+    }
+}
+
+impl DataMemory for ModbusContext {
+    fn get_size(&self, area: MemoryArea) -> usize {
+        match area {
+            MemoryArea::Markers => 0,
+            _ => CONTEXT_SIZE,
+        }
+    }
+
+    fn get_coil(&self, reg: u16) -> Result<bool, ErrorKind> { ModbusContext::get_coil(self, reg) }
+    fn get_discrete(&self, reg: u16) -> Result<bool, ErrorKind> { ModbusContext::get_discrete(self, reg) }
+    fn get_input(&self, reg: u16) -> Result<u16, ErrorKind> { ModbusContext::get_input(self, reg) }
+    fn get_holding(&self, reg: u16) -> Result<u16, ErrorKind> { ModbusContext::get_holding(self, reg) }
+
+    fn set_coil(&mut self, reg: u16, value: bool) -> Result<(), ErrorKind> {
+        ModbusContext::set_coil(self, reg, value)
+    }
+
+    fn set_discrete(&mut self, reg: u16, value: bool) -> Result<(), ErrorKind> {
+        ModbusContext::set_discrete(self, reg, value)
+    }
+
+    fn set_input(&mut self, reg: u16, value: u16) -> Result<(), ErrorKind> {
+        ModbusContext::set_input(self, reg, value)
+    }
+
+    fn set_holding(&mut self, reg: u16, value: u16) -> Result<(), ErrorKind> {
+        ModbusContext::set_holding(self, reg, value)
+    }
+
+    fn get_coils_bulk(&self, reg: u16, count: u16, result: &mut Vec<bool>) -> Result<(), ErrorKind> {
+        ModbusContext::get_coils_bulk(self, reg, count, result)
+    }
+
+    fn get_discretes_bulk(&self, reg: u16, count: u16, result: &mut Vec<bool>) -> Result<(), ErrorKind> {
+        ModbusContext::get_discretes_bulk(self, reg, count, result)
+    }
+
+    fn get_inputs_bulk(&self, reg: u16, count: u16, result: &mut Vec<u16>) -> Result<(), ErrorKind> {
+        ModbusContext::get_inputs_bulk(self, reg, count, result)
+    }
+
+    fn get_holdings_bulk(&self, reg: u16, count: u16, result: &mut Vec<u16>) -> Result<(), ErrorKind> {
+        ModbusContext::get_holdings_bulk(self, reg, count, result)
+    }
+
+    fn set_coils_bulk(&mut self, reg: u16, values: &[bool]) -> Result<(), ErrorKind> {
+        ModbusContext::set_coils_bulk(self, reg, values)
+    }
+
+    fn set_discretes_bulk(&mut self, reg: u16, values: &[bool]) -> Result<(), ErrorKind> {
+        ModbusContext::set_discretes_bulk(self, reg, values)
+    }
+
+    fn set_inputs_bulk(&mut self, reg: u16, values: &[u16]) -> Result<(), ErrorKind> {
+        ModbusContext::set_inputs_bulk(self, reg, values)
+    }
+
+    fn set_holdings_bulk(&mut self, reg: u16, values: &[u16]) -> Result<(), ErrorKind> {
+        ModbusContext::set_holdings_bulk(self, reg, values)
+    }
+}
@@ -0,0 +1,21 @@
+use std::{fmt, error};
+use super::data_memory::MemoryArea;
+
+#[derive(Debug, PartialEq)]
+pub enum ForceErr {
+    UnknownTag(String),
+    WrongType(MemoryArea, u16),
+    OutOfRange(MemoryArea, u16),
+}
+
+impl fmt::Display for ForceErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownTag(tag) => write!(f, "unknown tag {}", tag),
+            Self::WrongType(area, reg) => write!(f, "value type does not match {:?} {}", area, reg),
+            Self::OutOfRange(area, reg) => write!(f, "{:?} {} out of range", area, reg),
+        }
+    }
+}
+
+impl error::Error for ForceErr {}
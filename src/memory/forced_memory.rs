@@ -0,0 +1,134 @@
+use rmodbus::ErrorKind;
+use super::data_memory::{DataMemory, MemoryArea};
+use super::forces::Forces;
+
+pub struct ForcedMemory<M: DataMemory> {
+    memory: M,
+    forces: Forces,
+    indicator: Option<u16>,
+}
+
+impl<M: DataMemory> ForcedMemory<M> {
+    pub fn new(memory: M, forces: Forces) -> Self {
+        Self { memory, forces, indicator: None }
+    }
+
+    pub fn indicator(mut self, coil: u16) -> Self {
+        self.indicator = Some(coil);
+        self
+    }
+
+    pub fn get_forces(&self) -> &Forces { &self.forces }
+
+    pub fn get_memory(&self) -> &M { &self.memory }
+
+    pub fn get_memory_mut(&mut self) -> &mut M { &mut self.memory }
+}
+
+impl<M: DataMemory> DataMemory for ForcedMemory<M> {
+    fn get_size(&self, area: MemoryArea) -> usize {
+        self.memory.get_size(area)
+    }
+
+    fn get_coil(&self, reg: u16) -> Result<bool, ErrorKind> {
+        if self.indicator == Some(reg) {
+            return Ok(self.forces.is_active());
+        }
+
+        match self.forces.get(MemoryArea::Coils, reg) {
+            Some(v) => Ok(v != 0),
+            None => self.memory.get_coil(reg),
+        }
+    }
+
+    fn get_discrete(&self, reg: u16) -> Result<bool, ErrorKind> {
+        match self.forces.get(MemoryArea::Discretes, reg) {
+            Some(v) => Ok(v != 0),
+            None => self.memory.get_discrete(reg),
+        }
+    }
+
+    fn get_input(&self, reg: u16) -> Result<u16, ErrorKind> {
+        match self.forces.get(MemoryArea::Inputs, reg) {
+            Some(v) => Ok(v),
+            None => self.memory.get_input(reg),
+        }
+    }
+
+    fn get_holding(&self, reg: u16) -> Result<u16, ErrorKind> {
+        match self.forces.get(MemoryArea::Holdings, reg) {
+            Some(v) => Ok(v),
+            None => self.memory.get_holding(reg),
+        }
+    }
+
+    fn get_marker(&self, reg: u16) -> Result<u16, ErrorKind> {
+        match self.forces.get(MemoryArea::Markers, reg) {
+            Some(v) => Ok(v),
+            None => self.memory.get_marker(reg),
+        }
+    }
+
+    fn set_coil(&mut self, reg: u16, value: bool) -> Result<(), ErrorKind> {
+        self.memory.set_coil(reg, value)
+    }
+
+    fn set_discrete(&mut self, reg: u16, value: bool) -> Result<(), ErrorKind> {
+        self.memory.set_discrete(reg, value)
+    }
+
+    fn set_input(&mut self, reg: u16, value: u16) -> Result<(), ErrorKind> {
+        self.memory.set_input(reg, value)
+    }
+
+    fn set_holding(&mut self, reg: u16, value: u16) -> Result<(), ErrorKind> {
+        self.memory.set_holding(reg, value)
+    }
+
+    fn set_marker(&mut self, reg: u16, value: u16) -> Result<(), ErrorKind> {
+        self.memory.set_marker(reg, value)
+    }
+}
+
+#[test]
+fn test_forced_memory() {
+    use crate::diagnostics::AuditLog;
+    use super::plc_memory::PlcMemory;
+    use super::forces::Value;
+    use super::force_error::ForceErr;
+
+    let log = AuditLog::new(10);
+    let forces = Forces::new().audit_log(log.clone());
+    let mut memory = ForcedMemory::new(PlcMemory::new(100, 100, 100, 100, 10), forces.clone())
+        .indicator(99);
+
+    forces.tag("speed", MemoryArea::Holdings, 10);
+
+    assert!(!memory.get_coil(99).unwrap());
+
+    memory.set_discrete(1, false).unwrap();
+    forces.force(MemoryArea::Discretes, 1, Value::Bool(true)).unwrap();
+    forces.force_tag("speed", Value::F32(12.5)).unwrap();
+
+    assert!(memory.get_coil(99).unwrap());
+    assert!(memory.get_discrete(1).unwrap());
+    assert_eq!(memory.get_holdings_as_f32(10).unwrap(), 12.5);
+
+    memory.set_holding(10, 0).unwrap();
+    assert_eq!(memory.get_holdings_as_f32(10).unwrap(), 12.5);
+
+    assert_eq!(forces.force(MemoryArea::Holdings, 1, Value::Bool(true)), Err(ForceErr::WrongType(MemoryArea::Holdings, 1)));
+    assert_eq!(forces.force_tag("level", Value::U16(1)), Err(ForceErr::UnknownTag("level".to_string())));
+
+    assert_eq!(forces.list().len(), 2);
+    assert_eq!(forces.list()[1].get_tag(), Some("speed"));
+
+    assert!(forces.unforce(MemoryArea::Holdings, 11));
+    assert_eq!(memory.get_holding(10).unwrap(), 0);
+
+    forces.clear();
+    assert!(!memory.get_coil(99).unwrap());
+    assert!(!memory.get_discrete(1).unwrap());
+
+    assert_eq!(log.len(), 4);
+}
@@ -0,0 +1,261 @@
+use std::{rc::Rc, cell::RefCell, collections::HashMap};
+use rmodbus::ErrorKind;
+use serde::{Serialize, Deserialize};
+use crate::diagnostics::AuditLog;
+use super::data_memory::{DataMemory, MemoryArea};
+use super::force_error::ForceErr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueKind {
+    Bool,
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+}
+
+impl ValueKind {
+    pub fn get_count(self) -> u16 {
+        match self {
+            Self::Bool | Self::U16 | Self::I16 => 1,
+            Self::U32 | Self::I32 | Self::F32 => 2,
+        }
+    }
+
+    pub fn read(self, memory: &dyn DataMemory, area: MemoryArea, reg: u16) -> Result<Value, ErrorKind> {
+        let mut regs = Vec::with_capacity(self.get_count() as usize);
+        for i in 0..self.get_count() {
+            regs.push(memory.get_word(area, reg.checked_add(i).ok_or(ErrorKind::OOBContext)?)?);
+        }
+
+        Ok(Value::from_regs(self, &regs))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    U16(u16),
+    I16(i16),
+    U32(u32),
+    I32(i32),
+    F32(f32),
+}
+
+impl Value {
+    pub fn get_kind(&self) -> ValueKind {
+        match self {
+            Self::Bool(_) => ValueKind::Bool,
+            Self::U16(_) => ValueKind::U16,
+            Self::I16(_) => ValueKind::I16,
+            Self::U32(_) => ValueKind::U32,
+            Self::I32(_) => ValueKind::I32,
+            Self::F32(_) => ValueKind::F32,
+        }
+    }
+
+    pub fn to_regs(self) -> Vec<u16> {
+        let from_u32 = |v: u32| vec![(v >> 16) as u16, v as u16];
+
+        match self {
+            Self::Bool(v) => vec![v as u16],
+            Self::U16(v) => vec![v],
+            Self::I16(v) => vec![v as u16],
+            Self::U32(v) => from_u32(v),
+            Self::I32(v) => from_u32(v as u32),
+            Self::F32(v) => from_u32(v.to_bits()),
+        }
+    }
+
+    pub fn from_regs(kind: ValueKind, regs: &[u16]) -> Self {
+        let reg = |i: usize| regs.get(i).copied().unwrap_or(0);
+        let to_u32 = || (reg(0) as u32) << 16 | reg(1) as u32;
+
+        match kind {
+            ValueKind::Bool => Self::Bool(reg(0) != 0),
+            ValueKind::U16 => Self::U16(reg(0)),
+            ValueKind::I16 => Self::I16(reg(0) as i16),
+            ValueKind::U32 => Self::U32(to_u32()),
+            ValueKind::I32 => Self::I32(to_u32() as i32),
+            ValueKind::F32 => Self::F32(f32::from_bits(to_u32())),
+        }
+    }
+
+    pub fn to_f64(self) -> f64 {
+        match self {
+            Self::Bool(v) => v as u8 as f64,
+            Self::U16(v) => v as f64,
+            Self::I16(v) => v as f64,
+            Self::U32(v) => v as f64,
+            Self::I32(v) => v as f64,
+            Self::F32(v) => v as f64,
+        }
+    }
+
+    pub fn from_f64(kind: ValueKind, value: f64) -> Self {
+        match kind {
+            ValueKind::Bool => Self::Bool(value != 0.0),
+            ValueKind::U16 => Self::U16(value.round() as u16),
+            ValueKind::I16 => Self::I16(value.round() as i16),
+            ValueKind::U32 => Self::U32(value.round() as u32),
+            ValueKind::I32 => Self::I32(value.round() as i32),
+            ValueKind::F32 => Self::F32(value as f32),
+        }
+    }
+
+    fn fits(&self, area: MemoryArea) -> bool {
+        let is_bit = matches!(area, MemoryArea::Coils | MemoryArea::Discretes);
+        is_bit == (self.get_kind() == ValueKind::Bool)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Force {
+    area: MemoryArea,
+    reg: u16,
+    value: Value,
+    tag: Option<String>,
+}
+
+impl Force {
+    pub fn get_area(&self) -> MemoryArea { self.area }
+    pub fn get_reg(&self) -> u16 { self.reg }
+    pub fn get_value(&self) -> Value { self.value }
+    pub fn get_tag(&self) -> Option<&str> { self.tag.as_deref() }
+
+    fn covers(&self, area: MemoryArea, reg: u16, count: u16) -> bool {
+        let start = self.reg as u32;
+        let end = start + self.value.get_kind().get_count() as u32;
+        let other_start = reg as u32;
+        let other_end = other_start + count as u32;
+
+        self.area == area && start < other_end && other_start < end
+    }
+}
+
+#[derive(Default)]
+struct ForceTable {
+    forces: Vec<Force>,
+    cells: HashMap<(MemoryArea, u16), u16>,
+    tags: HashMap<String, (MemoryArea, u16)>,
+    audit: Option<AuditLog>,
+}
+
+impl ForceTable {
+    fn rebuild(&mut self) {
+        self.cells.clear();
+
+        for force in self.forces.iter() {
+            for (i, value) in force.value.to_regs().into_iter().enumerate() {
+                self.cells.insert((force.area, force.reg + i as u16), value);
+            }
+        }
+    }
+
+    fn audit(&self, message: String) {
+        if let Some(log) = &self.audit {
+            log.record("forces", message);
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Forces {
+    table: Rc<RefCell<ForceTable>>,
+}
+
+impl Forces {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn audit_log(self, log: AuditLog) -> Self {
+        self.table.borrow_mut().audit = Some(log);
+        self
+    }
+
+    pub fn tag(&self, name: &str, area: MemoryArea, reg: u16) {
+        self.table.borrow_mut().tags.insert(name.to_string(), (area, reg));
+    }
+
+    pub fn get_tag(&self, name: &str) -> Option<(MemoryArea, u16)> {
+        self.table.borrow().tags.get(name).copied()
+    }
+
+    pub fn force(&self, area: MemoryArea, reg: u16, value: Value) -> Result<(), ForceErr> {
+        self.insert(area, reg, value, None)
+    }
+
+    pub fn force_tag(&self, name: &str, value: Value) -> Result<(), ForceErr> {
+        let (area, reg) = self.get_tag(name).ok_or_else(|| ForceErr::UnknownTag(name.to_string()))?;
+        self.insert(area, reg, value, Some(name.to_string()))
+    }
+
+    pub fn unforce(&self, area: MemoryArea, reg: u16) -> bool {
+        let mut table = self.table.borrow_mut();
+        let len = table.forces.len();
+
+        table.forces.retain(|f| !f.covers(area, reg, 1));
+
+        let removed = len != table.forces.len();
+        if removed {
+            table.rebuild();
+            table.audit(format!("unforce {:?} {}", area, reg));
+        }
+
+        removed
+    }
+
+    pub fn unforce_tag(&self, name: &str) -> Result<bool, ForceErr> {
+        let (area, reg) = self.get_tag(name).ok_or_else(|| ForceErr::UnknownTag(name.to_string()))?;
+        Ok(self.unforce(area, reg))
+    }
+
+    pub fn clear(&self) {
+        let mut table = self.table.borrow_mut();
+
+        if !table.forces.is_empty() {
+            table.audit(format!("clear {} forces", table.forces.len()));
+        }
+
+        table.forces.clear();
+        table.rebuild();
+    }
+
+    pub fn list(&self) -> Vec<Force> {
+        self.table.borrow().forces.clone()
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.table.borrow().forces.is_empty()
+    }
+
+    pub fn get(&self, area: MemoryArea, reg: u16) -> Option<u16> {
+        self.table.borrow().cells.get(&(area, reg)).copied()
+    }
+
+    fn insert(&self, area: MemoryArea, reg: u16, value: Value, tag: Option<String>) -> Result<(), ForceErr> {
+        if !value.fits(area) {
+            return Err(ForceErr::WrongType(area, reg));
+        }
+
+        if reg.checked_add(value.get_kind().get_count() - 1).is_none() {
+            return Err(ForceErr::OutOfRange(area, reg));
+        }
+
+        let mut table = self.table.borrow_mut();
+        let count = value.get_kind().get_count();
+
+        table.forces.retain(|f| !f.covers(area, reg, count));
+        table.audit(match &tag {
+            Some(t) => format!("force {} ({:?} {}) = {:?}", t, area, reg, value),
+            None => format!("force {:?} {} = {:?}", area, reg, value),
+        });
+        table.forces.push(Force { area, reg, value, tag });
+        table.rebuild();
+
+        Ok(())
+    }
+}
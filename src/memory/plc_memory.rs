@@ -0,0 +1,103 @@
+use rmodbus::ErrorKind;
+use super::data_memory::{DataMemory, MemoryArea};
+
+pub struct PlcMemory {
+    coils: Vec<bool>,
+    discretes: Vec<bool>,
+    inputs: Vec<u16>,
+    holdings: Vec<u16>,
+    markers: Vec<u16>,
+}
+
+impl PlcMemory {
+    pub fn new(coils: usize, discretes: usize, inputs: usize, holdings: usize, markers: usize) -> Self {
+        let max = u16::MAX as usize + 1;
+
+        Self {
+            coils: vec![false; coils.min(max)],
+            discretes: vec![false; discretes.min(max)],
+            inputs: vec![0; inputs.min(max)],
+            holdings: vec![0; holdings.min(max)],
+            markers: vec![0; markers.min(max)],
+        }
+    }
+
+    pub fn clear_all(&mut self) {
+        self.coils.fill(false);
+        self.discretes.fill(false);
+        self.inputs.fill(0);
+        self.holdings.fill(0);
+        self.markers.fill(0);
+    }
+}
+
+impl Default for PlcMemory {
+    fn default() -> Self {
+        let max = u16::MAX as usize + 1;
+        Self::new(max, max, max, max, max)
+    }
+}
+
+fn get<T: Copy>(area: &[T], reg: u16) -> Result<T, ErrorKind> {
+    area.get(reg as usize).copied().ok_or(ErrorKind::OOBContext)
+}
+
+fn set<T>(area: &mut [T], reg: u16, value: T) -> Result<(), ErrorKind> {
+    match area.get_mut(reg as usize) {
+        Some(cell) => {
+            *cell = value;
+            Ok(())
+        },
+        None => Err(ErrorKind::OOBContext),
+    }
+}
+
+impl DataMemory for PlcMemory {
+    fn get_size(&self, area: MemoryArea) -> usize {
+        match area {
+            MemoryArea::Coils => self.coils.len(),
+            MemoryArea::Discretes => self.discretes.len(),
+            MemoryArea::Inputs => self.inputs.len(),
+            MemoryArea::Holdings => self.holdings.len(),
+            MemoryArea::Markers => self.markers.len(),
+        }
+    }
+
+    fn get_coil(&self, reg: u16) -> Result<bool, ErrorKind> { get(&self.coils, reg) }
+    fn get_discrete(&self, reg: u16) -> Result<bool, ErrorKind> { get(&self.discretes, reg) }
+    fn get_input(&self, reg: u16) -> Result<u16, ErrorKind> { get(&self.inputs, reg) }
+    fn get_holding(&self, reg: u16) -> Result<u16, ErrorKind> { get(&self.holdings, reg) }
+    fn get_marker(&self, reg: u16) -> Result<u16, ErrorKind> { get(&self.markers, reg) }
+
+    fn set_coil(&mut self, reg: u16, value: bool) -> Result<(), ErrorKind> { set(&mut self.coils, reg, value) }
+    fn set_discrete(&mut self, reg: u16, value: bool) -> Result<(), ErrorKind> { set(&mut self.discretes, reg, value) }
+    fn set_input(&mut self, reg: u16, value: u16) -> Result<(), ErrorKind> { set(&mut self.inputs, reg, value) }
+    fn set_holding(&mut self, reg: u16, value: u16) -> Result<(), ErrorKind> { set(&mut self.holdings, reg, value) }
+    fn set_marker(&mut self, reg: u16, value: u16) -> Result<(), ErrorKind> { set(&mut self.markers, reg, value) }
+}
+
+#[test]
+fn test_plc_memory() {
+    let mut memory = PlcMemory::new(16, 16, 20_000, 20_000, 100);
+
+    assert_eq!(memory.get_size(MemoryArea::Holdings), 20_000);
+    assert_eq!(memory.get_size(MemoryArea::Markers), 100);
+
+    memory.set_holding(19_999, 7).unwrap();
+    assert_eq!(memory.get_holding(19_999).unwrap(), 7);
+    assert_eq!(memory.get_holding(20_000), Err(ErrorKind::OOBContext));
+
+    assert_eq!(memory.set_coils_bulk(14, &[true, true, true]), Err(ErrorKind::OOBContext));
+    assert!(!memory.get_coil(14).unwrap());
+
+    memory.set_holdings_from_f32(100, 1.5).unwrap();
+    assert_eq!(memory.get_holdings_as_f32(100).unwrap(), 1.5);
+
+    memory.set_marker(99, 42).unwrap();
+    assert_eq!(memory.get_marker(99).unwrap(), 42);
+    assert_eq!(memory.get_marker(100), Err(ErrorKind::OOBContext));
+
+    let mut inputs = Vec::new();
+    memory.get_inputs_bulk(10, 3, &mut inputs).unwrap();
+    assert_eq!(inputs, [0, 0, 0]);
+}
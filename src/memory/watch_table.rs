@@ -0,0 +1,58 @@
+use rmodbus::ErrorKind;
+use super::data_memory::{DataMemory, MemoryArea};
+use super::forces::{Forces, Value, ValueKind};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WatchEntry {
+    name: String,
+    area: MemoryArea,
+    reg: u16,
+    kind: ValueKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WatchValue {
+    name: String,
+    value: Value,
+    forced: bool,
+}
+
+impl WatchValue {
+    pub fn get_name(&self) -> &str { &self.name }
+    pub fn get_value(&self) -> Value { self.value }
+    pub fn is_forced(&self) -> bool { self.forced }
+}
+
+#[derive(Default)]
+pub struct WatchTable {
+    entries: Vec<WatchEntry>,
+}
+
+impl WatchTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(mut self, name: &str, area: MemoryArea, reg: u16, kind: ValueKind) -> Self {
+        self.entries.push(WatchEntry { name: name.to_string(), area, reg, kind });
+        self
+    }
+
+    pub fn read(&self, memory: &dyn DataMemory, forces: Option<&Forces>) -> Result<Vec<WatchValue>, ErrorKind> {
+        let mut result = Vec::with_capacity(self.entries.len());
+
+        for entry in self.entries.iter() {
+            let forced = forces.is_some_and(|f| {
+                (0..entry.kind.get_count()).any(|i| f.get(entry.area, entry.reg.wrapping_add(i)).is_some())
+            });
+
+            result.push(WatchValue {
+                name: entry.name.clone(),
+                value: entry.kind.read(memory, entry.area, entry.reg)?,
+                forced,
+            });
+        }
+
+        Ok(result)
+    }
+}
@@ -225,6 +225,7 @@ fn test_set_bit() {
 }
 
 #[test]
+#[allow(clippy::unnecessary_cast)]
 fn test_to_reg() {
 
     use rmodbus::server::context::ModbusContext;
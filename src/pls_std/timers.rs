@@ -191,6 +191,7 @@ fn test_ton() {
 
 
 #[test]
+#[allow(clippy::if_same_then_else, clippy::needless_bool_assign)]
 fn test_tof() {
     let duration = Duration::from_millis(20);
     let mut timer = Tof::new(duration);
@@ -244,6 +245,7 @@ fn test_tof() {
 }
 
 #[test]
+#[allow(clippy::manual_is_multiple_of)]
 fn test_tp() {
     let duration = Duration::from_millis(20);
     let mut timer = Tp::new(duration);
@@ -1,3 +1,4 @@
 pub mod modbus;
+pub mod recipe;
 mod conts_wrapper;
-pub use conts_wrapper::ConstWrapper;
\ No newline at end of file
+pub use conts_wrapper::ConstWrapper;
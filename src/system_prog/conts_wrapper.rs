@@ -1,7 +1,7 @@
 use crate::task::{MutProgram, ConstProgram};
 use std::cell::RefCell;
 use std::{result, error};
-use rmodbus::server::context::ModbusContext;
+use crate::memory::DataMemory;
 
 pub struct ConstWrapper<T: MutProgram> {
     prog: RefCell<T>,
@@ -14,7 +14,7 @@ impl<T: MutProgram> ConstWrapper<T> {
 }
 
 impl<T: MutProgram> ConstProgram for ConstWrapper<T> {
-    fn run(&self, context: &mut ModbusContext) -> result::Result<(), Box<dyn error::Error>> {
+    fn run(&self, context: &mut dyn DataMemory) -> result::Result<(), Box<dyn error::Error>> {
         
         self.prog.borrow_mut().run(context)?;
         Ok(())
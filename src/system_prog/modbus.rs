@@ -7,16 +7,59 @@ mod modbus_tcp_master;
 mod modbus_master_actions;
 mod timeaut_heandler;
 mod modbus_rtu_master;
+mod modbus_access;
+mod modbus_write_event;
+mod modbus_process;
+mod modbus_connection;
+mod modbus_rtu_timing;
+mod modbus_serial_link;
+mod modbus_rtu_bus;
+mod modbus_quality;
+mod modbus_poll_plan;
+mod modbus_poll_driver;
+mod modbus_mapping;
+mod modbus_mapping_error;
+mod modbus_pdu;
+mod modbus_mock;
+mod modbus_allow_list;
+mod modbus_udp_transport;
+mod modbus_udp_master;
+mod modbus_udp_slave;
+mod modbus_tcp_peer;
+mod modbus_gateway;
+mod modbus_unit;
+mod modbus_counters;
+mod modbus_rtu_frame;
+mod modbus_diagnostics;
+mod modbus_tls;
+mod modbus_tls_error;
 
 pub use modbus_tcp_slave::ModbusTcpSlave;
 pub use modbus_rtu_slave::ModbusRtuSlave;
 pub use modbus_master::ModbusMaster;
 pub use modbus_slave::ModbusSlave;
-pub use modbus_error::ModbusErr;
+pub use modbus_error::{ModbusErr, ExceptionCode};
 pub use modbus_tcp_master::ModbusTcpMaster;
-pub use modbus_master_actions::Acton;
+pub use modbus_master_actions::{Acton, Quality};
 pub use timeaut_heandler::TimeautHeandler;
 pub use modbus_rtu_master::ModbusRtuMaster;
+pub use modbus_access::{Access, AccessArea, AccessRule};
+pub use modbus_write_event::{ModbusClient, WriteEvent, WriteHook, WriteQueue};
+pub use modbus_connection::{ConnectionState, ConnectionStatus};
+pub use modbus_rtu_timing::RtuTiming;
+pub use modbus_rtu_bus::{ModbusRtuBus, BusDevice, DeviceHealth};
+pub use modbus_quality::{BlockStatus, QualityTable};
+pub use modbus_mapping::{Mapping, Direction, Trigger, Conversion, RemoteRange, LocalRange};
+pub use modbus_mapping_error::MappingErr;
+pub use modbus_mock::{MockTransport, SimDevice, Fault};
+pub use modbus_allow_list::{AllowList, ClientClass, IpNet};
+pub use modbus_udp_master::ModbusUdpMaster;
+pub use modbus_udp_slave::ModbusUdpSlave;
+pub use modbus_gateway::ModbusGateway;
+pub use modbus_unit::{UnitContext, UnitWindow};
+pub use modbus_counters::BusCounters;
+pub use modbus_tls::{TlsConfig, MODBUS_TLS_PORT};
+pub use modbus_tls_error::TlsErr;
 
 pub mod serial_settings {
     pub use serial::{BaudRate, Parity, CharSize, StopBits, FlowControl, PortSettings};
@@ -0,0 +1,165 @@
+use crate::memory::DataMemory;
+use rmodbus::consts::{
+    MODBUS_SET_COIL, MODBUS_SET_HOLDING, MODBUS_SET_COILS_BULK, MODBUS_SET_HOLDINGS_BULK,
+    MODBUS_ERROR_ILLEGAL_DATA_ADDRESS, MODBUS_ERROR_ILLEGAL_DATA_VALUE,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Access {
+    ReadOnly,
+    ReadWrite,
+    Limited(u16, u16),
+    Unlock(u16),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AccessArea {
+    Coils,
+    Holdings,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct AccessRule {
+    area: AccessArea,
+    offset: u16,
+    count: u16,
+    access: Access,
+}
+
+impl AccessRule {
+    pub fn coils(offset: u16, count: u16, access: Access) -> Self {
+        Self { area: AccessArea::Coils, offset, count, access }
+    }
+
+    pub fn holdings(offset: u16, count: u16, access: Access) -> Self {
+        Self { area: AccessArea::Holdings, offset, count, access }
+    }
+
+    pub fn get_area(&self) -> AccessArea { self.area }
+    pub fn get_offset(&self) -> u16 { self.offset }
+    pub fn get_count(&self) -> u16 { self.count }
+    pub fn get_access(&self) -> Access { self.access }
+
+    fn contains(&self, area: AccessArea, reg: u16) -> bool {
+        self.area == area
+            && reg >= self.offset
+            && (reg as u32) < self.offset as u32 + self.count as u32
+    }
+
+    fn check(&self, context: &dyn DataMemory, value: u16) -> Result<(), u8> {
+        match self.access {
+            Access::ReadWrite => Ok(()),
+            Access::ReadOnly => Err(MODBUS_ERROR_ILLEGAL_DATA_ADDRESS),
+            Access::Limited(min, max) => match self.area {
+                AccessArea::Holdings if value < min || value > max => Err(MODBUS_ERROR_ILLEGAL_DATA_VALUE),
+                _ => Ok(()),
+            },
+            Access::Unlock(coil) => match context.get_coil(coil) {
+                Ok(true) => Ok(()),
+                _ => Err(MODBUS_ERROR_ILLEGAL_DATA_ADDRESS),
+            },
+        }
+    }
+}
+
+pub(crate) fn check_write(
+    rules: &[AccessRule],
+    context: &dyn DataMemory,
+    func: u8,
+    reg: u16,
+    count: u16,
+    frame: &[u8],
+) -> Result<(), u8> {
+
+    if rules.is_empty() {
+        return Ok(());
+    }
+
+    let (area, values) = match func {
+        MODBUS_SET_COIL => (AccessArea::Coils, vec![(get_u16(frame, 4)? == 0xff00) as u16]),
+        MODBUS_SET_HOLDING => (AccessArea::Holdings, vec![get_u16(frame, 4)?]),
+        MODBUS_SET_COILS_BULK => {
+            let mut values = Vec::with_capacity(count as usize);
+            for i in 0..count as usize {
+                let byte = frame.get(7 + i / 8).ok_or(MODBUS_ERROR_ILLEGAL_DATA_VALUE)?;
+                values.push(((byte >> (i % 8)) & 1) as u16);
+            }
+            (AccessArea::Coils, values)
+        },
+        MODBUS_SET_HOLDINGS_BULK => {
+            let mut values = Vec::with_capacity(count as usize);
+            for i in 0..count as usize {
+                values.push(get_u16(frame, 7 + i * 2)?);
+            }
+            (AccessArea::Holdings, values)
+        },
+        _ => return Ok(()),
+    };
+
+    for (i, value) in values.into_iter().enumerate() {
+        let addr = reg.wrapping_add(i as u16);
+
+        if let Some(rule) = rules.iter().find(|r| r.contains(area, addr)) {
+            rule.check(context, value)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn get_u16(frame: &[u8], pos: usize) -> Result<u16, u8> {
+    match (frame.get(pos), frame.get(pos + 1)) {
+        (Some(hi), Some(lo)) => Ok(u16::from_be_bytes([*hi, *lo])),
+        _ => Err(MODBUS_ERROR_ILLEGAL_DATA_VALUE),
+    }
+}
+
+#[test]
+fn test_check_write() {
+    use rmodbus::server::context::ModbusContext;
+    let mut context = ModbusContext::new();
+    let rules = [
+        AccessRule::holdings(0, 10, Access::ReadOnly),
+        AccessRule::holdings(10, 10, Access::Limited(5, 50)),
+        AccessRule::holdings(20, 10, Access::Unlock(100)),
+        AccessRule::coils(0, 8, Access::ReadOnly),
+    ];
+
+    // unit, func, reg, value
+    let set_holding = |reg: u16, value: u16| {
+        let mut frame = vec![1, MODBUS_SET_HOLDING];
+        frame.extend(reg.to_be_bytes());
+        frame.extend(value.to_be_bytes());
+        frame
+    };
+
+    let frame = set_holding(3, 1);
+    assert_eq!(check_write(&rules, &context, MODBUS_SET_HOLDING, 3, 1, &frame), Err(MODBUS_ERROR_ILLEGAL_DATA_ADDRESS));
+
+    let frame = set_holding(12, 51);
+    assert_eq!(check_write(&rules, &context, MODBUS_SET_HOLDING, 12, 1, &frame), Err(MODBUS_ERROR_ILLEGAL_DATA_VALUE));
+
+    let frame = set_holding(12, 50);
+    assert_eq!(check_write(&rules, &context, MODBUS_SET_HOLDING, 12, 1, &frame), Ok(()));
+
+    let frame = set_holding(25, 1000);
+    assert_eq!(check_write(&rules, &context, MODBUS_SET_HOLDING, 25, 1, &frame), Err(MODBUS_ERROR_ILLEGAL_DATA_ADDRESS));
+    context.set_coil(100, true).unwrap();
+    assert_eq!(check_write(&rules, &context, MODBUS_SET_HOLDING, 25, 1, &frame), Ok(()));
+
+    let frame = set_holding(40, 1);
+    assert_eq!(check_write(&rules, &context, MODBUS_SET_HOLDING, 40, 1, &frame), Ok(()));
+
+    // unit, func, reg, count, bytes, values
+    let frame = [1, MODBUS_SET_HOLDINGS_BULK, 0, 8, 0, 3, 6, 0, 1, 0, 2, 0, 3];
+    assert_eq!(check_write(&rules, &context, MODBUS_SET_HOLDINGS_BULK, 8, 3, &frame), Err(MODBUS_ERROR_ILLEGAL_DATA_ADDRESS));
+
+    let frame = [1, MODBUS_SET_HOLDINGS_BULK, 0, 10, 0, 2, 4, 0, 5, 0, 2];
+    assert_eq!(check_write(&rules, &context, MODBUS_SET_HOLDINGS_BULK, 10, 2, &frame), Err(MODBUS_ERROR_ILLEGAL_DATA_VALUE));
+
+    let frame = [1, MODBUS_SET_COILS_BULK, 0, 6, 0, 4, 1, 0b1010];
+    assert_eq!(check_write(&rules, &context, MODBUS_SET_COILS_BULK, 6, 4, &frame), Err(MODBUS_ERROR_ILLEGAL_DATA_ADDRESS));
+
+    let frame = [1, MODBUS_SET_COIL, 0, 8, 0xff, 0];
+    assert_eq!(check_write(&rules, &context, MODBUS_SET_COIL, 8, 1, &frame), Ok(()));
+}
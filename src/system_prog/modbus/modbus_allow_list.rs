@@ -0,0 +1,95 @@
+use std::net::IpAddr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClientClass {
+    ReadOnly,
+    ReadWrite,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNet {
+    pub fn new(addr: IpAddr, prefix: u8) -> Self {
+        let addr = addr.to_canonical();
+        let max = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+
+        Self { addr, prefix: prefix.min(max) }
+    }
+
+    pub fn parse(net: &str) -> Option<Self> {
+        match net.split_once('/') {
+            Some((addr, prefix)) => Some(Self::new(addr.trim().parse().ok()?, prefix.trim().parse().ok()?)),
+            None => Some(Self::new(net.trim().parse().ok()?, 128)),
+        }
+    }
+
+    pub fn get_addr(&self) -> IpAddr { self.addr }
+    pub fn get_prefix(&self) -> u8 { self.prefix }
+
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        match (self.addr, addr.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(addr) & mask
+            },
+            (IpAddr::V6(net), IpAddr::V6(addr)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(addr) & mask
+            },
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AllowList {
+    entries: Vec<(IpNet, ClientClass)>,
+}
+
+impl AllowList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn read_only(mut self, net: IpNet) -> Self {
+        self.entries.push((net, ClientClass::ReadOnly));
+        self
+    }
+
+    pub fn read_write(mut self, net: IpNet) -> Self {
+        self.entries.push((net, ClientClass::ReadWrite));
+        self
+    }
+
+    pub fn classify(&self, addr: IpAddr) -> Option<ClientClass> {
+        self.entries.iter()
+            .filter(|(net, _)| net.contains(addr))
+            .max_by_key(|(net, _)| net.prefix)
+            .map(|(_, class)| *class)
+    }
+}
+
+#[test]
+fn test_allow_list() {
+    let list = AllowList::new()
+        .read_only(IpNet::parse("10.0.0.0/8").unwrap())
+        .read_write(IpNet::parse("10.1.2.0/24").unwrap())
+        .read_write(IpNet::parse("::1").unwrap());
+
+    assert_eq!(list.classify("10.9.9.9".parse().unwrap()), Some(ClientClass::ReadOnly));
+    assert_eq!(list.classify("10.1.2.77".parse().unwrap()), Some(ClientClass::ReadWrite));
+    assert_eq!(list.classify("::ffff:10.1.2.3".parse().unwrap()), Some(ClientClass::ReadWrite));
+    assert_eq!(list.classify("192.168.0.1".parse().unwrap()), None);
+    assert_eq!(list.classify("::1".parse().unwrap()), Some(ClientClass::ReadWrite));
+
+    assert!(IpNet::parse("0.0.0.0/0").unwrap().contains("8.8.8.8".parse().unwrap()));
+    assert_eq!(IpNet::parse("127.0.0.1").unwrap().get_prefix(), 32);
+    assert!(IpNet::parse("10.0.0.0/x").is_none());
+}
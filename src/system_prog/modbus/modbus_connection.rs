@@ -0,0 +1,108 @@
+use std::time::{Duration, Instant};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConnectionState {
+    Connected,
+    Connecting,
+    Failed,
+}
+
+#[derive(Clone, Debug)]
+pub struct ConnectionStatus {
+    state: ConnectionState,
+    last_error: Option<String>,
+    reconnect_count: u32,
+}
+
+impl ConnectionStatus {
+    pub fn get_state(&self) -> ConnectionState { self.state }
+    pub fn get_last_error(&self) -> Option<&str> { self.last_error.as_deref() }
+    pub fn get_reconnect_count(&self) -> u32 { self.reconnect_count }
+    pub fn is_connected(&self) -> bool { self.state == ConnectionState::Connected }
+}
+
+pub(crate) struct Reconnect {
+    status: ConnectionStatus,
+    min_backoff: Duration,
+    max_backoff: Duration,
+    backoff: Duration,
+    next_attempt: Instant,
+    was_connected: bool,
+}
+
+impl Reconnect {
+    pub(crate) fn new(min_backoff: Duration, max_backoff: Duration) -> Self {
+        let status = ConnectionStatus {
+            state: ConnectionState::Connecting,
+            last_error: None,
+            reconnect_count: 0,
+        };
+
+        Self {
+            status,
+            min_backoff,
+            max_backoff,
+            backoff: min_backoff,
+            next_attempt: Instant::now(),
+            was_connected: false,
+        }
+    }
+
+    pub(crate) fn set_backoff(&mut self, min_backoff: Duration, max_backoff: Duration) {
+        self.min_backoff = min_backoff;
+        self.max_backoff = max_backoff.max(min_backoff);
+        self.backoff = min_backoff;
+    }
+
+    pub(crate) fn get_status(&self) -> &ConnectionStatus { &self.status }
+
+    pub(crate) fn can_attempt(&self) -> bool {
+        self.next_attempt <= Instant::now()
+    }
+
+    pub(crate) fn connecting(&mut self) {
+        self.status.state = ConnectionState::Connecting;
+    }
+
+    pub(crate) fn connected(&mut self) {
+        if self.was_connected {
+            self.status.reconnect_count += 1;
+        }
+
+        self.was_connected = true;
+        self.status.state = ConnectionState::Connected;
+        self.backoff = self.min_backoff;
+    }
+
+    pub(crate) fn failed<E: fmt::Display>(&mut self, err: &E) {
+        self.status.state = ConnectionState::Failed;
+        self.status.last_error = Some(err.to_string());
+        self.next_attempt = Instant::now() + self.backoff;
+        self.backoff = (self.backoff * 2).min(self.max_backoff);
+    }
+}
+
+#[test]
+fn test_reconnect_backoff() {
+    let mut reconnect = Reconnect::new(Duration::from_millis(10), Duration::from_millis(25));
+
+    assert!(reconnect.can_attempt());
+    assert_eq!(reconnect.get_status().get_state(), ConnectionState::Connecting);
+
+    reconnect.failed(&"refused");
+    assert!(!reconnect.can_attempt());
+    assert_eq!(reconnect.backoff, Duration::from_millis(20));
+    reconnect.failed(&"refused");
+    assert_eq!(reconnect.backoff, Duration::from_millis(25));
+    assert_eq!(reconnect.get_status().get_last_error(), Some("refused"));
+
+    reconnect.connected();
+    assert_eq!(reconnect.get_status().get_reconnect_count(), 0);
+    assert_eq!(reconnect.backoff, Duration::from_millis(10));
+
+    reconnect.failed(&"broken pipe");
+    reconnect.connected();
+    assert_eq!(reconnect.get_status().get_reconnect_count(), 1);
+    assert!(reconnect.get_status().is_connected());
+}
@@ -0,0 +1,56 @@
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BusCounters {
+    bus_messages: u16,
+    crc_errors: u16,
+    exceptions: u16,
+    slave_messages: u16,
+    no_responses: u16,
+    overruns: u16,
+    framing_errors: u16,
+}
+
+impl BusCounters {
+    pub fn get_bus_messages(&self) -> u16 { self.bus_messages }
+    pub fn get_crc_errors(&self) -> u16 { self.crc_errors }
+    pub fn get_exceptions(&self) -> u16 { self.exceptions }
+    pub fn get_slave_messages(&self) -> u16 { self.slave_messages }
+    pub fn get_no_responses(&self) -> u16 { self.no_responses }
+    pub fn get_overruns(&self) -> u16 { self.overruns }
+    pub fn get_framing_errors(&self) -> u16 { self.framing_errors }
+
+    pub(crate) fn bus_message(&mut self) {
+        self.bus_messages = self.bus_messages.wrapping_add(1);
+    }
+
+    pub(crate) fn crc_error(&mut self) {
+        self.crc_errors = self.crc_errors.wrapping_add(1);
+    }
+
+    pub(crate) fn exception(&mut self) {
+        self.exceptions = self.exceptions.wrapping_add(1);
+    }
+
+    pub(crate) fn slave_message(&mut self) {
+        self.slave_messages = self.slave_messages.wrapping_add(1);
+    }
+
+    pub(crate) fn no_response(&mut self) {
+        self.no_responses = self.no_responses.wrapping_add(1);
+    }
+
+    pub(crate) fn overrun(&mut self) {
+        self.overruns = self.overruns.wrapping_add(1);
+    }
+
+    pub(crate) fn framing_error(&mut self) {
+        self.framing_errors = self.framing_errors.wrapping_add(1);
+    }
+
+    pub(crate) fn clear_overruns(&mut self) {
+        self.overruns = 0;
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
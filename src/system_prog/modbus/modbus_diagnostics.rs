@@ -0,0 +1,219 @@
+use std::collections::VecDeque;
+use rmodbus::consts::{MODBUS_ERROR_ILLEGAL_FUNCTION, MODBUS_ERROR_ILLEGAL_DATA_VALUE};
+use super::modbus_counters::BusCounters;
+
+pub(crate) const MODBUS_DIAGNOSTICS: u8 = 8;
+pub(crate) const MODBUS_GET_COMM_EVENT_COUNTER: u8 = 11;
+pub(crate) const MODBUS_GET_COMM_EVENT_LOG: u8 = 12;
+
+const EVENT_LOG_SIZE: usize = 64;
+
+const EVENT_RESTART: u8 = 0x00;
+const EVENT_LISTEN_ONLY: u8 = 0x04;
+const EVENT_SEND: u8 = 0x40;
+const EVENT_RECEIVE: u8 = 0x80;
+const RECEIVE_COMM_ERROR: u8 = 0x02;
+const RECEIVE_LISTEN_ONLY: u8 = 0x20;
+const RECEIVE_BROADCAST: u8 = 0x40;
+const SEND_READ_EXCEPTION: u8 = 0x01;
+const SEND_ABORT_EXCEPTION: u8 = 0x02;
+const SEND_BUSY_EXCEPTION: u8 = 0x04;
+const SEND_NAK_EXCEPTION: u8 = 0x08;
+
+#[derive(Default)]
+pub(crate) struct Diagnostics {
+    counters: BusCounters,
+    event_count: u16,
+    events: VecDeque<u8>,
+    listen_only: bool,
+}
+
+impl Diagnostics {
+    pub(crate) fn get_counters(&self) -> BusCounters { self.counters }
+    pub(crate) fn get_counters_mut(&mut self) -> &mut BusCounters { &mut self.counters }
+    pub(crate) fn get_event_count(&self) -> u16 { self.event_count }
+    pub(crate) fn get_events(&self) -> Vec<u8> { self.events.iter().copied().collect() }
+    pub(crate) fn is_listen_only(&self) -> bool { self.listen_only }
+
+    pub(crate) fn clear(&mut self) {
+        self.counters.clear();
+        self.event_count = 0;
+    }
+
+    pub(crate) fn comm_error(&mut self) {
+        self.counters.crc_error();
+        self.push_event(EVENT_RECEIVE | RECEIVE_COMM_ERROR);
+    }
+
+    pub(crate) fn received(&mut self, broadcast: bool) {
+        self.counters.slave_message();
+
+        let mut event = EVENT_RECEIVE;
+        if broadcast {
+            event |= RECEIVE_BROADCAST;
+        }
+        if self.listen_only {
+            event |= RECEIVE_LISTEN_ONLY;
+        }
+
+        self.push_event(event);
+    }
+
+    pub(crate) fn completed(&mut self, func: u8, response: Option<&[u8]>) {
+        let Some(pdu) = response else {
+            self.counters.no_response();
+            return;
+        };
+
+        let event = match pdu {
+            [f, code, ..] if f & 0x80 != 0 => {
+                self.counters.exception();
+                match code {
+                    1..=3 => SEND_READ_EXCEPTION,
+                    4 => SEND_ABORT_EXCEPTION,
+                    5 | 6 => SEND_BUSY_EXCEPTION,
+                    7 => SEND_NAK_EXCEPTION,
+                    _ => 0,
+                }
+            },
+            _ => {
+                if func != MODBUS_GET_COMM_EVENT_COUNTER {
+                    self.event_count = self.event_count.wrapping_add(1);
+                }
+                0
+            },
+        };
+
+        self.push_event(EVENT_SEND | event);
+    }
+
+    pub(crate) fn process(&mut self, pdu: &[u8]) -> Option<Vec<u8>> {
+        match pdu.first() {
+            Some(&MODBUS_DIAGNOSTICS) => self.diagnostic(pdu),
+            Some(&MODBUS_GET_COMM_EVENT_COUNTER) => {
+                let mut response = vec![MODBUS_GET_COMM_EVENT_COUNTER, 0, 0];
+                response.extend_from_slice(&self.event_count.to_be_bytes());
+                Some(response)
+            },
+            Some(&MODBUS_GET_COMM_EVENT_LOG) => {
+                let mut response = vec![MODBUS_GET_COMM_EVENT_LOG, 6 + self.events.len() as u8, 0, 0];
+                response.extend_from_slice(&self.event_count.to_be_bytes());
+                response.extend_from_slice(&self.counters.get_bus_messages().to_be_bytes());
+                response.extend(self.events.iter());
+                Some(response)
+            },
+            Some(func) => Some(vec![func | 0x80, MODBUS_ERROR_ILLEGAL_FUNCTION]),
+            None => None,
+        }
+    }
+
+    fn diagnostic(&mut self, pdu: &[u8]) -> Option<Vec<u8>> {
+        let exception = |code: u8| Some(vec![MODBUS_DIAGNOSTICS | 0x80, code]);
+
+        if pdu.len() < 3 {
+            return exception(MODBUS_ERROR_ILLEGAL_DATA_VALUE);
+        }
+
+        let sub = u16::from_be_bytes([pdu[1], pdu[2]]);
+        let data = &pdu[3..];
+        let value = |v: u16| {
+            let mut response = pdu[..3].to_vec();
+            response.extend_from_slice(&v.to_be_bytes());
+            Some(response)
+        };
+
+        match sub {
+            0x00 => return Some(pdu.to_vec()),
+            0x01 => return self.restart(pdu, data),
+            _ if data != [0, 0] => return exception(MODBUS_ERROR_ILLEGAL_DATA_VALUE),
+            _ => {},
+        }
+
+        match sub {
+            0x02 => value(0),
+            0x04 => {
+                self.listen_only = true;
+                self.push_event(EVENT_LISTEN_ONLY);
+                None
+            },
+            0x0a => {
+                self.clear();
+                Some(pdu.to_vec())
+            },
+            0x0b => value(self.counters.get_bus_messages()),
+            0x0c => value(self.counters.get_crc_errors()),
+            0x0d => value(self.counters.get_exceptions()),
+            0x0e => value(self.counters.get_slave_messages()),
+            0x0f => value(self.counters.get_no_responses()),
+            0x10 | 0x11 => value(0),
+            0x12 => value(self.counters.get_overruns()),
+            0x14 => {
+                self.counters.clear_overruns();
+                Some(pdu.to_vec())
+            },
+            _ => exception(MODBUS_ERROR_ILLEGAL_FUNCTION),
+        }
+    }
+
+    fn restart(&mut self, pdu: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+        let clear_log = match data {
+            [0x00, 0x00] => false,
+            [0xff, 0x00] => true,
+            _ => return Some(vec![MODBUS_DIAGNOSTICS | 0x80, MODBUS_ERROR_ILLEGAL_DATA_VALUE]),
+        };
+
+        let listen_only = self.listen_only;
+
+        if clear_log {
+            self.events.clear();
+        }
+
+        self.clear();
+        self.listen_only = false;
+        self.push_event(EVENT_RESTART);
+
+        match listen_only {
+            true => None,
+            false => Some(pdu.to_vec()),
+        }
+    }
+
+    fn push_event(&mut self, event: u8) {
+        self.events.push_front(event);
+        self.events.truncate(EVENT_LOG_SIZE);
+    }
+}
+
+pub(crate) fn is_restart(pdu: &[u8]) -> bool {
+    pdu.len() >= 3 && pdu[0] == MODBUS_DIAGNOSTICS && pdu[1..3] == [0, 1]
+}
+
+#[test]
+fn test_diagnostics() {
+    let mut diagnostics = Diagnostics::default();
+
+    diagnostics.get_counters_mut().bus_message();
+    diagnostics.received(false);
+    diagnostics.completed(3, Some(&[3, 2, 0, 1]));
+    diagnostics.received(false);
+    diagnostics.completed(3, Some(&[0x83, 2]));
+    diagnostics.comm_error();
+
+    assert_eq!(diagnostics.process(&[8, 0, 0, 0x12, 0x34]), Some(vec![8, 0, 0, 0x12, 0x34]));
+    assert_eq!(diagnostics.process(&[8, 0, 0x0b, 0, 0]), Some(vec![8, 0, 0x0b, 0, 1]));
+    assert_eq!(diagnostics.process(&[8, 0, 0x0c, 0, 0]), Some(vec![8, 0, 0x0c, 0, 1]));
+    assert_eq!(diagnostics.process(&[8, 0, 0x0d, 0, 0]), Some(vec![8, 0, 0x0d, 0, 1]));
+    assert_eq!(diagnostics.process(&[8, 0, 0x0e, 0, 0]), Some(vec![8, 0, 0x0e, 0, 2]));
+    assert_eq!(diagnostics.process(&[8, 0, 0x0e, 0, 1]), Some(vec![0x88, 3]));
+    assert_eq!(diagnostics.process(&[8, 0, 0x30, 0, 0]), Some(vec![0x88, 1]));
+    assert_eq!(diagnostics.process(&[11]), Some(vec![11, 0, 0, 0, 1]));
+    assert_eq!(diagnostics.process(&[12]), Some(vec![12, 11, 0, 0, 0, 1, 0, 1, 0x82, 0x41, 0x80, 0x40, 0x80]));
+
+    assert_eq!(diagnostics.process(&[8, 0, 4, 0, 0]), None);
+    assert!(diagnostics.is_listen_only());
+    assert!(is_restart(&[8, 0, 1, 0xff, 0]));
+    assert_eq!(diagnostics.process(&[8, 0, 1, 0xff, 0]), None);
+    assert!(!diagnostics.is_listen_only());
+    assert_eq!(diagnostics.get_events(), vec![EVENT_RESTART]);
+    assert_eq!(diagnostics.get_counters(), BusCounters::default());
+}
@@ -1,9 +1,77 @@
 use std::{io, fmt, error};
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExceptionCode {
+    IllegalFunction,
+    IllegalDataAddress,
+    IllegalDataValue,
+    SlaveDeviceFailure,
+    Acknowledge,
+    SlaveDeviceBusy,
+    NegativeAcknowledge,
+    MemoryParityError,
+    GatewayPathUnavailable,
+    GatewayTargetFailed,
+    Unknown(u8),
+}
+
+impl ExceptionCode {
+    pub fn from_code(code: u8) -> Self {
+        match code {
+            0x01 => Self::IllegalFunction,
+            0x02 => Self::IllegalDataAddress,
+            0x03 => Self::IllegalDataValue,
+            0x04 => Self::SlaveDeviceFailure,
+            0x05 => Self::Acknowledge,
+            0x06 => Self::SlaveDeviceBusy,
+            0x07 => Self::NegativeAcknowledge,
+            0x08 => Self::MemoryParityError,
+            0x0a => Self::GatewayPathUnavailable,
+            0x0b => Self::GatewayTargetFailed,
+            c => Self::Unknown(c),
+        }
+    }
+
+    pub fn get_code(self) -> u8 {
+        match self {
+            Self::IllegalFunction => 0x01,
+            Self::IllegalDataAddress => 0x02,
+            Self::IllegalDataValue => 0x03,
+            Self::SlaveDeviceFailure => 0x04,
+            Self::Acknowledge => 0x05,
+            Self::SlaveDeviceBusy => 0x06,
+            Self::NegativeAcknowledge => 0x07,
+            Self::MemoryParityError => 0x08,
+            Self::GatewayPathUnavailable => 0x0a,
+            Self::GatewayTargetFailed => 0x0b,
+            Self::Unknown(c) => c,
+        }
+    }
+}
+
+impl fmt::Display for ExceptionCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IllegalFunction => write!(f, "illegal function"),
+            Self::IllegalDataAddress => write!(f, "illegal data address"),
+            Self::IllegalDataValue => write!(f, "illegal data value"),
+            Self::SlaveDeviceFailure => write!(f, "slave device failure"),
+            Self::Acknowledge => write!(f, "acknowledge"),
+            Self::SlaveDeviceBusy => write!(f, "slave device busy"),
+            Self::NegativeAcknowledge => write!(f, "negative acknowledge"),
+            Self::MemoryParityError => write!(f, "memory parity error"),
+            Self::GatewayPathUnavailable => write!(f, "gateway path unavailable"),
+            Self::GatewayTargetFailed => write!(f, "gateway target device failed to respond"),
+            Self::Unknown(c) => write!(f, "unknown exception {:#04x}", c),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum ModbusErr {
     Io(io::Error),
     Rmodbus(rmodbus::ErrorKind),
+    Exception { function: u8, code: ExceptionCode },
 }
 
 impl From<io::Error> for ModbusErr {
@@ -23,8 +91,9 @@ impl fmt::Display for ModbusErr {
         match self {
             Self::Io(e) => e.fmt(f),
             Self::Rmodbus(e) => e.fmt(f),
+            Self::Exception { function, code } => write!(f, "function {} exception: {}", function, code),
         }
     }
 }
 
-impl error::Error for ModbusErr {}
\ No newline at end of file
+impl error::Error for ModbusErr {}
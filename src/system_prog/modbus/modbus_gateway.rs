@@ -0,0 +1,246 @@
+use std::net::{TcpStream, SocketAddr};
+use std::cell::{Cell, RefCell};
+use std::io::Write;
+use std::time::Duration;
+use std::{result, error, io};
+use rmodbus::ModbusProto;
+use crate::task::ConstProgram;
+use crate::memory::DataMemory;
+use crate::diagnostics::AuditLog;
+use super::modbus_error::{ModbusErr, ExceptionCode};
+use super::modbus_serial_link::SerialLink;
+use super::modbus_connection::ConnectionStatus;
+use super::modbus_allow_list::AllowList;
+use super::modbus_tcp_peer::{TcpPeer, TcpServer};
+use super::modbus_tcp_master::connect;
+use super::modbus_pdu;
+
+enum Target {
+    Rtu(Box<SerialLink>),
+    Tcp {
+        socket: &'static str,
+        stream: RefCell<Option<TcpStream>>,
+        tr_id: Cell<u16>,
+    },
+}
+
+struct Route {
+    units: Vec<u8>,
+    target: Target,
+}
+
+impl Route {
+    fn forward(&self, unit: u8, pdu: &[u8], timeout: Duration) -> result::Result<Vec<u8>, ModbusErr> {
+        match &self.target {
+            Target::Rtu(link) => {
+                if !link.ensure_open() {
+                    return Err(io::Error::from(io::ErrorKind::NotConnected).into());
+                }
+
+                link.transaction(timeout, |port| {
+                    port.write_all(&modbus_pdu::wrap(ModbusProto::Rtu, unit, 0, pdu))?;
+
+                    match unit {
+                        0 => Ok(Vec::new()),
+                        _ => modbus_pdu::read_pdu(port, ModbusProto::Rtu, unit, 0),
+                    }
+                })
+            },
+            Target::Tcp { socket, stream, tr_id } => {
+                let mut stream = stream.borrow_mut();
+
+                if stream.is_none() {
+                    let tcp = connect(socket, timeout)
+                        .map_err(|e| io::Error::new(io::ErrorKind::NotConnected, e))?;
+                    *stream = Some(tcp);
+                }
+
+                let tcp = stream.as_mut().ok_or_else(|| io::Error::from(io::ErrorKind::NotConnected))?;
+                tr_id.set(tr_id.get().wrapping_add(1));
+
+                let result = tcp.set_read_timeout(Some(timeout))
+                    .and_then(|_| tcp.write_all(&modbus_pdu::wrap(ModbusProto::TcpUdp, unit, tr_id.get(), pdu)))
+                    .map_err(ModbusErr::from)
+                    .and_then(|_| match unit {
+                        0 => Ok(Vec::new()),
+                        _ => modbus_pdu::read_pdu(tcp, ModbusProto::TcpUdp, unit, tr_id.get()),
+                    });
+
+                if let Err(ModbusErr::Io(_)) | Err(ModbusErr::Rmodbus(_)) = result {
+                    *stream = None;
+                }
+
+                result
+            },
+        }
+    }
+}
+
+pub struct ModbusGateway {
+    server: TcpServer,
+    routes: Vec<Route>,
+    timeout: Duration,
+}
+
+impl ModbusGateway {
+
+    pub fn new(listen: &'static str, timeout: Duration) -> Self {
+        Self { server: TcpServer::new(listen, "modbus_gateway"), routes: Vec::new(), timeout }
+    }
+
+    pub fn rtu_bus<const N: usize>(mut self, units: [u8; N], port: &'static str, settings: serial::PortSettings) -> Self {
+        let link = SerialLink::new(port, settings, self.timeout);
+        self.routes.push(Route { units: Vec::from(units), target: Target::Rtu(Box::new(link)) });
+        self
+    }
+
+    pub fn tcp_device<const N: usize>(mut self, units: [u8; N], socket: &'static str) -> Self {
+        let target = Target::Tcp { socket, stream: RefCell::new(None), tr_id: Cell::new(0) };
+        self.routes.push(Route { units: Vec::from(units), target });
+        self
+    }
+
+    pub fn max_connections(mut self, max_connections: usize) -> Self {
+        self.server = self.server.max_connections(max_connections);
+        self
+    }
+
+    pub fn idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.server = self.server.idle_timeout(idle_timeout);
+        self
+    }
+
+    pub fn allow_list(mut self, allow_list: AllowList) -> Self {
+        self.server = self.server.allow_list(allow_list);
+        self
+    }
+
+    pub fn audit_log(mut self, log: AuditLog) -> Self {
+        self.server = self.server.audit_log(log);
+        self
+    }
+
+    pub fn get_local_addr(&self) -> io::Result<SocketAddr> {
+        self.server.get_local_addr()
+    }
+
+    pub fn get_clients(&self) -> Vec<SocketAddr> {
+        self.server.get_clients()
+    }
+
+    pub fn get_bus_status(&self, unit: u8) -> Option<ConnectionStatus> {
+        match &self.find_route(unit)?.target {
+            Target::Rtu(link) => Some(link.get_status()),
+            Target::Tcp { .. } => None,
+        }
+    }
+
+    fn find_route(&self, unit: u8) -> Option<&Route> {
+        self.routes.iter().find(|r| r.units.contains(&unit))
+    }
+
+    fn handle(&self, request: &[u8]) -> Option<Vec<u8>> {
+        let tr_id = u16::from_be_bytes([request[0], request[1]]);
+        let unit = request[6];
+        let pdu = &request[7..];
+
+        let exception = |code: ExceptionCode| {
+            Some(modbus_pdu::wrap(ModbusProto::TcpUdp, unit, tr_id, &[pdu[0] | 0x80, code.get_code()]))
+        };
+
+        let Some(route) = self.find_route(unit) else {
+            return exception(ExceptionCode::GatewayPathUnavailable);
+        };
+
+        match route.forward(unit, pdu, self.timeout) {
+            Ok(response) if response.is_empty() => None,
+            Ok(response) => Some(modbus_pdu::wrap(ModbusProto::TcpUdp, unit, tr_id, &response)),
+            Err(ModbusErr::Exception { function, code }) => {
+                Some(modbus_pdu::wrap(ModbusProto::TcpUdp, unit, tr_id, &[function | 0x80, code.get_code()]))
+            },
+            Err(ModbusErr::Io(ref e)) if e.kind() == io::ErrorKind::NotConnected => {
+                exception(ExceptionCode::GatewayPathUnavailable)
+            },
+            Err(_) => exception(ExceptionCode::GatewayTargetFailed),
+        }
+    }
+
+    fn serve(&self, client: &mut TcpPeer) -> result::Result<(), ModbusErr> {
+        while let Some(request) = client.next_frame(ModbusProto::TcpUdp)? {
+            if let Some(response) = self.handle(&request) {
+                client.send(&response)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ConstProgram for ModbusGateway {
+    fn run(&self, _context: &mut dyn DataMemory) -> result::Result<(), Box<dyn error::Error>> {
+
+        self.server.run(|client| self.serve(client))?;
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_gateway_routing() {
+    use std::io::Read;
+    use std::net::TcpListener;
+    use crate::memory::PlcMemory;
+
+    let device = TcpListener::bind("127.0.0.1:0").unwrap();
+    let device_addr: &'static str = Box::leak(device.local_addr().unwrap().to_string().into_boxed_str());
+
+    let gateway = ModbusGateway::new("127.0.0.1:0", Duration::from_millis(200))
+        .tcp_device([0, 5, 6], device_addr);
+    let mut memory = PlcMemory::new(1, 1, 1, 1, 0);
+
+    let handle = std::thread::spawn(move || {
+        let (mut stream, _) = device.accept().unwrap();
+
+        let mut request = [0u8; 12];
+        stream.read_exact(&mut request).unwrap();
+        assert_eq!(request[6..], [5, 3, 0, 1, 0, 1]);
+        stream.write_all(&modbus_pdu::wrap(ModbusProto::TcpUdp, 5, u16::from_be_bytes([request[0], request[1]]), &[3, 2, 0, 42])).unwrap();
+
+        stream.read_exact(&mut request).unwrap();
+        assert_eq!(request[6..], [6, 6, 0, 1, 0, 1]);
+        stream.write_all(&modbus_pdu::wrap(ModbusProto::TcpUdp, 6, u16::from_be_bytes([request[0], request[1]]), &[0x86, 2])).unwrap();
+
+        stream.read_exact(&mut request).unwrap();
+        assert_eq!(request[6..], [0, 6, 0, 1, 0, 2]);
+
+        stream.read_exact(&mut request).unwrap();
+    });
+
+    let mut client = TcpStream::connect(gateway.get_local_addr().unwrap()).unwrap();
+    client.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+    client.set_nodelay(true).unwrap();
+    let mut run = |request: &[u8], len: usize| {
+        client.write_all(request).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        gateway.run(&mut memory).unwrap();
+        let mut response = vec![0u8; len];
+        client.read_exact(&mut response).unwrap();
+        response
+    };
+
+    assert_eq!(run(&modbus_pdu::wrap(ModbusProto::TcpUdp, 5, 0x1234, &[3, 0, 1, 0, 1]), 11),
+        modbus_pdu::wrap(ModbusProto::TcpUdp, 5, 0x1234, &[3, 2, 0, 42]));
+    assert_eq!(run(&modbus_pdu::wrap(ModbusProto::TcpUdp, 6, 0x1235, &[6, 0, 1, 0, 1]), 9),
+        modbus_pdu::wrap(ModbusProto::TcpUdp, 6, 0x1235, &[0x86, 2]));
+    assert_eq!(run(&modbus_pdu::wrap(ModbusProto::TcpUdp, 9, 0x1236, &[3, 0, 1, 0, 1]), 9),
+        modbus_pdu::wrap(ModbusProto::TcpUdp, 9, 0x1236, &[0x83, 0x0a]));
+
+    let started = std::time::Instant::now();
+    assert!(run(&modbus_pdu::wrap(ModbusProto::TcpUdp, 0, 0x1237, &[6, 0, 1, 0, 2]), 0).is_empty());
+    assert!(started.elapsed() < Duration::from_millis(200));
+
+    assert_eq!(run(&modbus_pdu::wrap(ModbusProto::TcpUdp, 5, 0x1238, &[3, 0, 1, 0, 1]), 9),
+        modbus_pdu::wrap(ModbusProto::TcpUdp, 5, 0x1238, &[0x83, 0x0b]));
+
+    handle.join().unwrap();
+}
@@ -0,0 +1,313 @@
+use std::{fs, io, path};
+use serde::{Serialize, Deserialize};
+use rmodbus::ErrorKind;
+use crate::memory::{DataMemory, MemoryArea, Value, ValueKind};
+use super::modbus_mapping_error::MappingErr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Read,
+    Write,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    Cycle(u64),
+    FrontCoil(u16),
+    FrontDiscrete(u16),
+}
+
+impl Default for Trigger {
+    fn default() -> Self {
+        Self::Cycle(1000)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Conversion {
+    remote: ValueKind,
+    local: ValueKind,
+    #[serde(default = "default_scale")]
+    scale: f64,
+    #[serde(default)]
+    offset: f64,
+}
+
+fn default_scale() -> f64 { 1.0 }
+
+impl Conversion {
+    pub fn new(remote: ValueKind, local: ValueKind) -> Self {
+        Self { remote, local, scale: 1.0, offset: 0.0 }
+    }
+
+    pub fn scale(mut self, scale: f64, offset: f64) -> Self {
+        self.scale = scale;
+        self.offset = offset;
+        self
+    }
+
+    pub fn get_remote(&self) -> ValueKind { self.remote }
+    pub fn get_local(&self) -> ValueKind { self.local }
+    pub fn get_scale(&self) -> f64 { self.scale }
+    pub fn get_offset(&self) -> f64 { self.offset }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RemoteRange {
+    area: MemoryArea,
+    offset: u16,
+    count: u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LocalRange {
+    area: MemoryArea,
+    offset: u16,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Mapping {
+    direction: Direction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    unit: Option<u8>,
+    remote: RemoteRange,
+    local: LocalRange,
+    #[serde(default)]
+    trigger: Trigger,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    conversion: Option<Conversion>,
+}
+
+impl Mapping {
+    pub fn read(remote: MemoryArea, remote_offset: u16, count: u16, local: MemoryArea, local_offset: u16) -> Self {
+        Self {
+            direction: Direction::Read,
+            unit: None,
+            remote: RemoteRange { area: remote, offset: remote_offset, count },
+            local: LocalRange { area: local, offset: local_offset },
+            trigger: Trigger::default(),
+            conversion: None,
+        }
+    }
+
+    pub fn write(local: MemoryArea, local_offset: u16, remote: MemoryArea, remote_offset: u16, count: u16) -> Self {
+        Self { direction: Direction::Write, ..Self::read(remote, remote_offset, count, local, local_offset) }
+    }
+
+    pub fn unit(mut self, unit: u8) -> Self {
+        self.unit = Some(unit);
+        self
+    }
+
+    pub fn trigger(mut self, trigger: Trigger) -> Self {
+        self.trigger = trigger;
+        self
+    }
+
+    pub fn convert(mut self, conversion: Conversion) -> Self {
+        self.conversion = Some(conversion);
+        self
+    }
+
+    pub fn from_file<P: AsRef<path::Path>>(path: P) -> Result<Vec<Self>, MappingErr> {
+        let file = fs::File::open(path)?;
+        let mappings: Vec<Self> = serde_yaml::from_reader(io::BufReader::new(file))?;
+
+        for mapping in mappings.iter() {
+            mapping.validate()?;
+        }
+
+        Ok(mappings)
+    }
+
+    pub fn from_yaml(yaml: &str) -> Result<Vec<Self>, MappingErr> {
+        let mappings: Vec<Self> = serde_yaml::from_str(yaml)?;
+
+        for mapping in mappings.iter() {
+            mapping.validate()?;
+        }
+
+        Ok(mappings)
+    }
+
+    pub fn get_direction(&self) -> Direction { self.direction }
+    pub fn get_unit(&self) -> Option<u8> { self.unit }
+    pub fn get_remote_area(&self) -> MemoryArea { self.remote.area }
+    pub fn get_remote_offset(&self) -> u16 { self.remote.offset }
+    pub fn get_count(&self) -> u16 { self.remote.count }
+    pub fn get_local_area(&self) -> MemoryArea { self.local.area }
+    pub fn get_local_offset(&self) -> u16 { self.local.offset }
+    pub fn get_trigger(&self) -> Trigger { self.trigger }
+    pub fn get_conversion(&self) -> Option<Conversion> { self.conversion }
+
+    pub fn validate(&self) -> Result<(), MappingErr> {
+        let invalid = |msg: &str| Err(MappingErr::Invalid(format!("{} {:?} {}: {}",
+            match self.direction { Direction::Read => "read", Direction::Write => "write" },
+            self.remote.area, self.remote.offset, msg)));
+
+        let is_bits = matches!(self.remote.area, MemoryArea::Coils | MemoryArea::Discretes);
+        let limit = match (self.direction, is_bits) {
+            (Direction::Read, true) => 2000,
+            (Direction::Read, false) => 125,
+            (Direction::Write, true) => 1968,
+            (Direction::Write, false) => 123,
+        };
+
+        if self.remote.area == MemoryArea::Markers {
+            return invalid("markers are not a modbus area");
+        }
+
+        if self.direction == Direction::Write && !matches!(self.remote.area, MemoryArea::Coils | MemoryArea::Holdings) {
+            return invalid("only coils and holdings can be written");
+        }
+
+        if self.direction == Direction::Read && self.unit == Some(0) {
+            return invalid("reads can not be broadcast");
+        }
+
+        if self.remote.count == 0 || self.remote.count > limit {
+            return invalid("count out of protocol limits");
+        }
+
+        if let Some(conversion) = self.conversion {
+            if is_bits {
+                return invalid("conversion requires a register area");
+            }
+
+            if conversion.remote == ValueKind::Bool || conversion.local == ValueKind::Bool {
+                return invalid("bool is not a conversion type");
+            }
+
+            if !self.remote.count.is_multiple_of(conversion.remote.get_count()) {
+                return invalid("count does not match the remote type");
+            }
+
+            if conversion.scale == 0.0 || !conversion.scale.is_finite() {
+                return invalid("scale must be a finite non zero number");
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn store_bits(&self, context: &mut dyn DataMemory, bits: &[bool]) -> Result<(), ErrorKind> {
+        for (i, bit) in bits.iter().enumerate() {
+            context.set_word(self.local.area, local_reg(self.local.offset, i)?, *bit as u16)?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn store_words(&self, context: &mut dyn DataMemory, words: &[u16]) -> Result<(), ErrorKind> {
+        let Some(conversion) = self.conversion else {
+            for (i, word) in words.iter().enumerate() {
+                context.set_word(self.local.area, local_reg(self.local.offset, i)?, *word)?;
+            }
+
+            return Ok(());
+        };
+
+        let mut i = 0;
+
+        for chunk in words.chunks_exact(conversion.remote.get_count() as usize) {
+            let value = Value::from_regs(conversion.remote, chunk).to_f64() * conversion.scale + conversion.offset;
+
+            for word in Value::from_f64(conversion.local, value).to_regs() {
+                context.set_word(self.local.area, local_reg(self.local.offset, i)?, word)?;
+                i += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn collect_bits(&self, context: &dyn DataMemory) -> Result<Vec<bool>, ErrorKind> {
+        (0..self.remote.count as usize)
+            .map(|i| Ok(context.get_word(self.local.area, local_reg(self.local.offset, i)?)? != 0))
+            .collect()
+    }
+
+    pub(crate) fn collect_words(&self, context: &dyn DataMemory) -> Result<Vec<u16>, ErrorKind> {
+        let Some(conversion) = self.conversion else {
+            return (0..self.remote.count as usize)
+                .map(|i| context.get_word(self.local.area, local_reg(self.local.offset, i)?))
+                .collect();
+        };
+
+        let elements = self.remote.count / conversion.remote.get_count();
+        let mut words = Vec::with_capacity(self.remote.count as usize);
+
+        for i in 0..elements as usize {
+            let reg = local_reg(self.local.offset, i * conversion.local.get_count() as usize)?;
+            let value = conversion.local.read(context, self.local.area, reg)?.to_f64();
+            let raw = (value - conversion.offset) / conversion.scale;
+
+            words.extend(Value::from_f64(conversion.remote, raw).to_regs());
+        }
+
+        Ok(words)
+    }
+}
+
+fn local_reg(offset: u16, index: usize) -> Result<u16, ErrorKind> {
+    u16::try_from(offset as usize + index).map_err(|_| ErrorKind::OOBContext)
+}
+
+#[test]
+fn test_mapping_yaml_and_conversion() {
+    use crate::memory::PlcMemory;
+
+    let yaml = "
+- direction: read
+  remote: { area: holdings, offset: 100, count: 4 }
+  local: { area: inputs, offset: 10 }
+  trigger: { cycle: 500 }
+  conversion: { remote: i16, local: f32, scale: 0.1, offset: 2.0 }
+- direction: write
+  unit: 7
+  remote: { area: coils, offset: 0, count: 3 }
+  local: { area: coils, offset: 20 }
+  trigger: { front_coil: 5 }
+";
+
+    let mappings = Mapping::from_yaml(yaml).unwrap();
+    assert_eq!(mappings.len(), 2);
+    assert_eq!(
+        mappings[0],
+        Mapping::read(MemoryArea::Holdings, 100, 4, MemoryArea::Inputs, 10)
+            .trigger(Trigger::Cycle(500))
+            .convert(Conversion::new(ValueKind::I16, ValueKind::F32).scale(0.1, 2.0))
+    );
+    assert_eq!(mappings[0].get_unit(), None);
+    assert_eq!(mappings[1].get_unit(), Some(7));
+    assert_eq!(mappings[1].get_trigger(), Trigger::FrontCoil(5));
+
+    let mut memory = PlcMemory::new(30, 10, 30, 30, 0);
+    mappings[0].store_words(&mut memory, &[10, (-20i16) as u16, 0, 1000]).unwrap();
+    assert_eq!(memory.get_inputs_as_f32(10).unwrap(), 3.0);
+    assert_eq!(memory.get_inputs_as_f32(12).unwrap(), 0.0);
+    assert_eq!(memory.get_inputs_as_f32(16).unwrap(), 102.0);
+
+    memory.set_coil(21, true).unwrap();
+    assert_eq!(mappings[1].collect_bits(&memory).unwrap(), vec![false, true, false]);
+
+    let write = Mapping::write(MemoryArea::Holdings, 0, MemoryArea::Holdings, 0, 4)
+        .convert(Conversion::new(ValueKind::U32, ValueKind::F32).scale(0.5, 0.0));
+    memory.set_holdings_from_f32(0, 10.0).unwrap();
+    memory.set_holdings_from_f32(2, 65536.0).unwrap();
+    assert_eq!(write.collect_words(&memory).unwrap(), vec![0, 20, 2, 0]);
+
+    let invalid = "
+- direction: write
+  remote: { area: inputs, offset: 0, count: 1 }
+  local: { area: holdings, offset: 0 }
+";
+    assert!(matches!(Mapping::from_yaml(invalid), Err(MappingErr::Invalid(_))));
+    assert!(Mapping::read(MemoryArea::Holdings, 0, 1, MemoryArea::Holdings, 0).unit(0).validate().is_err());
+    assert!(Mapping::read(MemoryArea::Holdings, 0, 3, MemoryArea::Holdings, 0)
+        .convert(Conversion::new(ValueKind::U32, ValueKind::U32))
+        .validate()
+        .is_err());
+}
@@ -0,0 +1,32 @@
+use std::{io, fmt, error};
+
+#[derive(Debug)]
+pub enum MappingErr {
+    Io(io::Error),
+    Yaml(serde_yaml::Error),
+    Invalid(String),
+}
+
+impl From<io::Error> for MappingErr {
+    fn from(err: io::Error) -> MappingErr {
+        MappingErr::Io(err)
+    }
+}
+
+impl From<serde_yaml::Error> for MappingErr {
+    fn from(err: serde_yaml::Error) -> MappingErr {
+        MappingErr::Yaml(err)
+    }
+}
+
+impl fmt::Display for MappingErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => e.fmt(f),
+            Self::Yaml(e) => e.fmt(f),
+            Self::Invalid(e) => write!(f, "invalid mapping: {}", e),
+        }
+    }
+}
+
+impl error::Error for MappingErr {}
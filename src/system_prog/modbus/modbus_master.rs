@@ -1,20 +1,137 @@
 use std::{io, result};
-use rmodbus::client::ModbusRequest;
-use rmodbus::guess_response_frame_len;
+use std::cell::Cell;
 use rmodbus::ModbusProto;
-use rmodbus::server::context::ModbusContext;
+use crate::memory::DataMemory;
 use super::modbus_error::ModbusErr;
-use super::modbus_master_actions::Acton;
+use super::modbus_master_actions::{Acton, ReadKind, ReadData, publish_all};
+use super::modbus_mapping::Direction;
+use crate::memory::MemoryArea;
+use super::modbus_poll_plan::{PollItem, due_actions, plan};
+use super::modbus_pdu;
 
 pub struct ModbusMaster {
     id: u8,
     proto: ModbusProto,
+    tr_id: Cell<u16>,
 }
 
 impl ModbusMaster {
 
     pub fn new(id: u8, proto: ModbusProto) -> Self {
-        Self { id, proto }
+        Self { id, proto, tr_id: Cell::new(0) }
+    }
+
+    pub fn get_id(&self) -> u8 { self.id }
+
+    pub fn is_broadcast(&self) -> bool { self.id == 0 }
+
+    fn next_tr_id(&self) -> u16 {
+        self.tr_id.set(self.tr_id.get().wrapping_add(1));
+        self.tr_id.get()
+    }
+
+    fn read_bits<T: io::Read + io::Write>(
+        &self,
+        transport: &mut T,
+        function: u8,
+        offset: u16,
+        count: u16,
+    ) -> result::Result<Vec<bool>, ModbusErr> {
+
+        self.check_read()?;
+
+        if count == 0 || count > 2000 {
+            return Err(rmodbus::ErrorKind::OOB.into());
+        }
+
+        let mut pdu = vec![function];
+        modbus_pdu::extend_words(&mut pdu, &[offset, count]);
+
+        let response = self.transact(transport, &pdu)?;
+        let bytes = (count as usize).div_ceil(8);
+
+        if response.len() != bytes + 2 || response[1] as usize != bytes {
+            return Err(rmodbus::ErrorKind::FrameBroken.into());
+        }
+
+        Ok(modbus_pdu::unpack_bits(&response[2..], count as usize))
+    }
+
+    fn read_words<T: io::Read + io::Write>(
+        &self,
+        transport: &mut T,
+        function: u8,
+        offset: u16,
+        count: u16,
+    ) -> result::Result<Vec<u16>, ModbusErr> {
+
+        self.check_read()?;
+
+        if count == 0 || count > 125 {
+            return Err(rmodbus::ErrorKind::OOB.into());
+        }
+
+        let mut pdu = vec![function];
+        modbus_pdu::extend_words(&mut pdu, &[offset, count]);
+
+        let response = self.transact(transport, &pdu)?;
+        let bytes = count as usize * 2;
+
+        if response.len() != bytes + 2 || response[1] as usize != bytes {
+            return Err(rmodbus::ErrorKind::FrameBroken.into());
+        }
+
+        Ok(modbus_pdu::words(&response[2..]))
+    }
+
+    fn expect_echo<T: io::Read + io::Write>(
+        &self,
+        transport: &mut T,
+        pdu: &[u8],
+        echo_len: usize,
+    ) -> result::Result<(), ModbusErr> {
+
+        let response = self.transact(transport, pdu)?;
+
+        if self.is_broadcast() {
+            return Ok(());
+        }
+
+        if response.len() != echo_len || response[..] != pdu[..echo_len] {
+            return Err(rmodbus::ErrorKind::FrameBroken.into());
+        }
+
+        Ok(())
+    }
+
+    fn transact<T: io::Read + io::Write>(
+        &self,
+        transport: &mut T,
+        pdu: &[u8],
+    ) -> result::Result<Vec<u8>, ModbusErr> {
+
+        let tr_id = self.next_tr_id();
+        transport.write_all(&modbus_pdu::wrap(self.proto, self.id, tr_id, pdu))?;
+
+        if self.is_broadcast() {
+            return Ok(pdu.to_vec());
+        }
+
+        let response = modbus_pdu::read_pdu(transport, self.proto, self.id, tr_id)?;
+
+        if response[0] != pdu[0] {
+            return Err(rmodbus::ErrorKind::FrameBroken.into());
+        }
+
+        Ok(response)
+    }
+
+    fn check_read(&self) -> result::Result<(), ModbusErr> {
+        if self.is_broadcast() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "read requests can not be broadcast").into());
+        }
+
+        Ok(())
     }
 
     pub fn read_coils<T: io::Read + io::Write>(
@@ -24,17 +141,7 @@ impl ModbusMaster {
         count: u16,
     ) -> result::Result<Vec<bool>, ModbusErr> {
         
-        let mut mreq = ModbusRequest::new(self.id, self.proto);
-        let mut request = Vec::with_capacity(count as usize);
-        mreq.generate_get_coils(offset, count, &mut request)?;
-        transport.write(&request)?;
-    
-        let response = self.read_request(transport)?;
-    
-        let mut data = Vec::with_capacity(count as usize);
-        mreq.parse_bool(&response, &mut data).unwrap();
-
-        Ok(data)
+        self.read_bits(transport, 1, offset, count)
     }
     
     pub fn read_discretes<T: io::Read + io::Write>(
@@ -44,17 +151,7 @@ impl ModbusMaster {
         count: u16,
     ) -> result::Result<Vec<bool>, ModbusErr> {
 
-        let mut mreq = ModbusRequest::new(self.id, self.proto);
-        let mut request = Vec::with_capacity(count as usize);
-        mreq.generate_get_discretes(offset, count, &mut request)?;
-        transport.write(&request)?;
-
-        let response = self.read_request(transport)?;
-
-        let mut data = Vec::new();
-        mreq.parse_bool(&response, &mut data).unwrap();
-
-        Ok(data)
+        self.read_bits(transport, 2, offset, count)
     }
 
     pub fn read_holdings<T: io::Read + io::Write>(
@@ -64,17 +161,7 @@ impl ModbusMaster {
         count: u16,
     ) -> result::Result<Vec<u16>, ModbusErr> {
 
-        let mut mreq = ModbusRequest::new(self.id, self.proto);
-        let mut request = Vec::with_capacity((count * 2) as usize);
-        mreq.generate_get_holdings(offset, count, &mut request)?;
-        transport.write(&request)?;
-
-        let response = self.read_request(transport)?;
-
-        let mut data = Vec::new();
-        mreq.parse_u16(&response, &mut data).unwrap();
-
-        Ok(data)
+        self.read_words(transport, 3, offset, count)
     }
 
     pub fn read_inputs<T: io::Read + io::Write>(
@@ -84,17 +171,7 @@ impl ModbusMaster {
         count: u16,
     ) -> result::Result<Vec<u16>, ModbusErr> {
 
-        let mut mreq = ModbusRequest::new(self.id, self.proto);
-        let mut request = Vec::with_capacity((count * 2) as usize);
-        mreq.generate_get_inputs(offset, count, &mut request)?;
-        transport.write(&request)?;
-
-        let response = self.read_request(transport)?;
-
-        let mut data = Vec::new();
-        mreq.parse_u16(&response, &mut data).unwrap();
-
-        Ok(data)
+        self.read_words(transport, 4, offset, count)
     }
 
     pub fn write_coil<T: io::Read + io::Write>(
@@ -104,16 +181,10 @@ impl ModbusMaster {
         value: bool,
     ) -> result::Result<(), ModbusErr> {
 
-        let mut mreq = ModbusRequest::new(self.id, self.proto);
-        let mut request = Vec::with_capacity(8);
-        mreq.generate_set_coil(offset, value, &mut request)?;
-        transport.write(&request)?;
-
-        let response = self.read_request(transport)?;
+        let mut pdu = vec![5];
+        modbus_pdu::extend_words(&mut pdu, &[offset, if value { 0xff00 } else { 0 }]);
 
-        mreq.parse_ok(&response)?;
-
-        Ok(())
+        self.expect_echo(transport, &pdu, pdu.len())
     }
 
     pub fn write_multipl_coils<T: io::Read + io::Write>(
@@ -122,16 +193,18 @@ impl ModbusMaster {
         offset: u16,
         values: Vec<bool>,
     ) ->result::Result<(), ModbusErr> {
-        let mut mreq = ModbusRequest::new(self.id, self.proto);
-        let mut request = Vec::with_capacity(8);
-        mreq.generate_set_coils_bulk(offset, &values, &mut request)?;
-        transport.write(&request)?;
 
-        let response = self.read_request(transport)?;
+        if values.is_empty() || values.len() > 1968 {
+            return Err(rmodbus::ErrorKind::OOB.into());
+        }
 
-        mreq.parse_ok(&response)?;
+        let bytes = modbus_pdu::pack_bits(&values);
+        let mut pdu = vec![15];
+        modbus_pdu::extend_words(&mut pdu, &[offset, values.len() as u16]);
+        pdu.push(bytes.len() as u8);
+        pdu.extend(bytes);
 
-        Ok(())
+        self.expect_echo(transport, &pdu, 5)
     }
 
     pub fn write_holding<T: io::Read + io::Write>(
@@ -141,16 +214,10 @@ impl ModbusMaster {
         value: u16,
     ) -> result::Result<(), ModbusErr> {
 
-        let mut mreq = ModbusRequest::new(self.id, self.proto);
-        let mut request = Vec::with_capacity(8);
-        mreq.generate_set_holding(offset, value, &mut request)?;
-        transport.write(&request)?;
+        let mut pdu = vec![6];
+        modbus_pdu::extend_words(&mut pdu, &[offset, value]);
 
-        let response = self.read_request(transport)?;
-
-        mreq.parse_ok(&response)?;
-
-        Ok(())
+        self.expect_echo(transport, &pdu, pdu.len())
     }
 
     pub fn write_multipl_holding<T: io::Read + io::Write>(
@@ -160,19 +227,212 @@ impl ModbusMaster {
         values: Vec<u16>,
     ) ->result::Result<(), ModbusErr> {
 
-        let mut mreq = ModbusRequest::new(self.id, self.proto);
-        let mut request = Vec::with_capacity(8);
-        mreq.generate_set_holdings_bulk(offset, &values, &mut request)?;
-        transport.write(&request)?;
+        if values.is_empty() || values.len() > 123 {
+            return Err(rmodbus::ErrorKind::OOB.into());
+        }
+
+        let mut pdu = vec![16];
+        modbus_pdu::extend_words(&mut pdu, &[offset, values.len() as u16]);
+        pdu.push((values.len() * 2) as u8);
+        modbus_pdu::extend_words(&mut pdu, &values);
+
+        self.expect_echo(transport, &pdu, 5)
+    }
+
+    pub fn mask_write_holding<T: io::Read + io::Write>(
+        &self,
+        transport: &mut T,
+        offset: u16,
+        and_mask: u16,
+        or_mask: u16,
+    ) -> result::Result<(), ModbusErr> {
 
-        let response = self.read_request(transport)?;
+        let mut pdu = vec![22];
+        modbus_pdu::extend_words(&mut pdu, &[offset, and_mask, or_mask]);
 
-        mreq.parse_ok(&response)?;
+        if self.transact(transport, &pdu)? != pdu {
+            return Err(rmodbus::ErrorKind::FrameBroken.into());
+        }
 
         Ok(())
     }
 
-    pub fn execute_action<T: io::Read + io::Write>(&self, action: &Acton, context: &mut ModbusContext, stream: &mut T) -> result::Result<(), ModbusErr> {
+    pub fn read_write_holdings<T: io::Read + io::Write>(
+        &self,
+        transport: &mut T,
+        read_offset: u16,
+        read_count: u16,
+        write_offset: u16,
+        values: Vec<u16>,
+    ) -> result::Result<Vec<u16>, ModbusErr> {
+
+        self.check_read()?;
+
+        if read_count == 0 || read_count > 125 || values.is_empty() || values.len() > 121 {
+            return Err(rmodbus::ErrorKind::OOB.into());
+        }
+
+        let mut pdu = vec![23];
+        modbus_pdu::extend_words(&mut pdu, &[read_offset, read_count, write_offset, values.len() as u16]);
+        pdu.push((values.len() * 2) as u8);
+        modbus_pdu::extend_words(&mut pdu, &values);
+
+        let response = self.transact(transport, &pdu)?;
+
+        if response.len() < 2 || response[1] as usize != read_count as usize * 2 || response.len() != response[1] as usize + 2 {
+            return Err(rmodbus::ErrorKind::FrameBroken.into());
+        }
+
+        Ok(modbus_pdu::words(&response[2..]))
+    }
+
+    pub fn read_device_identification<T: io::Read + io::Write>(
+        &self,
+        transport: &mut T,
+        code: u8,
+        object_id: u8,
+    ) -> result::Result<Vec<(u8, String)>, ModbusErr> {
+
+        self.check_read()?;
+
+        let mut objects = Vec::new();
+        let mut next = object_id;
+
+        loop {
+            let response = self.transact(transport, &[43, 14, code, next])?;
+
+            if response.len() < 7 || response[1] != 14 {
+                return Err(rmodbus::ErrorKind::FrameBroken.into());
+            }
+
+            let more_follows = response[4] == 0xff;
+            let mut pos = 7;
+
+            for _ in 0..response[6] {
+                let id = *response.get(pos).ok_or(rmodbus::ErrorKind::FrameBroken)?;
+                let len = *response.get(pos + 1).ok_or(rmodbus::ErrorKind::FrameBroken)? as usize;
+                let value = response.get(pos + 2..pos + 2 + len).ok_or(rmodbus::ErrorKind::FrameBroken)?;
+
+                objects.push((id, String::from_utf8_lossy(value).into_owned()));
+                pos += 2 + len;
+            }
+
+            if !more_follows || code == 4 || response[5] <= next {
+                return Ok(objects);
+            }
+
+            next = response[5];
+        }
+    }
+
+    pub fn report_server_id<T: io::Read + io::Write>(
+        &self,
+        transport: &mut T,
+    ) -> result::Result<Vec<u8>, ModbusErr> {
+
+        self.check_read()?;
+
+        let response = self.transact(transport, &[17])?;
+
+        if response.len() < 2 || response.len() != response[1] as usize + 2 {
+            return Err(rmodbus::ErrorKind::FrameBroken.into());
+        }
+
+        Ok(response[2..].to_vec())
+    }
+
+    pub fn read_file_record<T: io::Read + io::Write>(
+        &self,
+        transport: &mut T,
+        file: u16,
+        record: u16,
+        length: u16,
+    ) -> result::Result<Vec<u16>, ModbusErr> {
+
+        self.check_read()?;
+
+        if length == 0 || length > 124 {
+            return Err(rmodbus::ErrorKind::OOB.into());
+        }
+
+        let mut pdu = vec![20, 7, 6];
+        modbus_pdu::extend_words(&mut pdu, &[file, record, length]);
+
+        let response = self.transact(transport, &pdu)?;
+        let data_len = length as usize * 2;
+
+        if response.len() != data_len + 4 || response[2] as usize != data_len + 1 || response[3] != 6 {
+            return Err(rmodbus::ErrorKind::FrameBroken.into());
+        }
+
+        Ok(modbus_pdu::words(&response[4..]))
+    }
+
+    pub fn write_file_record<T: io::Read + io::Write>(
+        &self,
+        transport: &mut T,
+        file: u16,
+        record: u16,
+        values: Vec<u16>,
+    ) -> result::Result<(), ModbusErr> {
+
+        if values.is_empty() || values.len() > 122 {
+            return Err(rmodbus::ErrorKind::OOB.into());
+        }
+
+        let mut pdu = vec![21, (7 + values.len() * 2) as u8, 6];
+        modbus_pdu::extend_words(&mut pdu, &[file, record, values.len() as u16]);
+        modbus_pdu::extend_words(&mut pdu, &values);
+
+        if self.transact(transport, &pdu)? != pdu {
+            return Err(rmodbus::ErrorKind::FrameBroken.into());
+        }
+
+        Ok(())
+    }
+
+    pub fn diagnostics<T: io::Read + io::Write>(
+        &self,
+        transport: &mut T,
+        sub_function: u16,
+        data: u16,
+    ) -> result::Result<u16, ModbusErr> {
+
+        let mut pdu = vec![8];
+        modbus_pdu::extend_words(&mut pdu, &[sub_function, data]);
+
+        let response = self.transact(transport, &pdu)?;
+
+        if response.len() != 5 || response[1..3] != pdu[1..3] {
+            return Err(rmodbus::ErrorKind::FrameBroken.into());
+        }
+
+        Ok(u16::from_be_bytes([response[3], response[4]]))
+    }
+
+    pub fn poll<T: io::Read + io::Write>(
+        &self,
+        transport: &mut T,
+        actions: &[Acton],
+        context: &mut dyn DataMemory,
+    ) -> result::Result<(), ModbusErr> {
+
+        let mut result = Ok(());
+
+        for item in plan(due_actions(actions, context)?, None) {
+            let action_result = item.attempt(0, |item| self.execute_item(item, context, transport));
+
+            if let (Err(err), true) = (action_result, result.is_ok()) {
+                result = Err(err);
+            }
+        }
+
+        publish_all(actions, context)?;
+
+        result
+    }
+
+    pub fn execute_action<T: io::Read + io::Write>(&self, action: &Acton, context: &mut dyn DataMemory, stream: &mut T) -> result::Result<(), ModbusErr> {
         match action {
             Acton::ReadCoils(data) => {
                 let request = self.read_coils(stream, data.get_offset(), data.get_count())?;
@@ -190,7 +450,7 @@ impl ModbusMaster {
                 Ok(())
             },
             Acton::ReadInputs(data) => {
-                let request = self.read_holdings(stream, data.get_offset(), data.get_count())?;
+                let request = self.read_inputs(stream, data.get_offset(), data.get_count())?;
                 data.handler(context, request);
                 Ok(())
             },
@@ -214,26 +474,183 @@ impl ModbusMaster {
                 self.write_multipl_holding(stream, data.get_offset(), values)?;
                 Ok(())
             },
+            Acton::MaskWriteHolding(data) => {
+                let (and_mask, or_mask) = data.handler(context, ());
+                self.mask_write_holding(stream, data.get_offset(), and_mask, or_mask)
+            },
+            Acton::ReadWriteHoldings(data, write_offset, write) => {
+                let values = write(context, ());
+                let request = self.read_write_holdings(stream, data.get_offset(), data.get_count(), *write_offset, values)?;
+                data.handler(context, request);
+                Ok(())
+            },
+            Acton::ReadDeviceId(data, code, object_id) => {
+                let request = self.read_device_identification(stream, *code, *object_id)?;
+                data.handler(context, request);
+                Ok(())
+            },
+            Acton::ReportServerId(data) => {
+                let request = self.report_server_id(stream)?;
+                data.handler(context, request);
+                Ok(())
+            },
+            Acton::ReadFileRecord(data, file) => {
+                let request = self.read_file_record(stream, *file, data.get_offset(), data.get_count())?;
+                data.handler(context, request);
+                Ok(())
+            },
+            Acton::WriteFileRecord(data, file) => {
+                let values = data.handler(context, ());
+                self.write_file_record(stream, *file, data.get_offset(), values)
+            },
+            Acton::Diagnostics(data, sub_function, value) => {
+                let request = self.diagnostics(stream, *sub_function, *value)?;
+                data.handler(context, request);
+                Ok(())
+            },
+            Acton::Mapped(data) => {
+                let mapping = data.get_mapping();
+
+                if let Some(unit) = mapping.get_unit().filter(|unit| *unit != self.id) {
+                    let master = Self { id: unit, proto: self.proto, tr_id: Cell::new(self.tr_id.get()) };
+                    let result = master.execute_action(action, context, stream);
+                    self.tr_id.set(master.tr_id.get());
+                    return result;
+                }
+
+                if mapping.get_direction() == Direction::Read {
+                    let kind = ReadKind::from_area(mapping.get_remote_area()).ok_or(rmodbus::ErrorKind::IllegalDataAddress)?;
+                    let block = self.read_block(stream, kind, mapping.get_remote_offset(), mapping.get_count())?;
+                    action.deliver(context, mapping.get_remote_offset(), &block)?;
+                    return Ok(());
+                }
+
+                match mapping.get_remote_area() {
+                    MemoryArea::Coils => {
+                        let values = mapping.collect_bits(context)?;
+                        self.write_multipl_coils(stream, mapping.get_remote_offset(), values)
+                    },
+                    _ => {
+                        let values = mapping.collect_words(context)?;
+                        self.write_multipl_holding(stream, mapping.get_remote_offset(), values)
+                    },
+                }
+            },
         }
     }
 
-    fn read_request<T: io::Read + io::Write>(
+    pub(crate) fn read_block<T: io::Read + io::Write>(
         &self,
         transport: &mut T,
-    ) -> result::Result<Vec<u8>, ModbusErr> {
-    
-        let mut buf = [0u8; 6];
-        transport.read_exact(&mut buf)?;
-        let mut response = Vec::with_capacity(8);
-        response.extend_from_slice(&buf);
-        let len = guess_response_frame_len(&buf, self.proto)?;
-    
-        if len > 6 {
-            let mut rest = vec![0u8; (len - 6) as usize];
-            transport.read_exact(&mut rest).unwrap();
-            response.extend(rest);
+        kind: ReadKind,
+        offset: u16,
+        count: u16,
+    ) -> result::Result<ReadData, ModbusErr> {
+        match kind {
+            ReadKind::Coils => Ok(ReadData::Bits(self.read_coils(transport, offset, count)?)),
+            ReadKind::Discretes => Ok(ReadData::Bits(self.read_discretes(transport, offset, count)?)),
+            ReadKind::Holdings => Ok(ReadData::Words(self.read_holdings(transport, offset, count)?)),
+            ReadKind::Inputs => Ok(ReadData::Words(self.read_inputs(transport, offset, count)?)),
         }
-    
-        Ok(response)
     }
+
+    pub(crate) fn execute_item<T: io::Read + io::Write>(&self, item: &PollItem, context: &mut dyn DataMemory, stream: &mut T) -> result::Result<(), ModbusErr> {
+        match item {
+            PollItem::Single(action) => self.execute_action(action, context, stream),
+            PollItem::Merged(group) => {
+                let data = self.read_block(stream, group.get_kind(), group.get_offset(), group.get_count())?;
+
+                for action in group.get_actions() {
+                    action.deliver(context, group.get_offset(), &data)?;
+                }
+
+                Ok(())
+            },
+        }
+    }
+}
+
+#[test]
+fn test_master_exceptions_and_malformed_replies() {
+    use std::io::{Read, Write};
+    use super::modbus_error::ExceptionCode;
+
+    struct Loopback {
+        response: io::Cursor<Vec<u8>>,
+        request: Vec<u8>,
+    }
+
+    impl Read for Loopback {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> { self.response.read(buf) }
+    }
+
+    impl Write for Loopback {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> { self.request.write(buf) }
+        fn flush(&mut self) -> io::Result<()> { Ok(()) }
+    }
+
+    let reply = |pdu: &[u8]| Loopback {
+        response: io::Cursor::new(modbus_pdu::wrap(ModbusProto::Rtu, 1, 0, pdu)),
+        request: Vec::new(),
+    };
+    let master = ModbusMaster::new(1, ModbusProto::Rtu);
+
+    let mut transport = reply(&[3, 4, 0, 1, 0x12, 0x34]);
+    assert_eq!(master.read_holdings(&mut transport, 10, 2).unwrap(), vec![1, 0x1234]);
+    assert_eq!(transport.request, modbus_pdu::wrap(ModbusProto::Rtu, 1, 0, &[3, 0, 10, 0, 2]));
+
+    let mut transport = reply(&[0x83, 2]);
+    assert!(matches!(
+        master.read_holdings(&mut transport, 10, 2),
+        Err(ModbusErr::Exception { function: 3, code: ExceptionCode::IllegalDataAddress })
+    ));
+
+    let mut transport = reply(&[0x8f, 0x0b]);
+    let err = master.write_multipl_coils(&mut transport, 0, vec![true; 3]).unwrap_err();
+    assert!(matches!(err, ModbusErr::Exception { function: 15, code: ExceptionCode::GatewayTargetFailed }));
+    assert_eq!(err.to_string(), "function 15 exception: gateway target device failed to respond");
+
+    let mut transport = reply(&[0x81, 0x42]);
+    assert!(matches!(
+        master.read_coils(&mut transport, 0, 1),
+        Err(ModbusErr::Exception { function: 1, code: ExceptionCode::Unknown(0x42) })
+    ));
+
+    let mut transport = reply(&[1, 1, 0b101]);
+    assert_eq!(master.read_coils(&mut transport, 0, 3).unwrap(), vec![true, false, true]);
+
+    let mut transport = reply(&[3, 4, 0, 1]);
+    assert!(master.read_holdings(&mut transport, 10, 2).is_err());
+
+    let mut transport = reply(&[4, 2, 0, 1]);
+    assert!(matches!(master.read_holdings(&mut transport, 10, 1), Err(ModbusErr::Rmodbus(rmodbus::ErrorKind::FrameBroken))));
+
+    let mut transport = reply(&[6, 0, 5, 0, 8]);
+    assert!(master.write_holding(&mut transport, 5, 7).is_err());
+
+    let mut transport = reply(&[16, 0, 5, 0, 2]);
+    assert!(master.write_multipl_holding(&mut transport, 5, vec![1, 2]).is_ok());
+
+    let mut truncated = reply(&[3, 4, 0, 1, 0x12, 0x34]);
+    truncated.response.get_mut().truncate(5);
+    assert!(matches!(master.read_holdings(&mut truncated, 10, 2), Err(ModbusErr::Io(_))));
+
+    assert!(master.read_holdings(&mut reply(&[]), 0, 0).is_err());
+    assert!(master.read_holdings(&mut reply(&[]), 0, 126).is_err());
+
+    let mapped = Acton::mapped(super::modbus_mapping::Mapping::read(MemoryArea::Holdings, 4, 1, MemoryArea::Holdings, 0).unit(7)).unwrap();
+    let mut memory = crate::memory::PlcMemory::new(1, 1, 1, 1, 0);
+    let mut transport = Loopback {
+        response: io::Cursor::new(modbus_pdu::wrap(ModbusProto::Rtu, 7, 0, &[3, 2, 0, 9])),
+        request: Vec::new(),
+    };
+    master.execute_action(&mapped, &mut memory, &mut transport).unwrap();
+    assert_eq!(transport.request, modbus_pdu::wrap(ModbusProto::Rtu, 7, 0, &[3, 0, 4, 0, 1]));
+    assert_eq!(memory.get_holding(0).unwrap(), 9);
+
+    let echo = Acton::cycle_diagnostics(0, 0xa5a5, std::time::Duration::ZERO, |ctx, data| ctx.set_holding(0, data).unwrap());
+    let mut transport = reply(&[8, 0, 0, 0xa5, 0xa5]);
+    master.execute_action(&echo, &mut memory, &mut transport).unwrap();
+    assert_eq!(transport.request, modbus_pdu::wrap(ModbusProto::Rtu, 1, 0, &[8, 0, 0, 0xa5, 0xa5]));
+    assert_eq!(memory.get_holding(0).unwrap(), 0xa5a5);
 }
@@ -1,29 +1,177 @@
-use std::{time};
-use super::modbus_error::ModbusErr;
-use rmodbus::server::context::ModbusContext;
+use std::{time, io};
+use super::modbus_error::{ModbusErr, ExceptionCode};
+use super::modbus_quality::QualityTable;
+use super::modbus_mapping::{Mapping, Direction, Trigger};
+use super::modbus_mapping_error::MappingErr;
+use crate::memory::MemoryArea;
+use crate::memory::DataMemory;
 use std::cell::RefCell;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Quality {
+    Fresh,
+    Stale,
+    Bad,
+    CommFailure,
+}
+
+impl Quality {
+    pub fn to_code(self) -> u16 {
+        match self {
+            Self::Fresh => 0,
+            Self::Stale => 1,
+            Self::Bad => 2,
+            Self::CommFailure => 3,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ActonState {
+    quality: Quality,
+    failures: u32,
+    updated: Option<time::SystemTime>,
+}
+
+impl ActonState {
+    fn update(&mut self, result: &Result<(), ModbusErr>, bad_after: u32) {
+        let Err(err) = result else {
+            self.failures = 0;
+            self.quality = Quality::Fresh;
+            self.updated = Some(time::SystemTime::now());
+            return;
+        };
+
+        self.failures += 1;
+
+        if !is_comm_failure(err) {
+            self.quality = Quality::Bad;
+        } else if self.failures >= bad_after || !matches!(self.quality, Quality::Fresh | Quality::Stale) {
+            self.quality = Quality::CommFailure;
+        } else {
+            self.quality = Quality::Stale;
+        }
+    }
+}
+
+struct ActonPolicy {
+    timeout: Option<time::Duration>,
+    retries: Option<u32>,
+    bad_after: u32,
+    state: RefCell<ActonState>,
+    block: Option<(QualityTable, String)>,
+    max_age: Option<time::Duration>,
+    status_register: Option<u16>,
+}
+
+impl ActonPolicy {
+    fn new() -> Self {
+        Self {
+            timeout: None,
+            retries: None,
+            bad_after: 3,
+            state: RefCell::new(ActonState { quality: Quality::CommFailure, failures: 0, updated: None }),
+            block: None,
+            max_age: None,
+            status_register: None,
+        }
+    }
+
+    fn get_quality(&self) -> Quality {
+        let state = self.state.borrow();
+        let expired = self.max_age.is_some_and(|max| {
+            state.updated.is_some_and(|t| t.elapsed().unwrap_or_default() > max)
+        });
+
+        match state.quality {
+            Quality::Fresh if expired => Quality::Stale,
+            quality => quality,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum ReadKind {
+    Coils,
+    Discretes,
+    Holdings,
+    Inputs,
+}
+
+impl ReadKind {
+    pub(crate) fn from_area(area: MemoryArea) -> Option<Self> {
+        match area {
+            MemoryArea::Coils => Some(Self::Coils),
+            MemoryArea::Discretes => Some(Self::Discretes),
+            MemoryArea::Holdings => Some(Self::Holdings),
+            MemoryArea::Inputs => Some(Self::Inputs),
+            MemoryArea::Markers => None,
+        }
+    }
+
+    pub(crate) fn get_limit(self) -> u16 {
+        match self {
+            Self::Coils | Self::Discretes => 2000,
+            Self::Holdings | Self::Inputs => 125,
+        }
+    }
+}
+
+pub(crate) enum ReadData {
+    Bits(Vec<bool>),
+    Words(Vec<u16>),
+}
+
 pub struct ActonData<U, K> {
     offset: u16,
     count: u16,
     type_action: TypeAction,
-    handler: fn(&mut ModbusContext, U) -> K,
+    handler: fn(&mut dyn DataMemory, U) -> K,
+    policy: ActonPolicy,
 }
 
 impl<U, K> ActonData<U, K> {
+    fn new(offset: u16, count: u16, type_action: TypeAction, handler: fn(&mut dyn DataMemory, U) -> K) -> Self {
+        Self { offset, count, type_action, handler, policy: ActonPolicy::new() }
+    }
+
     pub fn get_offset(&self) -> u16 { self.offset }
     pub fn get_count(&self) -> u16 { self.count }
+    pub fn get_quality(&self) -> Quality { self.policy.get_quality() }
+    pub fn get_failures(&self) -> u32 { self.policy.state.borrow().failures }
     
-    pub fn need_run(&self, context: &mut ModbusContext) -> Result<bool, ModbusErr> {
+    pub fn need_run(&self, context: &mut dyn DataMemory) -> Result<bool, ModbusErr> {
         self.type_action.need_run(context)
     }
+
+    pub(crate) fn is_edge(&self) -> bool {
+        self.type_action.is_edge()
+    }
     
-    pub fn handler(&self, context: &mut ModbusContext, data: U) -> K {
+    pub fn handler(&self, context: &mut dyn DataMemory, data: U) -> K {
         let exec = self.handler;
         exec(context, data)
     }
 }
 
+pub struct MappedData {
+    mapping: Mapping,
+    type_action: TypeAction,
+    policy: ActonPolicy,
+}
+
+impl MappedData {
+    pub fn get_mapping(&self) -> &Mapping { &self.mapping }
+
+    pub fn need_run(&self, context: &mut dyn DataMemory) -> Result<bool, ModbusErr> {
+        self.type_action.need_run(context)
+    }
+
+    pub(crate) fn is_edge(&self) -> bool {
+        self.type_action.is_edge()
+    }
+}
+
 pub enum TypeAction {
     Cycle(time::Duration, RefCell<time::Instant>),
     FrontColi(u16, RefCell<bool>),
@@ -31,7 +179,19 @@ pub enum TypeAction {
 }
 
 impl TypeAction {
-    fn need_run(&self, context: &mut ModbusContext) -> Result<bool, ModbusErr> {
+    fn from_trigger(trigger: Trigger) -> Self {
+        match trigger {
+            Trigger::Cycle(ms) => Self::Cycle(time::Duration::from_millis(ms), RefCell::new(time::Instant::now())),
+            Trigger::FrontCoil(coil) => Self::FrontColi(coil, RefCell::new(false)),
+            Trigger::FrontDiscrete(discrete) => Self::FrontDiscrete(discrete, RefCell::new(false)),
+        }
+    }
+
+    fn is_edge(&self) -> bool {
+        !matches!(self, Self::Cycle(..))
+    }
+
+    fn need_run(&self, context: &mut dyn DataMemory) -> Result<bool, ModbusErr> {
         match &self {
             Self::Cycle(t, i) => {
                 let time_left = i.borrow().elapsed();
@@ -67,6 +227,14 @@ pub enum Acton {
     WriteHolding(ActonData<(), u16>),
     WriteCoils(ActonData<(), Vec<bool>>),
     WriteHoldings(ActonData<(), Vec<u16>>),
+    MaskWriteHolding(ActonData<(), (u16, u16)>),
+    ReadWriteHoldings(ActonData<Vec<u16>, ()>, u16, fn(&mut dyn DataMemory, ()) -> Vec<u16>),
+    ReadDeviceId(ActonData<Vec<(u8, String)>, ()>, u8, u8),
+    ReportServerId(ActonData<Vec<u8>, ()>),
+    ReadFileRecord(ActonData<Vec<u16>, ()>, u16),
+    WriteFileRecord(ActonData<(), Vec<u16>>, u16),
+    Diagnostics(ActonData<u16, ()>, u16, u16),
+    Mapped(MappedData),
 }
 
 impl Acton {
@@ -74,279 +242,279 @@ impl Acton {
         offset: u16,
         count: u16,
         time: time::Duration,
-        handler: fn(&mut ModbusContext, Vec<bool>),
+        handler: fn(&mut dyn DataMemory, Vec<bool>),
     ) -> Self {
         
         let type_action = TypeAction::Cycle(time, RefCell::new(time::Instant::now())); 
         
-        Self::ReadCoils(ActonData { offset, count, type_action, handler })
+        Self::ReadCoils(ActonData::new(offset, count, type_action, handler))
     }
 
     pub fn front_coil_read_colis(
         offset: u16,
         count: u16,
         coil: u16,
-        handler: fn(&mut ModbusContext, Vec<bool>),
+        handler: fn(&mut dyn DataMemory, Vec<bool>),
     ) -> Self {
         
         let type_action = TypeAction::FrontColi(coil, RefCell::new(false)); 
         
-        Self::ReadCoils(ActonData { offset, count, type_action, handler })
+        Self::ReadCoils(ActonData::new(offset, count, type_action, handler))
     }
 
     pub fn front_discrete_read_colis(
         offset: u16,
         count: u16,
         coil: u16,
-        handler: fn(&mut ModbusContext, Vec<bool>),
+        handler: fn(&mut dyn DataMemory, Vec<bool>),
     ) -> Self {
         
         let type_action = TypeAction::FrontDiscrete(coil, RefCell::new(false)); 
         
-        Self::ReadCoils(ActonData { offset, count, type_action, handler })
+        Self::ReadCoils(ActonData::new(offset, count, type_action, handler))
     }
 
     pub fn cycle_read_discretes(
         offset: u16,
         count: u16,
         time: time::Duration,
-        handler: fn(&mut ModbusContext, Vec<bool>),
+        handler: fn(&mut dyn DataMemory, Vec<bool>),
     ) -> Self {
         
         let type_action = TypeAction::Cycle(time, RefCell::new(time::Instant::now())); 
         
-        Self::ReadDiscretes(ActonData { offset, count, type_action, handler })
+        Self::ReadDiscretes(ActonData::new(offset, count, type_action, handler))
     }
 
     pub fn front_coil_read_discretes(
         offset: u16,
         count: u16,
         coil: u16,
-        handler: fn(&mut ModbusContext, Vec<bool>),
+        handler: fn(&mut dyn DataMemory, Vec<bool>),
     ) -> Self {
         
         let type_action = TypeAction::FrontColi(coil, RefCell::new(false)); 
         
-        Self::ReadDiscretes(ActonData { offset, count, type_action, handler })
+        Self::ReadDiscretes(ActonData::new(offset, count, type_action, handler))
     }
 
     pub fn front_discrete_read_discretes(
         offset: u16,
         count: u16,
         coil: u16,
-        handler: fn(&mut ModbusContext, Vec<bool>),
+        handler: fn(&mut dyn DataMemory, Vec<bool>),
     ) -> Self {
         
         let type_action = TypeAction::FrontDiscrete(coil, RefCell::new(false)); 
         
-        Self::ReadDiscretes(ActonData { offset, count, type_action, handler })
+        Self::ReadDiscretes(ActonData::new(offset, count, type_action, handler))
     }
 
     pub fn cycle_read_inputs(
         offset: u16,
         count: u16,
         time: time::Duration,
-        handler: fn(&mut ModbusContext, Vec<u16>),
+        handler: fn(&mut dyn DataMemory, Vec<u16>),
     ) -> Self {
         
         let type_action = TypeAction::Cycle(time, RefCell::new(time::Instant::now())); 
         
-        Self::ReadInputs(ActonData { offset, count, type_action, handler })
+        Self::ReadInputs(ActonData::new(offset, count, type_action, handler))
     }
 
     pub fn front_coil_read_inputs(
         offset: u16,
         count: u16,
         coil: u16,
-        handler: fn(&mut ModbusContext, Vec<u16>),
+        handler: fn(&mut dyn DataMemory, Vec<u16>),
     ) -> Self {
         
         let type_action = TypeAction::FrontColi(coil, RefCell::new(false)); 
         
-        Self::ReadInputs(ActonData { offset, count, type_action, handler })
+        Self::ReadInputs(ActonData::new(offset, count, type_action, handler))
     }
 
     pub fn front_discrete_read_inputs(
         offset: u16,
         count: u16,
         coil: u16,
-        handler: fn(&mut ModbusContext, Vec<u16>),
+        handler: fn(&mut dyn DataMemory, Vec<u16>),
     ) -> Self {
         
         let type_action = TypeAction::FrontDiscrete(coil, RefCell::new(false)); 
         
-        Self::ReadInputs(ActonData { offset, count, type_action, handler })
+        Self::ReadInputs(ActonData::new(offset, count, type_action, handler))
     }
 
     pub fn cycle_read_holdings(
         offset: u16,
         count: u16,
         time: time::Duration,
-        handler: fn(&mut ModbusContext, Vec<u16>),
+        handler: fn(&mut dyn DataMemory, Vec<u16>),
     ) -> Self {
         
         let type_action = TypeAction::Cycle(time, RefCell::new(time::Instant::now())); 
         
-        Self::ReadHoldings(ActonData { offset, count, type_action, handler })
+        Self::ReadHoldings(ActonData::new(offset, count, type_action, handler))
     }
 
     pub fn front_coil_read_holdings(
         offset: u16,
         count: u16,
         coil: u16,
-        handler: fn(&mut ModbusContext, Vec<u16>),
+        handler: fn(&mut dyn DataMemory, Vec<u16>),
     ) -> Self {
         
         let type_action = TypeAction::FrontColi(coil, RefCell::new(false)); 
         
-        Self::ReadHoldings(ActonData { offset, count, type_action, handler })
+        Self::ReadHoldings(ActonData::new(offset, count, type_action, handler))
     }
 
     pub fn front_discrete_read_holdings(
         offset: u16,
         count: u16,
         coil: u16,
-        handler: fn(&mut ModbusContext, Vec<u16>),
+        handler: fn(&mut dyn DataMemory, Vec<u16>),
     ) -> Self {
         
         let type_action = TypeAction::FrontDiscrete(coil, RefCell::new(false)); 
         
-        Self::ReadHoldings(ActonData { offset, count, type_action, handler })
+        Self::ReadHoldings(ActonData::new(offset, count, type_action, handler))
     }
 
     pub fn cycle_write_coil(
         offset: u16,
         time: time::Duration,
-        handler: fn(&mut ModbusContext, ()) -> bool,
+        handler: fn(&mut dyn DataMemory, ()) -> bool,
     ) -> Self {
         
         let type_action = TypeAction::Cycle(time, RefCell::new(time::Instant::now())); 
         
-        Self::WriteCoil(ActonData { offset, count: 0, type_action, handler })
+        Self::WriteCoil(ActonData::new(offset, 0, type_action, handler))
     }
 
     pub fn front_coil_write_coil(
         offset: u16,
         coil: u16,
-        handler: fn(&mut ModbusContext, ()) -> bool,
+        handler: fn(&mut dyn DataMemory, ()) -> bool,
     ) -> Self {
         
         let type_action = TypeAction::FrontColi(coil, RefCell::new(false)); 
         
-        Self::WriteCoil(ActonData { offset, count: 0, type_action, handler })
+        Self::WriteCoil(ActonData::new(offset, 0, type_action, handler))
     }
 
     pub fn front_discrete_write_coil(
         offset: u16,
         coil: u16,
-        handler: fn(&mut ModbusContext, ()) -> bool,
+        handler: fn(&mut dyn DataMemory, ()) -> bool,
     ) -> Self {
         
         let type_action = TypeAction::FrontDiscrete(coil, RefCell::new(false)); 
         
-        Self::WriteCoil(ActonData { offset, count: 0, type_action, handler })
+        Self::WriteCoil(ActonData::new(offset, 0, type_action, handler))
     }
 
     pub fn cycle_write_coils(
         offset: u16,
         time: time::Duration,
-        handler: fn(&mut ModbusContext, ()) -> Vec<bool>,
+        handler: fn(&mut dyn DataMemory, ()) -> Vec<bool>,
     ) -> Self {
         
         let type_action = TypeAction::Cycle(time, RefCell::new(time::Instant::now())); 
         
-        Self::WriteCoils(ActonData { offset, count: 0, type_action, handler })
+        Self::WriteCoils(ActonData::new(offset, 0, type_action, handler))
     }
 
     pub fn front_coil_coils(
         offset: u16,
         coil: u16,
-        handler: fn(&mut ModbusContext, ()) -> Vec<bool>,
+        handler: fn(&mut dyn DataMemory, ()) -> Vec<bool>,
     ) -> Self {
         
         let type_action = TypeAction::FrontColi(coil, RefCell::new(false)); 
         
-        Self::WriteCoils(ActonData { offset, count: 0, type_action, handler })
+        Self::WriteCoils(ActonData::new(offset, 0, type_action, handler))
     }
 
     pub fn front_discrete_coils(
         offset: u16,
         coil: u16,
-        handler: fn(&mut ModbusContext, ()) -> Vec<bool>,
+        handler: fn(&mut dyn DataMemory, ()) -> Vec<bool>,
     ) -> Self {
         
         let type_action = TypeAction::FrontDiscrete(coil, RefCell::new(false)); 
         
-        Self::WriteCoils(ActonData { offset, count: 0, type_action, handler })
+        Self::WriteCoils(ActonData::new(offset, 0, type_action, handler))
     }
     
     pub fn cycle_write_holding(
         offset: u16,
         time: time::Duration,
-        handler: fn(&mut ModbusContext, ()) -> u16,
+        handler: fn(&mut dyn DataMemory, ()) -> u16,
     ) -> Self {
         
         let type_action = TypeAction::Cycle(time, RefCell::new(time::Instant::now())); 
         
-        Self::WriteHolding(ActonData { offset, count: 0, type_action, handler })
+        Self::WriteHolding(ActonData::new(offset, 0, type_action, handler))
     }
 
     pub fn front_coil_write_holding(
         offset: u16,
         coil: u16,
-        handler: fn(&mut ModbusContext, ()) -> u16,
+        handler: fn(&mut dyn DataMemory, ()) -> u16,
     ) -> Self {
         
         let type_action = TypeAction::FrontColi(coil, RefCell::new(false)); 
         
-        Self::WriteHolding(ActonData { offset, count: 0, type_action, handler })
+        Self::WriteHolding(ActonData::new(offset, 0, type_action, handler))
     }
 
     pub fn front_discrete_write_holding(
         offset: u16,
         coil: u16,
-        handler: fn(&mut ModbusContext, ()) -> u16,
+        handler: fn(&mut dyn DataMemory, ()) -> u16,
     ) -> Self {
         
         let type_action = TypeAction::FrontDiscrete(coil, RefCell::new(false)); 
         
-        Self::WriteHolding(ActonData { offset, count: 0, type_action, handler })
+        Self::WriteHolding(ActonData::new(offset, 0, type_action, handler))
     }
 
     pub fn cycle_write_holdings(
         offset: u16,
         time: time::Duration,
-        handler: fn(&mut ModbusContext, ()) -> Vec<u16>,
+        handler: fn(&mut dyn DataMemory, ()) -> Vec<u16>,
     ) -> Self {
         
         let type_action = TypeAction::Cycle(time, RefCell::new(time::Instant::now())); 
         
-        Self::WriteHoldings(ActonData { offset, count: 0, type_action, handler })
+        Self::WriteHoldings(ActonData::new(offset, 0, type_action, handler))
     }
 
     pub fn front_coil_holdings(
         offset: u16,
         coil: u16,
-        handler: fn(&mut ModbusContext, ()) -> Vec<u16>,
+        handler: fn(&mut dyn DataMemory, ()) -> Vec<u16>,
     ) -> Self {
         
         let type_action = TypeAction::FrontColi(coil, RefCell::new(false)); 
         
-        Self::WriteHoldings(ActonData { offset, count: 0, type_action, handler })
+        Self::WriteHoldings(ActonData::new(offset, 0, type_action, handler))
     }
 
     pub fn front_discrete_holdings(
         offset: u16,
         coil: u16,
-        handler: fn(&mut ModbusContext, ()) -> Vec<u16>,
+        handler: fn(&mut dyn DataMemory, ()) -> Vec<u16>,
     ) -> Self {
         
         let type_action = TypeAction::FrontDiscrete(coil, RefCell::new(false)); 
         
-        Self::WriteHoldings(ActonData { offset, count: 0, type_action, handler })
+        Self::WriteHoldings(ActonData::new(offset, 0, type_action, handler))
     }
 
-    pub fn need_run(&self, context: &mut ModbusContext) -> Result<bool, ModbusErr> {
+    pub fn need_run(&self, context: &mut dyn DataMemory) -> Result<bool, ModbusErr> {
         match self {
             Self::ReadCoils(data) => { data.need_run(context) }
             Self::ReadDiscretes(data) => { data.need_run(context) }
@@ -356,6 +524,633 @@ impl Acton {
             Self::WriteCoils(data) => { data.need_run(context) }
             Self::WriteHolding(data) => { data.need_run(context) }
             Self::WriteHoldings(data) => { data.need_run(context) }
+            Self::MaskWriteHolding(data) => { data.need_run(context) }
+            Self::ReadWriteHoldings(data, ..) => { data.need_run(context) }
+            Self::ReadDeviceId(data, ..) => { data.need_run(context) }
+            Self::ReportServerId(data) => { data.need_run(context) }
+            Self::ReadFileRecord(data, _) => { data.need_run(context) }
+            Self::WriteFileRecord(data, _) => { data.need_run(context) }
+            Self::Diagnostics(data, ..) => { data.need_run(context) }
+            Self::Mapped(data) => { data.need_run(context) }
+        }
+    }
+
+    pub(crate) fn is_edge(&self) -> bool {
+        match self {
+            Self::ReadCoils(data) => { data.is_edge() }
+            Self::ReadDiscretes(data) => { data.is_edge() }
+            Self::ReadHoldings(data) => { data.is_edge() }
+            Self::ReadInputs(data) => { data.is_edge() }
+            Self::WriteCoil(data) => { data.is_edge() }
+            Self::WriteCoils(data) => { data.is_edge() }
+            Self::WriteHolding(data) => { data.is_edge() }
+            Self::WriteHoldings(data) => { data.is_edge() }
+            Self::MaskWriteHolding(data) => { data.is_edge() }
+            Self::ReadWriteHoldings(data, ..) => { data.is_edge() }
+            Self::ReadDeviceId(data, ..) => { data.is_edge() }
+            Self::ReportServerId(data) => { data.is_edge() }
+            Self::ReadFileRecord(data, _) => { data.is_edge() }
+            Self::WriteFileRecord(data, _) => { data.is_edge() }
+            Self::Diagnostics(data, ..) => { data.is_edge() }
+            Self::Mapped(data) => { data.is_edge() }
+        }
+    }
+
+    pub(crate) fn is_read(&self) -> bool {
+        match self {
+            Self::ReadCoils(_) | Self::ReadDiscretes(_) | Self::ReadHoldings(_) | Self::ReadInputs(_) => true,
+            Self::ReadWriteHoldings(..) | Self::ReadDeviceId(..) | Self::ReportServerId(_) | Self::ReadFileRecord(..) => true,
+            Self::Mapped(data) => data.get_mapping().get_direction() == Direction::Read,
+            _ => false,
         }
     }
+
+    pub fn cycle_mask_write_holding(
+        offset: u16,
+        time: time::Duration,
+        handler: fn(&mut dyn DataMemory, ()) -> (u16, u16),
+    ) -> Self {
+
+        let type_action = TypeAction::Cycle(time, RefCell::new(time::Instant::now()));
+
+        Self::MaskWriteHolding(ActonData::new(offset, 0, type_action, handler))
+    }
+
+    pub fn front_coil_mask_write_holding(
+        offset: u16,
+        coil: u16,
+        handler: fn(&mut dyn DataMemory, ()) -> (u16, u16),
+    ) -> Self {
+
+        let type_action = TypeAction::FrontColi(coil, RefCell::new(false));
+
+        Self::MaskWriteHolding(ActonData::new(offset, 0, type_action, handler))
+    }
+
+    pub fn front_discrete_mask_write_holding(
+        offset: u16,
+        coil: u16,
+        handler: fn(&mut dyn DataMemory, ()) -> (u16, u16),
+    ) -> Self {
+
+        let type_action = TypeAction::FrontDiscrete(coil, RefCell::new(false));
+
+        Self::MaskWriteHolding(ActonData::new(offset, 0, type_action, handler))
+    }
+
+    pub fn cycle_read_write_holdings(
+        read_offset: u16,
+        read_count: u16,
+        write_offset: u16,
+        time: time::Duration,
+        write: fn(&mut dyn DataMemory, ()) -> Vec<u16>,
+        handler: fn(&mut dyn DataMemory, Vec<u16>),
+    ) -> Self {
+
+        let type_action = TypeAction::Cycle(time, RefCell::new(time::Instant::now()));
+
+        Self::ReadWriteHoldings(ActonData::new(read_offset, read_count, type_action, handler), write_offset, write)
+    }
+
+    pub fn front_coil_read_write_holdings(
+        read_offset: u16,
+        read_count: u16,
+        write_offset: u16,
+        coil: u16,
+        write: fn(&mut dyn DataMemory, ()) -> Vec<u16>,
+        handler: fn(&mut dyn DataMemory, Vec<u16>),
+    ) -> Self {
+
+        let type_action = TypeAction::FrontColi(coil, RefCell::new(false));
+
+        Self::ReadWriteHoldings(ActonData::new(read_offset, read_count, type_action, handler), write_offset, write)
+    }
+
+    pub fn front_discrete_read_write_holdings(
+        read_offset: u16,
+        read_count: u16,
+        write_offset: u16,
+        coil: u16,
+        write: fn(&mut dyn DataMemory, ()) -> Vec<u16>,
+        handler: fn(&mut dyn DataMemory, Vec<u16>),
+    ) -> Self {
+
+        let type_action = TypeAction::FrontDiscrete(coil, RefCell::new(false));
+
+        Self::ReadWriteHoldings(ActonData::new(read_offset, read_count, type_action, handler), write_offset, write)
+    }
+
+    pub fn cycle_read_device_id(
+        code: u8,
+        object_id: u8,
+        time: time::Duration,
+        handler: fn(&mut dyn DataMemory, Vec<(u8, String)>),
+    ) -> Self {
+
+        let type_action = TypeAction::Cycle(time, RefCell::new(time::Instant::now()));
+
+        Self::ReadDeviceId(ActonData::new(0, 0, type_action, handler), code, object_id)
+    }
+
+    pub fn front_coil_read_device_id(
+        code: u8,
+        object_id: u8,
+        coil: u16,
+        handler: fn(&mut dyn DataMemory, Vec<(u8, String)>),
+    ) -> Self {
+
+        let type_action = TypeAction::FrontColi(coil, RefCell::new(false));
+
+        Self::ReadDeviceId(ActonData::new(0, 0, type_action, handler), code, object_id)
+    }
+
+    pub fn front_discrete_read_device_id(
+        code: u8,
+        object_id: u8,
+        coil: u16,
+        handler: fn(&mut dyn DataMemory, Vec<(u8, String)>),
+    ) -> Self {
+
+        let type_action = TypeAction::FrontDiscrete(coil, RefCell::new(false));
+
+        Self::ReadDeviceId(ActonData::new(0, 0, type_action, handler), code, object_id)
+    }
+
+    pub fn cycle_report_server_id(
+        time: time::Duration,
+        handler: fn(&mut dyn DataMemory, Vec<u8>),
+    ) -> Self {
+
+        let type_action = TypeAction::Cycle(time, RefCell::new(time::Instant::now()));
+
+        Self::ReportServerId(ActonData::new(0, 0, type_action, handler))
+    }
+
+    pub fn front_coil_report_server_id(
+        coil: u16,
+        handler: fn(&mut dyn DataMemory, Vec<u8>),
+    ) -> Self {
+
+        let type_action = TypeAction::FrontColi(coil, RefCell::new(false));
+
+        Self::ReportServerId(ActonData::new(0, 0, type_action, handler))
+    }
+
+    pub fn front_discrete_report_server_id(
+        coil: u16,
+        handler: fn(&mut dyn DataMemory, Vec<u8>),
+    ) -> Self {
+
+        let type_action = TypeAction::FrontDiscrete(coil, RefCell::new(false));
+
+        Self::ReportServerId(ActonData::new(0, 0, type_action, handler))
+    }
+
+    pub fn cycle_read_file_record(
+        file: u16,
+        record: u16,
+        length: u16,
+        time: time::Duration,
+        handler: fn(&mut dyn DataMemory, Vec<u16>),
+    ) -> Self {
+
+        let type_action = TypeAction::Cycle(time, RefCell::new(time::Instant::now()));
+
+        Self::ReadFileRecord(ActonData::new(record, length, type_action, handler), file)
+    }
+
+    pub fn front_coil_read_file_record(
+        file: u16,
+        record: u16,
+        length: u16,
+        coil: u16,
+        handler: fn(&mut dyn DataMemory, Vec<u16>),
+    ) -> Self {
+
+        let type_action = TypeAction::FrontColi(coil, RefCell::new(false));
+
+        Self::ReadFileRecord(ActonData::new(record, length, type_action, handler), file)
+    }
+
+    pub fn front_discrete_read_file_record(
+        file: u16,
+        record: u16,
+        length: u16,
+        coil: u16,
+        handler: fn(&mut dyn DataMemory, Vec<u16>),
+    ) -> Self {
+
+        let type_action = TypeAction::FrontDiscrete(coil, RefCell::new(false));
+
+        Self::ReadFileRecord(ActonData::new(record, length, type_action, handler), file)
+    }
+
+    pub fn cycle_write_file_record(
+        file: u16,
+        record: u16,
+        time: time::Duration,
+        handler: fn(&mut dyn DataMemory, ()) -> Vec<u16>,
+    ) -> Self {
+
+        let type_action = TypeAction::Cycle(time, RefCell::new(time::Instant::now()));
+
+        Self::WriteFileRecord(ActonData::new(record, 0, type_action, handler), file)
+    }
+
+    pub fn front_coil_write_file_record(
+        file: u16,
+        record: u16,
+        coil: u16,
+        handler: fn(&mut dyn DataMemory, ()) -> Vec<u16>,
+    ) -> Self {
+
+        let type_action = TypeAction::FrontColi(coil, RefCell::new(false));
+
+        Self::WriteFileRecord(ActonData::new(record, 0, type_action, handler), file)
+    }
+
+    pub fn front_discrete_write_file_record(
+        file: u16,
+        record: u16,
+        coil: u16,
+        handler: fn(&mut dyn DataMemory, ()) -> Vec<u16>,
+    ) -> Self {
+
+        let type_action = TypeAction::FrontDiscrete(coil, RefCell::new(false));
+
+        Self::WriteFileRecord(ActonData::new(record, 0, type_action, handler), file)
+    }
+
+    pub fn cycle_diagnostics(
+        sub_function: u16,
+        data: u16,
+        time: time::Duration,
+        handler: fn(&mut dyn DataMemory, u16),
+    ) -> Self {
+
+        let type_action = TypeAction::Cycle(time, RefCell::new(time::Instant::now()));
+
+        Self::Diagnostics(ActonData::new(0, 0, type_action, handler), sub_function, data)
+    }
+
+    pub fn front_coil_diagnostics(
+        sub_function: u16,
+        data: u16,
+        coil: u16,
+        handler: fn(&mut dyn DataMemory, u16),
+    ) -> Self {
+
+        let type_action = TypeAction::FrontColi(coil, RefCell::new(false));
+
+        Self::Diagnostics(ActonData::new(0, 0, type_action, handler), sub_function, data)
+    }
+
+    pub fn front_discrete_diagnostics(
+        sub_function: u16,
+        data: u16,
+        coil: u16,
+        handler: fn(&mut dyn DataMemory, u16),
+    ) -> Self {
+
+        let type_action = TypeAction::FrontDiscrete(coil, RefCell::new(false));
+
+        Self::Diagnostics(ActonData::new(0, 0, type_action, handler), sub_function, data)
+    }
+
+    pub fn mapped(mapping: Mapping) -> Result<Self, MappingErr> {
+        mapping.validate()?;
+
+        let type_action = TypeAction::from_trigger(mapping.get_trigger());
+
+        Ok(Self::Mapped(MappedData { mapping, type_action, policy: ActonPolicy::new() }))
+    }
+
+    pub fn load_mappings<P: AsRef<std::path::Path>>(path: P) -> Result<Vec<Self>, MappingErr> {
+        Mapping::from_file(path)?.into_iter().map(Self::mapped).collect()
+    }
+
+    pub fn timeout(mut self, timeout: time::Duration) -> Self {
+        self.policy_mut().timeout = Some(timeout);
+        self
+    }
+
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.policy_mut().retries = Some(retries);
+        self
+    }
+
+    pub fn bad_after(mut self, failures: u32) -> Self {
+        self.policy_mut().bad_after = failures.max(1);
+        self
+    }
+
+    pub fn block(mut self, table: &QualityTable, name: &str) -> Self {
+        let policy = self.policy_mut();
+        table.register(name, policy.max_age);
+        policy.block = Some((table.clone(), name.to_string()));
+        self
+    }
+
+    pub fn max_age(mut self, max_age: time::Duration) -> Self {
+        let policy = self.policy_mut();
+        policy.max_age = Some(max_age);
+
+        if let Some((table, name)) = &policy.block {
+            table.register(name, policy.max_age);
+        }
+
+        self
+    }
+
+    pub fn status_register(mut self, reg: u16) -> Self {
+        self.policy_mut().status_register = Some(reg);
+        self
+    }
+
+    pub fn get_timeout(&self) -> Option<time::Duration> {
+        self.policy().timeout
+    }
+
+    pub fn get_quality(&self) -> Quality {
+        self.policy().get_quality()
+    }
+
+    pub fn get_updated(&self) -> Option<time::SystemTime> {
+        self.policy().state.borrow().updated
+    }
+
+    pub fn get_failures(&self) -> u32 {
+        self.policy().state.borrow().failures
+    }
+
+    pub fn get_block(&self) -> Option<&str> {
+        self.policy().block.as_ref().map(|(_, name)| name.as_str())
+    }
+
+    pub(crate) fn attempt(
+        &self,
+        default_retries: u32,
+        exec: impl FnMut() -> Result<(), ModbusErr>,
+    ) -> Result<(), ModbusErr> {
+
+        let result = retry(self.get_retries(default_retries), exec);
+
+        self.record(&result);
+
+        result
+    }
+
+    pub(crate) fn get_retries(&self, default_retries: u32) -> u32 {
+        self.policy().retries.unwrap_or(default_retries)
+    }
+
+    pub(crate) fn record(&self, result: &Result<(), ModbusErr>) {
+        let policy = self.policy();
+
+        policy.state.borrow_mut().update(result, policy.bad_after);
+
+        if let Some((table, name)) = &policy.block {
+            let error = result.as_ref().err().map(|e| e.to_string());
+            let state = *policy.state.borrow();
+            table.update(name, state.quality, state.updated, error);
+        }
+    }
+
+    pub(crate) fn read_range(&self) -> Option<(ReadKind, u16, u16)> {
+        match self {
+            Self::ReadCoils(data) => Some((ReadKind::Coils, data.offset, data.count)),
+            Self::ReadDiscretes(data) => Some((ReadKind::Discretes, data.offset, data.count)),
+            Self::ReadHoldings(data) => Some((ReadKind::Holdings, data.offset, data.count)),
+            Self::ReadInputs(data) => Some((ReadKind::Inputs, data.offset, data.count)),
+            Self::Mapped(data) if data.mapping.get_direction() == Direction::Read && data.mapping.get_unit().is_none() => {
+                let mapping = &data.mapping;
+                ReadKind::from_area(mapping.get_remote_area())
+                    .map(|kind| (kind, mapping.get_remote_offset(), mapping.get_count()))
+            },
+            _ => None,
+        }
+    }
+
+    pub(crate) fn deliver(&self, context: &mut dyn DataMemory, start: u16, block: &ReadData) -> Result<(), rmodbus::ErrorKind> {
+        let slice = |data_offset: u16, count: u16| {
+            let from = (data_offset - start) as usize;
+            from..from + count as usize
+        };
+
+        match (self, block) {
+            (Self::ReadCoils(data), ReadData::Bits(bits)) | (Self::ReadDiscretes(data), ReadData::Bits(bits)) => {
+                data.handler(context, bits[slice(data.offset, data.count)].to_vec());
+            },
+            (Self::ReadHoldings(data), ReadData::Words(words)) | (Self::ReadInputs(data), ReadData::Words(words)) => {
+                data.handler(context, words[slice(data.offset, data.count)].to_vec());
+            },
+            (Self::Mapped(data), ReadData::Bits(bits)) => {
+                let range = slice(data.mapping.get_remote_offset(), data.mapping.get_count());
+                data.mapping.store_bits(context, &bits[range])?;
+            },
+            (Self::Mapped(data), ReadData::Words(words)) => {
+                let range = slice(data.mapping.get_remote_offset(), data.mapping.get_count());
+                data.mapping.store_words(context, &words[range])?;
+            },
+            _ => {},
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn publish(&self, context: &mut dyn DataMemory) -> Result<(), rmodbus::ErrorKind> {
+        let policy = self.policy();
+
+        let Some(reg) = policy.status_register else {
+            return Ok(());
+        };
+
+        let quality = match &policy.block {
+            Some((table, name)) => table.get_quality(name),
+            None => policy.get_quality(),
+        };
+
+        context.set_holding(reg, quality.to_code())
+    }
+
+    fn policy(&self) -> &ActonPolicy {
+        match self {
+            Self::ReadCoils(data) => &data.policy,
+            Self::ReadDiscretes(data) => &data.policy,
+            Self::ReadHoldings(data) => &data.policy,
+            Self::ReadInputs(data) => &data.policy,
+            Self::WriteCoil(data) => &data.policy,
+            Self::WriteCoils(data) => &data.policy,
+            Self::WriteHolding(data) => &data.policy,
+            Self::WriteHoldings(data) => &data.policy,
+            Self::MaskWriteHolding(data) => &data.policy,
+            Self::ReadWriteHoldings(data, ..) => &data.policy,
+            Self::ReadDeviceId(data, ..) => &data.policy,
+            Self::ReportServerId(data) => &data.policy,
+            Self::ReadFileRecord(data, _) => &data.policy,
+            Self::WriteFileRecord(data, _) => &data.policy,
+            Self::Diagnostics(data, ..) => &data.policy,
+            Self::Mapped(data) => &data.policy,
+        }
+    }
+
+    fn policy_mut(&mut self) -> &mut ActonPolicy {
+        match self {
+            Self::ReadCoils(data) => &mut data.policy,
+            Self::ReadDiscretes(data) => &mut data.policy,
+            Self::ReadHoldings(data) => &mut data.policy,
+            Self::ReadInputs(data) => &mut data.policy,
+            Self::WriteCoil(data) => &mut data.policy,
+            Self::WriteCoils(data) => &mut data.policy,
+            Self::WriteHolding(data) => &mut data.policy,
+            Self::WriteHoldings(data) => &mut data.policy,
+            Self::MaskWriteHolding(data) => &mut data.policy,
+            Self::ReadWriteHoldings(data, ..) => &mut data.policy,
+            Self::ReadDeviceId(data, ..) => &mut data.policy,
+            Self::ReportServerId(data) => &mut data.policy,
+            Self::ReadFileRecord(data, _) => &mut data.policy,
+            Self::WriteFileRecord(data, _) => &mut data.policy,
+            Self::Diagnostics(data, ..) => &mut data.policy,
+            Self::Mapped(data) => &mut data.policy,
+        }
+    }
+}
+
+pub(crate) fn mark_offline(actions: &[Acton], context: &mut dyn DataMemory) -> Result<(), ModbusErr> {
+    for action in actions.iter() {
+        if !action.is_edge() && action.need_run(context)? {
+            let _ = action.attempt(0, || Err(io::Error::from(io::ErrorKind::NotConnected).into()));
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) fn publish_all(actions: &[Acton], context: &mut dyn DataMemory) -> Result<(), rmodbus::ErrorKind> {
+    for action in actions.iter() {
+        action.publish(context)?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn retry(retries: u32, mut exec: impl FnMut() -> Result<(), ModbusErr>) -> Result<(), ModbusErr> {
+    let mut result = exec();
+
+    for _ in 0..retries {
+        match &result {
+            Err(err) if is_retryable(err) => result = exec(),
+            _ => break,
+        }
+    }
+
+    result
+}
+
+fn is_comm_error(err: &rmodbus::ErrorKind) -> bool {
+    matches!(
+        err,
+        rmodbus::ErrorKind::FrameBroken | rmodbus::ErrorKind::FrameCRCError | rmodbus::ErrorKind::CommunicationError
+    )
+}
+
+fn is_comm_failure(err: &ModbusErr) -> bool {
+    match err {
+        ModbusErr::Io(_) => true,
+        ModbusErr::Rmodbus(e) => is_comm_error(e),
+        ModbusErr::Exception { .. } => false,
+    }
+}
+
+fn is_retryable(err: &ModbusErr) -> bool {
+    match err {
+        ModbusErr::Io(e) => e.kind() == io::ErrorKind::TimedOut || e.kind() == io::ErrorKind::WouldBlock,
+        ModbusErr::Rmodbus(e) => is_comm_error(e),
+        ModbusErr::Exception { code, .. } => *code == ExceptionCode::SlaveDeviceBusy,
+    }
+}
+
+#[test]
+fn test_acton_retries_and_quality() {
+    let action = Acton::cycle_read_holdings(0, 2, time::Duration::from_millis(10), |_, _| {})
+        .retries(2)
+        .bad_after(2);
+
+    assert_eq!(action.get_quality(), Quality::CommFailure);
+    assert_eq!(action.get_updated(), None);
+
+    let mut calls = 0;
+    let result = action.attempt(0, || {
+        calls += 1;
+        if calls < 3 { Err(ModbusErr::Io(io::ErrorKind::TimedOut.into())) } else { Ok(()) }
+    });
+    assert!(result.is_ok());
+    assert_eq!(calls, 3);
+    assert_eq!(action.get_quality(), Quality::Fresh);
+    assert!(action.get_updated().is_some());
+
+    calls = 0;
+    let result = action.attempt(0, || {
+        calls += 1;
+        Err(ModbusErr::Io(io::ErrorKind::ConnectionReset.into()))
+    });
+    assert!(result.is_err());
+    assert_eq!(calls, 1);
+    assert_eq!(action.get_quality(), Quality::Stale);
+
+    let _ = action.attempt(0, || Err(ModbusErr::Io(io::ErrorKind::TimedOut.into())));
+    assert_eq!(action.get_quality(), Quality::CommFailure);
+
+    let _ = action.attempt(0, || Err(ModbusErr::Rmodbus(rmodbus::ErrorKind::IllegalDataAddress)));
+    assert_eq!(action.get_quality(), Quality::Bad);
+    assert_eq!(action.get_failures(), 3);
+}
+
+#[test]
+fn test_acton_block_quality() {
+    use crate::memory::PlcMemory;
+
+    let table = QualityTable::new();
+    let action = Acton::cycle_read_inputs(0, 2, time::Duration::from_millis(10), |_, _| {})
+        .block(&table, "tank")
+        .bad_after(2)
+        .status_register(9);
+    let mut memory = PlcMemory::new(10, 10, 10, 10, 0);
+
+    action.publish(&mut memory).unwrap();
+    assert_eq!(memory.get_holding(9).unwrap(), Quality::CommFailure.to_code());
+
+    action.attempt(0, || Ok(())).unwrap();
+    action.publish(&mut memory).unwrap();
+    assert_eq!(table.get_quality("tank"), Quality::Fresh);
+    assert_eq!(memory.get_holding(9).unwrap(), 0);
+
+    let _ = action.attempt(0, || Err(ModbusErr::Io(io::ErrorKind::TimedOut.into())));
+    assert_eq!(table.get_quality("tank"), Quality::Stale);
+
+    let _ = action.attempt(0, || Err(ModbusErr::Io(io::ErrorKind::TimedOut.into())));
+    assert_eq!(table.get_quality("tank"), Quality::CommFailure);
+
+    let _ = action.attempt(0, || Err(ModbusErr::Rmodbus(rmodbus::ErrorKind::IllegalDataAddress)));
+    action.publish(&mut memory).unwrap();
+    assert_eq!(table.get_quality("tank"), Quality::Bad);
+    assert_eq!(memory.get_holding(9).unwrap(), 2);
+    assert!(table.get("tank").unwrap().get_updated().is_some());
+}
+
+#[test]
+fn test_mark_offline_keeps_edges() {
+    use crate::memory::PlcMemory;
+
+    let actions = vec![
+        Acton::front_coil_read_holdings(0, 1, 3, |_, _| {}),
+        Acton::cycle_read_holdings(0, 1, time::Duration::ZERO, |_, _| {}),
+    ];
+    let mut memory = PlcMemory::new(10, 10, 10, 10, 0);
+    memory.set_coil(3, true).unwrap();
+
+    mark_offline(&actions, &mut memory).unwrap();
+    assert_eq!(actions[0].get_failures(), 0);
+    assert_eq!(actions[1].get_failures(), 1);
+    assert!(actions[0].need_run(&mut memory).unwrap());
 }
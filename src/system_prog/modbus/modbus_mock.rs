@@ -0,0 +1,260 @@
+use std::{io, thread, time::Duration, collections::VecDeque};
+use rmodbus::ModbusProto;
+use rmodbus::server::context::ModbusContext;
+use super::modbus_slave::ModbusSlave;
+use super::modbus_access::AccessRule;
+use super::modbus_error::ExceptionCode;
+use super::modbus_pdu;
+
+#[derive(Default)]
+pub struct MockTransport {
+    input: VecDeque<u8>,
+    written: Vec<u8>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.input.extend(bytes);
+    }
+
+    pub fn push_pdu(&mut self, proto: ModbusProto, unit: u8, tr_id: u16, pdu: &[u8]) {
+        self.push(&modbus_pdu::wrap(proto, unit, tr_id, pdu));
+    }
+
+    pub fn get_written(&self) -> &[u8] { &self.written }
+
+    pub fn take_written(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.written)
+    }
+
+    pub fn pending(&self) -> usize { self.input.len() }
+}
+
+impl io::Read for MockTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.input.is_empty() && !buf.is_empty() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+
+        self.input.read(buf)
+    }
+}
+
+impl io::Write for MockTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> { Ok(()) }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fault {
+    Delay(Duration),
+    Timeout,
+    CrcError,
+    Exception(ExceptionCode),
+    PartialFrame(usize),
+}
+
+pub struct SimDevice {
+    proto: ModbusProto,
+    slave: ModbusSlave,
+    context: Box<ModbusContext>,
+    faults: VecDeque<Fault>,
+    output: MockTransport,
+    requests: usize,
+}
+
+impl SimDevice {
+    pub fn new(id: u8, proto: ModbusProto) -> Self {
+        Self {
+            proto,
+            slave: ModbusSlave::new(id, proto),
+            context: Box::new(ModbusContext::new()),
+            faults: VecDeque::new(),
+            output: MockTransport::new(),
+            requests: 0,
+        }
+    }
+
+    pub fn access_rules<const N: usize>(mut self, rules: [AccessRule; N]) -> Self {
+        self.slave = self.slave.access_rules(rules);
+        self
+    }
+
+    pub fn fault(mut self, fault: Fault) -> Self {
+        self.inject(fault);
+        self
+    }
+
+    pub fn inject(&mut self, fault: Fault) {
+        self.faults.push_back(fault);
+    }
+
+    pub fn get_context(&self) -> &ModbusContext { &self.context }
+
+    pub fn get_context_mut(&mut self) -> &mut ModbusContext { &mut self.context }
+
+    pub fn get_requests(&self) -> usize { self.requests }
+
+    fn respond(&mut self, request: &[u8]) {
+        self.requests += 1;
+
+        let fault = self.faults.pop_front();
+
+        let response = match fault {
+            Some(Fault::Timeout) => return,
+            Some(Fault::Exception(code)) => match self.exception(request, code) {
+                Some(v) => v,
+                None => return,
+            },
+            _ => {
+                let mut transport = MockTransport::new();
+                transport.push(request);
+
+                if self.slave.handler(&mut transport, &mut *self.context).is_err() {
+                    return;
+                }
+
+                transport.take_written()
+            },
+        };
+
+        let response = match fault {
+            Some(Fault::Delay(delay)) => {
+                thread::sleep(delay);
+                response
+            },
+            Some(Fault::CrcError) => {
+                let mut response = response;
+                let check = match self.proto {
+                    ModbusProto::Ascii => response.len().checked_sub(3),
+                    _ => response.len().checked_sub(1),
+                };
+                if let Some(byte) = check.and_then(|i| response.get_mut(i)) {
+                    *byte = match (self.proto, *byte) {
+                        (ModbusProto::Ascii, b'0') => b'1',
+                        (ModbusProto::Ascii, _) => b'0',
+                        (_, b) => b ^ 0xff,
+                    };
+                }
+                response
+            },
+            Some(Fault::PartialFrame(len)) => response.into_iter().take(len).collect(),
+            _ => response,
+        };
+
+        self.output.push(&response);
+    }
+
+    fn exception(&self, request: &[u8], code: ExceptionCode) -> Option<Vec<u8>> {
+        let decoded;
+        let request = match self.proto {
+            ModbusProto::Ascii => {
+                decoded = modbus_pdu::from_ascii(request).ok()?;
+                decoded.as_slice()
+            },
+            _ => request,
+        };
+
+        let (unit, tr_id, function) = match self.proto {
+            ModbusProto::TcpUdp => (*request.get(6)?, u16::from_be_bytes([request[0], request[1]]), *request.get(7)?),
+            _ => (*request.first()?, 0, *request.get(1)?),
+        };
+
+        Some(modbus_pdu::wrap(self.proto, unit, tr_id, &[function | 0x80, code.get_code()]))
+    }
+}
+
+impl io::Read for SimDevice {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.output.read(buf)
+    }
+}
+
+impl io::Write for SimDevice {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.respond(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> { Ok(()) }
+}
+
+#[test]
+fn test_sim_device_faults() {
+    use super::modbus_master::ModbusMaster;
+    use super::modbus_error::ModbusErr;
+
+    let master = ModbusMaster::new(1, ModbusProto::Rtu);
+    let mut device = SimDevice::new(1, ModbusProto::Rtu)
+        .fault(Fault::Exception(ExceptionCode::SlaveDeviceBusy))
+        .fault(Fault::CrcError)
+        .fault(Fault::Timeout)
+        .fault(Fault::PartialFrame(4))
+        .fault(Fault::Delay(Duration::from_millis(1)));
+    device.get_context_mut().set_holding(7, 42).unwrap();
+
+    assert!(matches!(
+        master.read_holdings(&mut device, 7, 1),
+        Err(ModbusErr::Exception { function: 3, code: ExceptionCode::SlaveDeviceBusy })
+    ));
+    assert!(matches!(master.read_holdings(&mut device, 7, 1), Err(ModbusErr::Rmodbus(rmodbus::ErrorKind::FrameCRCError))));
+    assert!(matches!(master.read_holdings(&mut device, 7, 1), Err(ModbusErr::Io(ref e)) if e.kind() == io::ErrorKind::TimedOut));
+    assert!(matches!(master.read_holdings(&mut device, 7, 1), Err(ModbusErr::Io(_))));
+    assert_eq!(master.read_holdings(&mut device, 7, 1).unwrap(), vec![42]);
+
+    master.write_multipl_coils(&mut device, 3, vec![true, false, true]).unwrap();
+    assert!(device.get_context().get_coil(5).unwrap());
+    assert_eq!(master.read_coils(&mut device, 3, 3).unwrap(), vec![true, false, true]);
+    assert_eq!(device.get_requests(), 7);
+
+    let master = ModbusMaster::new(2, ModbusProto::TcpUdp);
+    let mut device = SimDevice::new(2, ModbusProto::TcpUdp).fault(Fault::Exception(ExceptionCode::IllegalFunction));
+    assert!(matches!(
+        master.write_holding(&mut device, 1, 5),
+        Err(ModbusErr::Exception { function: 6, code: ExceptionCode::IllegalFunction })
+    ));
+    master.write_holding(&mut device, 1, 5).unwrap();
+    assert_eq!(device.get_context().get_holding(1).unwrap(), 5);
+
+    let mut transport = MockTransport::new();
+    transport.push_pdu(ModbusProto::TcpUdp, 2, 3, &[4, 2, 0, 9]);
+    assert_eq!(master.read_inputs(&mut transport, 0, 1).unwrap(), vec![9]);
+    assert_eq!(transport.get_written(), modbus_pdu::wrap(ModbusProto::TcpUdp, 2, 3, &[4, 0, 0, 0, 1]));
+    assert_eq!(transport.pending(), 0);
+}
+
+#[test]
+fn test_sim_device_acton_scheduling() {
+    use crate::memory::{DataMemory, PlcMemory};
+    use super::modbus_master::ModbusMaster;
+    use super::modbus_master_actions::{Acton, Quality};
+
+    let master = ModbusMaster::new(1, ModbusProto::Rtu);
+    let mut device = SimDevice::new(1, ModbusProto::Rtu);
+    device.get_context_mut().set_holding(10, 7).unwrap();
+
+    let actions = [
+        Acton::cycle_read_holdings(10, 1, Duration::ZERO, |ctx, data| ctx.set_holding(0, data[0]).unwrap()),
+        Acton::cycle_write_holding(20, Duration::ZERO, |ctx, _| ctx.get_holding(1).unwrap()),
+    ];
+    let mut memory = PlcMemory::new(10, 10, 10, 10, 0);
+    memory.set_holding(1, 99).unwrap();
+
+    master.poll(&mut device, &actions, &mut memory).unwrap();
+    assert_eq!(memory.get_holding(0).unwrap(), 7);
+    assert_eq!(device.get_context().get_holding(20).unwrap(), 99);
+    assert_eq!(actions[0].get_quality(), Quality::Fresh);
+
+    device.inject(Fault::Timeout);
+    assert!(master.poll(&mut device, &actions, &mut memory).is_err());
+    assert_eq!(actions[0].get_quality(), Quality::Stale);
+    assert_eq!(device.get_requests(), 4);
+}
@@ -0,0 +1,342 @@
+use std::io;
+use rmodbus::{ErrorKind, ModbusProto};
+use super::modbus_error::{ModbusErr, ExceptionCode};
+
+pub(crate) fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xffff;
+
+    for byte in data.iter() {
+        crc ^= *byte as u16;
+
+        for _ in 0..8 {
+            if crc & 1 != 0 {
+                crc = (crc >> 1) ^ 0xa001;
+            } else {
+                crc >>= 1;
+            }
+        }
+    }
+
+    crc
+}
+
+pub(crate) fn lrc(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |lrc, b| lrc.wrapping_add(*b)).wrapping_neg()
+}
+
+pub(crate) fn to_ascii(frame: &[u8]) -> Vec<u8> {
+    let mut text = Vec::with_capacity(frame.len() * 2 + 3);
+    text.push(b':');
+
+    for byte in frame.iter() {
+        text.extend_from_slice(format!("{:02X}", byte).as_bytes());
+    }
+
+    text.extend_from_slice(b"\r\n");
+    text
+}
+
+pub(crate) fn from_ascii(text: &[u8]) -> Result<Vec<u8>, ErrorKind> {
+    let hex = text.strip_prefix(b":")
+        .and_then(|t| t.strip_suffix(b"\r\n"))
+        .ok_or(ErrorKind::FrameBroken)?;
+
+    if hex.len() % 2 != 0 || hex.len() > 510 {
+        return Err(ErrorKind::FrameBroken);
+    }
+
+    hex.chunks_exact(2)
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair).map_err(|_| ErrorKind::FrameBroken)?;
+            u8::from_str_radix(pair, 16).map_err(|_| ErrorKind::FrameBroken)
+        })
+        .collect()
+}
+
+pub(crate) fn take_ascii_frame(buffer: &mut Vec<u8>) -> Option<Vec<u8>> {
+    loop {
+        let start = buffer.iter().position(|b| *b == b':');
+        buffer.drain(..start.unwrap_or(buffer.len()));
+
+        let restart = buffer.iter().skip(1).position(|b| *b == b':').map(|p| p + 1);
+        let end = buffer.windows(2).position(|w| w == b"\r\n").map(|p| p + 2);
+
+        match (restart, end) {
+            (Some(restart), Some(end)) if restart < end => { buffer.drain(..restart); },
+            (Some(restart), None) => { buffer.drain(..restart); },
+            (_, Some(end)) => return Some(buffer.drain(..end).collect()),
+            (None, None) if buffer.len() > 513 => {
+                buffer.clear();
+                return None;
+            },
+            (None, None) => return None,
+        }
+    }
+}
+
+pub(crate) fn wrap(proto: ModbusProto, unit: u8, tr_id: u16, pdu: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(pdu.len() + 7);
+
+    match proto {
+        ModbusProto::TcpUdp => {
+            frame.extend_from_slice(&tr_id.to_be_bytes());
+            frame.extend_from_slice(&[0, 0]);
+            frame.extend_from_slice(&(pdu.len() as u16 + 1).to_be_bytes());
+            frame.push(unit);
+            frame.extend_from_slice(pdu);
+        },
+        ModbusProto::Rtu => {
+            frame.push(unit);
+            frame.extend_from_slice(pdu);
+            let crc = crc16(&frame);
+            frame.extend_from_slice(&crc.to_le_bytes());
+        },
+        ModbusProto::Ascii => {
+            frame.push(unit);
+            frame.extend_from_slice(pdu);
+            frame.push(lrc(&frame));
+            frame = to_ascii(&frame);
+        },
+    }
+
+    frame
+}
+
+pub(crate) fn read_pdu<T: io::Read>(transport: &mut T, proto: ModbusProto, unit: u8, tr_id: u16) -> Result<Vec<u8>, ModbusErr> {
+    let pdu = match proto {
+        ModbusProto::TcpUdp => read_tcp_pdu(transport, unit, tr_id)?,
+        ModbusProto::Rtu => read_rtu_pdu(transport, unit)?,
+        ModbusProto::Ascii => read_ascii_pdu(transport, unit)?,
+    };
+
+    if pdu[0] & 0x80 != 0 {
+        let code = ExceptionCode::from_code(*pdu.get(1).ok_or(ErrorKind::FrameBroken)?);
+        return Err(ModbusErr::Exception { function: pdu[0] & 0x7f, code });
+    }
+
+    Ok(pdu)
+}
+
+fn read_tcp_pdu<T: io::Read>(transport: &mut T, unit: u8, tr_id: u16) -> Result<Vec<u8>, ModbusErr> {
+    loop {
+        let mut header = [0u8; 7];
+        transport.read_exact(&mut header)?;
+
+        let len = u16::from_be_bytes([header[4], header[5]]) as usize;
+        if !(3..=254).contains(&len) || header[2..4] != [0, 0] {
+            return Err(ErrorKind::FrameBroken.into());
+        }
+
+        let mut pdu = vec![0u8; len - 1];
+        transport.read_exact(&mut pdu)?;
+
+        if u16::from_be_bytes([header[0], header[1]]) != tr_id {
+            continue;
+        }
+
+        if header[6] != unit {
+            return Err(ErrorKind::FrameBroken.into());
+        }
+
+        return Ok(pdu);
+    }
+}
+
+fn read_rtu_pdu<T: io::Read>(transport: &mut T, unit: u8) -> Result<Vec<u8>, ModbusErr> {
+    let mut frame = vec![0u8; 2];
+    transport.read_exact(&mut frame)?;
+
+    let mut read = |frame: &mut Vec<u8>, count: usize| -> io::Result<()> {
+        let start = frame.len();
+        frame.resize(start + count, 0);
+        transport.read_exact(&mut frame[start..])
+    };
+
+    match frame[1] {
+        f if f & 0x80 != 0 => read(&mut frame, 1)?,
+        1 | 2 | 3 | 4 | 17 | 20 | 21 | 23 => {
+            read(&mut frame, 1)?;
+            let count = frame[2] as usize;
+            read(&mut frame, count)?;
+        },
+        5 | 6 | 8 | 15 | 16 => read(&mut frame, 4)?,
+        22 => read(&mut frame, 6)?,
+        43 => {
+            read(&mut frame, 6)?;
+            for _ in 0..frame[7] {
+                read(&mut frame, 2)?;
+                let len = frame[frame.len() - 1] as usize;
+                read(&mut frame, len)?;
+            }
+        },
+        _ => return Err(ErrorKind::FrameBroken.into()),
+    }
+
+    read(&mut frame, 2)?;
+
+    let len = frame.len();
+    if crc16(&frame[..len - 2]) != u16::from_le_bytes([frame[len - 2], frame[len - 1]]) {
+        return Err(ErrorKind::FrameCRCError.into());
+    }
+
+    if frame[0] != unit {
+        return Err(ErrorKind::FrameBroken.into());
+    }
+
+    Ok(frame[1..len - 2].to_vec())
+}
+
+fn read_ascii_pdu<T: io::Read>(transport: &mut T, unit: u8) -> Result<Vec<u8>, ModbusErr> {
+    let mut byte = [0u8; 1];
+
+    loop {
+        transport.read_exact(&mut byte)?;
+        if byte[0] == b':' {
+            break;
+        }
+    }
+
+    let mut text = vec![b':'];
+
+    while !text.ends_with(b"\r\n") {
+        if text.len() > 513 {
+            return Err(ErrorKind::FrameBroken.into());
+        }
+
+        transport.read_exact(&mut byte)?;
+        text.push(byte[0]);
+    }
+
+    let frame = from_ascii(&text)?;
+
+    if frame.len() < 3 {
+        return Err(ErrorKind::FrameBroken.into());
+    }
+
+    let len = frame.len();
+    if lrc(&frame[..len - 1]) != frame[len - 1] {
+        return Err(ErrorKind::FrameCRCError.into());
+    }
+
+    if frame[0] != unit {
+        return Err(ErrorKind::FrameBroken.into());
+    }
+
+    Ok(frame[1..len - 1].to_vec())
+}
+
+pub(crate) fn rtu_request_len(frame: &[u8]) -> Option<usize> {
+    let byte = |i: usize| frame.get(i).map(|b| *b as usize);
+
+    match byte(1)? {
+        1..=6 | 8 => Some(8),
+        7 | 11 | 12 | 17 => Some(4),
+        15 | 16 => Some(9 + byte(6)?),
+        20 | 21 => Some(5 + byte(2)?),
+        22 => Some(10),
+        23 => Some(13 + byte(10)?),
+        24 => Some(6),
+        43 => Some(7),
+        _ => rtu_crc_len(frame),
+    }
+}
+
+pub(crate) fn check_request(frame: &[u8]) -> Result<(), ErrorKind> {
+    let byte_count = match frame.get(1) {
+        Some(15 | 16) => frame.get(6).copied().unwrap_or(0),
+        _ => 0,
+    };
+
+    match frame.len() > 256 || byte_count > 246 {
+        true => Err(ErrorKind::FrameBroken),
+        false => Ok(()),
+    }
+}
+
+fn rtu_crc_len(frame: &[u8]) -> Option<usize> {
+    (4..=frame.len().min(256)).find(|len| frame[len - 2..*len] == crc16(&frame[..len - 2]).to_le_bytes())
+}
+
+pub(crate) fn words(data: &[u8]) -> Vec<u16> {
+    data.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect()
+}
+
+pub(crate) fn pack_bits(bits: &[bool]) -> Vec<u8> {
+    let mut result = vec![0u8; bits.len().div_ceil(8)];
+
+    for (i, bit) in bits.iter().enumerate() {
+        if *bit {
+            result[i / 8] |= 1 << (i % 8);
+        }
+    }
+
+    result
+}
+
+pub(crate) fn unpack_bits(bytes: &[u8], count: usize) -> Vec<bool> {
+    (0..count).map(|i| bytes[i / 8] & (1 << (i % 8)) != 0).collect()
+}
+
+pub(crate) fn extend_words(pdu: &mut Vec<u8>, values: &[u16]) {
+    for value in values.iter() {
+        pdu.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+#[test]
+fn test_pdu_framing() {
+    let frame = wrap(ModbusProto::Rtu, 1, 0, &[1, 0, 10, 0, 10]);
+    assert_eq!(frame, vec![1, 1, 0, 10, 0, 10, 0x9c, 0x0f]);
+
+    let frame = wrap(ModbusProto::TcpUdp, 3, 0x1234, &[17]);
+    assert_eq!(frame, vec![0x12, 0x34, 0, 0, 0, 2, 3, 17]);
+
+    let response = wrap(ModbusProto::Rtu, 1, 0, &[22, 0, 4, 0, 0xf2, 0, 0x25]);
+    let pdu = read_pdu(&mut response.as_slice(), ModbusProto::Rtu, 1, 0).unwrap();
+    assert_eq!(pdu, vec![22, 0, 4, 0, 0xf2, 0, 0x25]);
+
+    let mut broken = response.clone();
+    broken[3] ^= 0xff;
+    assert!(matches!(read_pdu(&mut broken.as_slice(), ModbusProto::Rtu, 1, 0), Err(ModbusErr::Rmodbus(ErrorKind::FrameCRCError))));
+
+    let exception = wrap(ModbusProto::Rtu, 1, 0, &[0x97, 2]);
+    assert!(matches!(
+        read_pdu(&mut exception.as_slice(), ModbusProto::Rtu, 1, 0),
+        Err(ModbusErr::Exception { function: 0x17, code: ExceptionCode::IllegalDataAddress })
+    ));
+
+    let mut stream = wrap(ModbusProto::TcpUdp, 1, 6, &[17, 1, 0xff]);
+    stream.extend(wrap(ModbusProto::TcpUdp, 1, 7, &[17, 2, 0x10, 0xff]));
+    assert_eq!(read_pdu(&mut stream.as_slice(), ModbusProto::TcpUdp, 1, 7).unwrap(), vec![17, 2, 0x10, 0xff]);
+
+    let ascii = wrap(ModbusProto::Ascii, 0x11, 0, &[3, 0, 0x6b, 0, 3]);
+    assert_eq!(ascii, b":1103006B00037E\r\n".to_vec());
+
+    let mut noisy = b"\0\n".to_vec();
+    noisy.extend(wrap(ModbusProto::Ascii, 0x11, 0, &[3, 2, 0xae, 0x41]));
+    assert_eq!(read_pdu(&mut noisy.as_slice(), ModbusProto::Ascii, 0x11, 0).unwrap(), vec![3, 2, 0xae, 0x41]);
+
+    let corrupted = b":1103006B00037F\r\n".to_vec();
+    assert!(matches!(read_pdu(&mut corrupted.as_slice(), ModbusProto::Ascii, 0x11, 0), Err(ModbusErr::Rmodbus(ErrorKind::FrameCRCError))));
+    assert!(from_ascii(b":1G\r\n").is_err());
+
+    let mut buffer = b"xx:01\r\n:0103:0203\r\n:04".to_vec();
+    assert_eq!(take_ascii_frame(&mut buffer), Some(b":01\r\n".to_vec()));
+    assert_eq!(take_ascii_frame(&mut buffer), Some(b":0203\r\n".to_vec()));
+    assert_eq!(take_ascii_frame(&mut buffer), None);
+    assert_eq!(buffer, b":04".to_vec());
+
+    assert_eq!(rtu_request_len(&[1, 3]), Some(8));
+    assert_eq!(rtu_request_len(&[1, 16, 0, 0, 0, 2]), None);
+    assert_eq!(rtu_request_len(&[1, 16, 0, 0, 0, 2, 4]), Some(13));
+    assert_eq!(rtu_request_len(&[1, 0x65, 0]), None);
+
+    let mut pipelined = wrap(ModbusProto::Rtu, 1, 0, &[0x65, 1, 2]);
+    let len = pipelined.len();
+    pipelined.extend(wrap(ModbusProto::Rtu, 1, 0, &[3, 0, 0, 0, 1]));
+    assert_eq!(rtu_request_len(&pipelined[..len - 1]), None);
+    assert_eq!(rtu_request_len(&pipelined), Some(len));
+
+    let device_id = wrap(ModbusProto::Rtu, 1, 0, &[43, 14, 1, 1, 0, 0, 2, 0, 3, b'A', b'C', b'M', 1, 2, b'X', b'1']);
+    assert_eq!(read_pdu(&mut device_id.as_slice(), ModbusProto::Rtu, 1, 0).unwrap().len(), 16);
+}
@@ -0,0 +1,166 @@
+use super::modbus_master::ModbusMaster;
+use super::modbus_master_actions::{Acton, mark_offline, publish_all};
+use super::modbus_error::ModbusErr;
+use super::modbus_poll_plan::{plan, due_actions};
+use super::modbus_connection::{ConnectionStatus, Reconnect};
+use super::timeaut_heandler::TimeautHeandler;
+use rmodbus::ModbusProto;
+use crate::memory::DataMemory;
+use std::{io, time};
+use std::cell::RefCell;
+
+pub(crate) trait PollLink {
+    type Conn;
+    type Stream<'a>: io::Read + io::Write where Self: 'a;
+
+    fn open(&self, timeout: time::Duration) -> io::Result<Self::Conn>;
+
+    fn stream<'a>(&'a self, conn: &'a mut Self::Conn, timeout: time::Duration) -> io::Result<Self::Stream<'a>>;
+}
+
+pub(crate) struct PollDriver<L: PollLink> {
+    link: L,
+    modbus_master: ModbusMaster,
+    actions: Vec<Acton>,
+    timeout_heandler: TimeautHeandler,
+    conn: RefCell<Option<L::Conn>>,
+    reconnect: RefCell<Reconnect>,
+    status_coil: Option<u16>,
+    retries: u32,
+    merge_gap: Option<u16>,
+}
+
+impl<L: PollLink> PollDriver<L> {
+    pub(crate) fn new(link: L, modbus_master: ModbusMaster, actions: Vec<Acton>, timeout_heandler: TimeautHeandler) -> Self {
+        let reconnect = Reconnect::new(time::Duration::from_millis(100), time::Duration::from_secs(30));
+
+        Self {
+            link,
+            modbus_master,
+            actions,
+            timeout_heandler,
+            conn: RefCell::new(None),
+            reconnect: RefCell::new(reconnect),
+            status_coil: None,
+            retries: 0,
+            merge_gap: None,
+        }
+    }
+
+    pub(crate) fn proto(mut self, proto: ModbusProto) -> Self {
+        self.modbus_master = ModbusMaster::new(self.modbus_master.get_id(), proto);
+        self
+    }
+
+    pub(crate) fn link_mut(&mut self) -> &mut L { &mut self.link }
+
+    pub(crate) fn reconnect_backoff(self, min: time::Duration, max: time::Duration) -> Self {
+        self.reconnect.borrow_mut().set_backoff(min, max);
+        self
+    }
+
+    pub(crate) fn status_coil(mut self, coil: u16) -> Self {
+        self.status_coil = Some(coil);
+        self
+    }
+
+    pub(crate) fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    pub(crate) fn merge_reads(mut self, gap: u16) -> Self {
+        self.merge_gap = Some(gap);
+        self
+    }
+
+    pub(crate) fn add_actions(mut self, actions: Vec<Acton>) -> Self {
+        self.actions.extend(actions);
+        self
+    }
+
+    pub(crate) fn get_actions(&self) -> &[Acton] { &self.actions }
+
+    pub(crate) fn get_connection_status(&self) -> ConnectionStatus {
+        self.reconnect.borrow().get_status().clone()
+    }
+
+    fn ensure_open(&self) -> bool {
+        if self.conn.borrow().is_some() {
+            return true;
+        }
+
+        let mut reconnect = self.reconnect.borrow_mut();
+
+        if !reconnect.can_attempt() {
+            return false;
+        }
+
+        reconnect.connecting();
+
+        match self.link.open(self.timeout_heandler.get_timeout()) {
+            Ok(conn) => {
+                *self.conn.borrow_mut() = Some(conn);
+                reconnect.connected();
+                true
+            },
+            Err(e) => {
+                reconnect.failed(&e);
+                false
+            },
+        }
+    }
+
+    fn update_status_coil(&self, context: &mut dyn DataMemory) -> Result<(), rmodbus::ErrorKind> {
+        match self.status_coil {
+            Some(coil) => context.set_coil(coil, self.reconnect.borrow().get_status().is_connected()),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn run(&self, context: &mut dyn DataMemory) -> std::result::Result<(), Box<dyn std::error::Error>> {
+
+        if !self.ensure_open() {
+            mark_offline(&self.actions, context)?;
+            publish_all(&self.actions, context)?;
+            self.update_status_coil(context)?;
+            return Ok(());
+        }
+
+        let mut result: std::result::Result<(), Box<dyn std::error::Error>> = Ok(());
+
+        for item in plan(due_actions(&self.actions, context)?, self.merge_gap) {
+
+            let timeout = item.get_timeout().unwrap_or(self.timeout_heandler.get_timeout());
+
+            let action_result = item.attempt(self.retries, |item| {
+                let mut conn = self.conn.borrow_mut();
+                let conn = conn.as_mut().ok_or_else(|| io::Error::from(io::ErrorKind::NotConnected))?;
+                let mut stream = self.link.stream(conn, timeout)?;
+                self.modbus_master.execute_item(item, context, &mut stream)
+            });
+
+            match action_result {
+                Ok(()) => {},
+                Err(ModbusErr::Io(ref e))
+                    if e.kind() == io::ErrorKind::TimedOut || e.kind() == io::ErrorKind::WouldBlock => {},
+                Err(ModbusErr::Io(e)) => {
+                    *self.conn.borrow_mut() = None;
+                    self.reconnect.borrow_mut().failed(&e);
+                    result = Err(Box::new(ModbusErr::Io(e)));
+                    break;
+                }
+                Err(err) => {
+                    if result.is_ok() {
+                        result = Err(Box::new(err));
+                    }
+                }
+            }
+        }
+
+        publish_all(&self.actions, context)?;
+        self.update_status_coil(context)?;
+
+        result
+    }
+}
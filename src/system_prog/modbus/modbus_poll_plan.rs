@@ -0,0 +1,230 @@
+use super::modbus_master_actions::{Acton, ReadKind, retry};
+use super::modbus_error::ModbusErr;
+use crate::memory::DataMemory;
+use std::time::Duration;
+
+pub(crate) struct ReadGroup<'a> {
+    kind: ReadKind,
+    offset: u16,
+    count: u16,
+    actions: Vec<&'a Acton>,
+}
+
+impl<'a> ReadGroup<'a> {
+    pub(crate) fn get_kind(&self) -> ReadKind { self.kind }
+    pub(crate) fn get_offset(&self) -> u16 { self.offset }
+    pub(crate) fn get_count(&self) -> u16 { self.count }
+    pub(crate) fn get_actions(&self) -> &[&'a Acton] { &self.actions }
+}
+
+pub(crate) enum PollItem<'a> {
+    Single(&'a Acton),
+    Merged(ReadGroup<'a>),
+}
+
+impl<'a> PollItem<'a> {
+    fn get_actions(&self) -> &[&'a Acton] {
+        match self {
+            Self::Single(action) => std::slice::from_ref(action),
+            Self::Merged(group) => group.get_actions(),
+        }
+    }
+
+    pub(crate) fn get_timeout(&self) -> Option<Duration> {
+        self.get_actions().iter().filter_map(|a| a.get_timeout()).max()
+    }
+
+    pub(crate) fn attempt(
+        &self,
+        default_retries: u32,
+        mut exec: impl FnMut(&PollItem) -> Result<(), ModbusErr>,
+    ) -> Result<(), ModbusErr> {
+
+        let group = match self {
+            Self::Single(action) => return action.attempt(default_retries, || exec(self)),
+            Self::Merged(group) => group,
+        };
+
+        let retries = group.actions.iter().map(|a| a.get_retries(default_retries)).max().unwrap_or(default_retries);
+        let result = retry(retries, || exec(self));
+
+        if let Err(ModbusErr::Exception { .. }) = result {
+            let mut result = Ok(());
+
+            for action in group.actions.iter() {
+                let single = PollItem::Single(action);
+                let action_result = action.attempt(default_retries, || exec(&single));
+                result = result.and(action_result);
+            }
+
+            return result;
+        }
+
+        for action in group.actions.iter() {
+            action.record(&result);
+        }
+
+        result
+    }
+}
+
+pub(crate) fn due_actions<'a>(actions: &'a [Acton], context: &mut dyn DataMemory) -> Result<Vec<&'a Acton>, ModbusErr> {
+    let mut due = Vec::new();
+
+    for action in actions.iter() {
+        if action.need_run(context)? {
+            due.push(action);
+        }
+    }
+
+    Ok(due)
+}
+
+pub(crate) fn plan<'a>(due: Vec<&'a Acton>, gap: Option<u16>) -> Vec<PollItem<'a>> {
+    let Some(gap) = gap else {
+        return due.into_iter().map(PollItem::Single).collect();
+    };
+
+    let mut reads: Vec<(usize, ReadKind, u16, u16)> = Vec::new();
+    let mut items: Vec<(usize, PollItem<'a>)> = Vec::new();
+
+    for (i, action) in due.iter().enumerate() {
+        match action.read_range() {
+            Some((kind, offset, count)) => reads.push((i, kind, offset, count)),
+            None => items.push((i, PollItem::Single(action))),
+        }
+    }
+
+    reads.sort_by_key(|&(i, kind, offset, _)| (kind, offset, i));
+
+    let mut group: Option<(usize, ReadGroup<'a>)> = None;
+
+    for (i, kind, offset, count) in reads {
+        let end = offset as u32 + count as u32;
+
+        if let Some((first, current)) = group.as_mut() {
+            let current_end = current.offset as u32 + current.count as u32;
+            let merged_count = end.max(current_end) - current.offset as u32;
+
+            if current.kind == kind && offset as u32 <= current_end + gap as u32 && merged_count <= kind.get_limit() as u32 {
+                current.count = merged_count as u16;
+                current.actions.push(due[i]);
+                *first = (*first).min(i);
+                continue;
+            }
+        }
+
+        if let Some(done) = group.take() {
+            items.push(close(done));
+        }
+
+        group = Some((i, ReadGroup { kind, offset, count, actions: vec![due[i]] }));
+    }
+
+    if let Some(done) = group.take() {
+        items.push(close(done));
+    }
+
+    items.sort_by_key(|(i, _)| *i);
+    items.into_iter().map(|(_, item)| item).collect()
+}
+
+fn close(group: (usize, ReadGroup)) -> (usize, PollItem) {
+    let (first, group) = group;
+
+    if group.actions.len() == 1 {
+        return (first, PollItem::Single(group.actions[0]));
+    }
+
+    (first, PollItem::Merged(group))
+}
+
+#[test]
+fn test_plan_merges_reads() {
+    use crate::memory::PlcMemory;
+    use super::modbus_master_actions::ReadData;
+
+    let period = Duration::from_millis(10);
+    let actions = [
+        Acton::cycle_read_holdings(10, 2, period, |ctx, data| ctx.set_holdings_bulk(0, &data).unwrap()),
+        Acton::cycle_write_holding(0, period, |_, _| 1),
+        Acton::cycle_read_holdings(0, 4, period, |ctx, data| ctx.set_holdings_bulk(10, &data).unwrap()),
+        Acton::cycle_read_holdings(5, 3, period, |ctx, data| ctx.set_holdings_bulk(20, &data).unwrap()),
+        Acton::cycle_read_holdings(200, 1, period, |_, _| {}),
+        Acton::cycle_read_colis(0, 8, period, |_, _| {}),
+        Acton::cycle_read_inputs(0, 4, period, |_, _| {}),
+    ];
+
+    let items = plan(actions.iter().collect(), None);
+    assert_eq!(items.len(), 7);
+
+    let items = plan(actions.iter().collect(), Some(1));
+    assert_eq!(items.len(), 6);
+
+    let items = plan(actions.iter().collect(), Some(3));
+    assert_eq!(items.len(), 5);
+
+    let PollItem::Merged(group) = &items[0] else { panic!("holdings are not merged") };
+    assert_eq!((group.get_kind(), group.get_offset(), group.get_count()), (ReadKind::Holdings, 0, 12));
+    assert_eq!(group.get_actions().len(), 3);
+    assert!(matches!(items[1], PollItem::Single(Acton::WriteHolding(_))));
+
+    let mut memory = PlcMemory::new(10, 10, 10, 30, 0);
+    let block = ReadData::Words((100..112).collect());
+    for action in group.get_actions() {
+        action.deliver(&mut memory, group.get_offset(), &block).unwrap();
+    }
+
+    let mut regs = Vec::new();
+    memory.get_holdings_bulk(0, 23, &mut regs).unwrap();
+    assert_eq!(&regs[0..2], &[110, 111]);
+    assert_eq!(&regs[10..14], &[100, 101, 102, 103]);
+    assert_eq!(&regs[20..23], &[105, 106, 107]);
+
+    let long = [
+        Acton::cycle_read_holdings(0, 100, period, |_, _| {}),
+        Acton::cycle_read_holdings(100, 26, period, |_, _| {}),
+    ];
+    assert_eq!(plan(long.iter().collect(), Some(0)).len(), 2);
+}
+
+#[test]
+fn test_merged_attempt() {
+    use std::io;
+    use super::modbus_error::ExceptionCode;
+    use super::modbus_master_actions::Quality;
+
+    let period = Duration::from_millis(10);
+    let actions = [
+        Acton::cycle_read_holdings(0, 2, period, |_, _| {}),
+        Acton::cycle_read_holdings(4, 2, period, |_, _| {}).retries(2),
+    ];
+
+    let items = plan(actions.iter().collect(), Some(2));
+    assert_eq!(items.len(), 1);
+
+    let mut calls = 0;
+    let result = items[0].attempt(0, |_| {
+        calls += 1;
+        Err(ModbusErr::Io(io::ErrorKind::TimedOut.into()))
+    });
+    assert!(result.is_err());
+    assert_eq!(calls, 3);
+    assert!(actions.iter().all(|a| a.get_failures() == 1));
+
+    let mut singles = Vec::new();
+    let result = items[0].attempt(0, |item| match item {
+        PollItem::Merged(_) => Err(ModbusErr::Exception { function: 3, code: ExceptionCode::IllegalDataAddress }),
+        PollItem::Single(action) => {
+            singles.push(action.read_range().unwrap().1);
+            match singles.len() {
+                1 => Ok(()),
+                _ => Err(ModbusErr::Exception { function: 3, code: ExceptionCode::IllegalDataAddress }),
+            }
+        },
+    });
+    assert!(matches!(result, Err(ModbusErr::Exception { .. })));
+    assert_eq!(singles, [0, 4]);
+    assert_eq!(actions[0].get_quality(), Quality::Fresh);
+    assert_eq!(actions[1].get_quality(), Quality::Bad);
+}
@@ -0,0 +1,159 @@
+use rmodbus::{ErrorKind, ModbusFrameBuf, ModbusProto};
+use rmodbus::server::ModbusFrame;
+use rmodbus::consts::{
+    MODBUS_GET_COILS, MODBUS_GET_DISCRETES, MODBUS_GET_HOLDINGS, MODBUS_GET_INPUTS,
+    MODBUS_SET_COIL, MODBUS_SET_HOLDING, MODBUS_SET_COILS_BULK, MODBUS_SET_HOLDINGS_BULK,
+    MODBUS_ERROR_ILLEGAL_DATA_ADDRESS, MODBUS_ERROR_ILLEGAL_DATA_VALUE,
+};
+use crate::memory::DataMemory;
+use super::modbus_pdu::pack_bits;
+
+pub(crate) fn process_read(
+    frame: &mut ModbusFrame<Vec<u8>>,
+    buf: &ModbusFrameBuf,
+    context: &dyn DataMemory,
+) -> Result<(), ErrorKind> {
+
+    let start = frame.frame_start;
+    let response_len = frame.response.len();
+
+    let data = match frame.func {
+        MODBUS_GET_COILS | MODBUS_GET_DISCRETES => {
+            let mut bits = Vec::with_capacity(frame.count as usize);
+            let result = match frame.func {
+                MODBUS_GET_COILS => context.get_coils_bulk(frame.reg, frame.count, &mut bits),
+                _ => context.get_discretes_bulk(frame.reg, frame.count, &mut bits),
+            };
+            result.map(|_| pack_bits(&bits))
+        },
+        MODBUS_GET_HOLDINGS | MODBUS_GET_INPUTS => {
+            let mut regs = Vec::with_capacity(frame.count as usize);
+            let result = match frame.func {
+                MODBUS_GET_HOLDINGS => context.get_holdings_bulk(frame.reg, frame.count, &mut regs),
+                _ => context.get_inputs_bulk(frame.reg, frame.count, &mut regs),
+            };
+            result.map(|_| regs.iter().flat_map(|r| r.to_be_bytes()).collect::<Vec<u8>>())
+        },
+        _ => return Ok(()),
+    };
+
+    let data = match data {
+        Ok(v) => v,
+        Err(ErrorKind::OOBContext) => {
+            frame.response.truncate(response_len);
+            frame.error = MODBUS_ERROR_ILLEGAL_DATA_ADDRESS;
+            return Ok(());
+        },
+        Err(e) => return Err(e),
+    };
+
+    if data.len() > u8::MAX as usize {
+        return Err(ErrorKind::OOB);
+    }
+
+    if frame.proto == ModbusProto::TcpUdp {
+        frame.response.extend((data.len() as u16 + 3).to_be_bytes());
+    }
+
+    frame.response.extend_from_slice(&buf[start..start + 2]);
+    frame.response.push(data.len() as u8);
+    frame.response.extend(data);
+
+    Ok(())
+}
+
+pub(crate) fn process_write(
+    frame: &mut ModbusFrame<Vec<u8>>,
+    buf: &ModbusFrameBuf,
+    context: &mut dyn DataMemory,
+) -> Result<(), ErrorKind> {
+
+    let start = frame.frame_start;
+
+    let result = match frame.func {
+        MODBUS_SET_COIL => {
+            let value = match u16::from_be_bytes([buf[start + 4], buf[start + 5]]) {
+                0xff00 => true,
+                0x0000 => false,
+                _ => {
+                    frame.error = MODBUS_ERROR_ILLEGAL_DATA_VALUE;
+                    return Ok(());
+                },
+            };
+            context.set_coil(frame.reg, value)
+        },
+        MODBUS_SET_HOLDING => {
+            let value = u16::from_be_bytes([buf[start + 4], buf[start + 5]]);
+            context.set_holding(frame.reg, value)
+        },
+        MODBUS_SET_COILS_BULK => {
+            let bytes = buf[start + 6] as usize;
+            let data = &buf[start + 7..start + 7 + bytes];
+            let values: Vec<bool> = (0..frame.count as usize)
+                .take(bytes * 8)
+                .map(|i| (data[i / 8] >> (i % 8)) & 1 == 1)
+                .collect();
+            context.set_coils_bulk(frame.reg, &values)
+        },
+        MODBUS_SET_HOLDINGS_BULK => {
+            let bytes = buf[start + 6] as usize;
+            let values: Vec<u16> = buf[start + 7..start + 7 + bytes]
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            context.set_holdings_bulk(frame.reg, &values)
+        },
+        _ => return Ok(()),
+    };
+
+    if result.is_err() {
+        frame.error = MODBUS_ERROR_ILLEGAL_DATA_ADDRESS;
+        return Ok(());
+    }
+
+    if frame.proto == ModbusProto::TcpUdp {
+        frame.response.extend(6u16.to_be_bytes());
+    }
+
+    frame.response.extend_from_slice(&buf[start..start + 6]);
+
+    Ok(())
+}
+
+#[test]
+fn test_process_large_memory() {
+    use crate::memory::PlcMemory;
+
+    let mut memory = PlcMemory::new(16, 16, 16, 20_000, 0);
+    let mut response = Vec::new();
+
+    // tr id, proto, len, unit, func 16, reg 15000, count 2, bytes 4, values
+    let mut buf: ModbusFrameBuf = [0; 256];
+    buf[..17].copy_from_slice(&[0, 1, 0, 0, 0, 11, 1, 16, 0x3a, 0x98, 0, 2, 4, 0, 7, 0, 8]);
+    let mut frame = ModbusFrame::new(1, &buf, ModbusProto::TcpUdp, &mut response);
+    frame.parse().unwrap();
+    process_write(&mut frame, &buf, &mut memory).unwrap();
+    frame.finalize_response().unwrap();
+
+    assert_eq!(response, [0, 1, 0, 0, 0, 6, 1, 16, 0x3a, 0x98, 0, 2]);
+    assert_eq!(memory.get_holding(15_001).unwrap(), 8);
+
+    // tr id, proto, len, unit, func 3, reg 14999, count 3
+    buf[..12].copy_from_slice(&[0, 2, 0, 0, 0, 6, 1, 3, 0x3a, 0x97, 0, 3]);
+    let mut frame = ModbusFrame::new(1, &buf, ModbusProto::TcpUdp, &mut response);
+    frame.parse().unwrap();
+    process_read(&mut frame, &buf, &memory).unwrap();
+    frame.finalize_response().unwrap();
+
+    assert_eq!(response, [0, 2, 0, 0, 0, 9, 1, 3, 6, 0, 0, 0, 7, 0, 8]);
+
+    // unit, func 1, reg 10, count 10, crc
+    let mut buf: ModbusFrameBuf = [0; 256];
+    buf[..8].copy_from_slice(&[1, 1, 0, 10, 0, 10, 0x9c, 0x0f]);
+    let mut frame = ModbusFrame::new(1, &buf, ModbusProto::Rtu, &mut response);
+    frame.parse().unwrap();
+    process_read(&mut frame, &buf, &memory).unwrap();
+    frame.finalize_response().unwrap();
+
+    assert_eq!(&response[..3], [1, 0x81, 2]);
+}
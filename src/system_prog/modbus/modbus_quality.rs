@@ -0,0 +1,100 @@
+use std::{rc::Rc, cell::RefCell, collections::BTreeMap};
+use std::time::{Duration, SystemTime};
+use super::modbus_master_actions::Quality;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockStatus {
+    quality: Quality,
+    updated: Option<SystemTime>,
+    last_error: Option<String>,
+    max_age: Option<Duration>,
+}
+
+impl BlockStatus {
+    fn new(max_age: Option<Duration>) -> Self {
+        Self { quality: Quality::CommFailure, updated: None, last_error: None, max_age }
+    }
+
+    pub fn get_quality(&self) -> Quality {
+        let expired = self.max_age.is_some_and(|max| self.get_age().is_some_and(|age| age > max));
+
+        match self.quality {
+            Quality::Fresh if expired => Quality::Stale,
+            quality => quality,
+        }
+    }
+
+    pub fn get_updated(&self) -> Option<SystemTime> { self.updated }
+    pub fn get_last_error(&self) -> Option<&str> { self.last_error.as_deref() }
+
+    pub fn get_age(&self) -> Option<Duration> {
+        self.updated.map(|t| t.elapsed().unwrap_or_default())
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct QualityTable {
+    blocks: Rc<RefCell<BTreeMap<String, BlockStatus>>>,
+}
+
+impl QualityTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<BlockStatus> {
+        self.blocks.borrow().get(name).cloned()
+    }
+
+    pub fn get_quality(&self, name: &str) -> Quality {
+        self.get(name).map_or(Quality::CommFailure, |b| b.get_quality())
+    }
+
+    pub fn list(&self) -> Vec<(String, BlockStatus)> {
+        self.blocks.borrow().iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+    }
+
+    pub(crate) fn register(&self, name: &str, max_age: Option<Duration>) {
+        self.blocks.borrow_mut()
+            .entry(name.to_string())
+            .and_modify(|b| b.max_age = max_age)
+            .or_insert_with(|| BlockStatus::new(max_age));
+    }
+
+    pub(crate) fn update(&self, name: &str, quality: Quality, updated: Option<SystemTime>, error: Option<String>) {
+        let mut blocks = self.blocks.borrow_mut();
+        let block = blocks.entry(name.to_string()).or_insert_with(|| BlockStatus::new(None));
+
+        block.quality = quality;
+        block.updated = updated;
+
+        if error.is_some() {
+            block.last_error = error;
+        }
+    }
+}
+
+#[test]
+fn test_quality_table() {
+    let table = QualityTable::new();
+    table.register("boiler", Some(Duration::ZERO));
+    table.register("pump", None);
+
+    assert_eq!(table.get_quality("boiler"), Quality::CommFailure);
+    assert_eq!(table.get_quality("unknown"), Quality::CommFailure);
+
+    table.update("pump", Quality::Fresh, Some(SystemTime::now()), None);
+    assert_eq!(table.get_quality("pump"), Quality::Fresh);
+    assert!(table.get("pump").unwrap().get_updated().is_some());
+
+    table.update("boiler", Quality::Fresh, Some(SystemTime::now()), None);
+    std::thread::sleep(Duration::from_millis(1));
+    assert_eq!(table.get_quality("boiler"), Quality::Stale);
+
+    let updated = table.get("pump").unwrap().get_updated();
+    table.update("pump", Quality::Bad, updated, Some("exception".to_string()));
+    assert_eq!(table.get("pump").unwrap().get_last_error(), Some("exception"));
+    assert_eq!(table.get("pump").unwrap().get_updated(), updated);
+    assert_eq!(table.get_quality("pump").to_code(), 2);
+    assert_eq!(table.list().len(), 2);
+}
@@ -0,0 +1,322 @@
+use super::modbus_master::ModbusMaster;
+use super::modbus_master_actions::{Acton, mark_offline, publish_all};
+use super::modbus_error::ModbusErr;
+use super::modbus_poll_plan::{PollItem, plan, due_actions};
+use super::modbus_connection::ConnectionStatus;
+use super::modbus_rtu_timing::RtuTiming;
+use super::modbus_serial_link::{SerialLink, is_timeout};
+use rmodbus::ModbusProto;
+use crate::memory::DataMemory;
+use crate::task::ConstProgram;
+use std::time::{Duration, Instant};
+use std::cell::{Cell, RefCell};
+use std::{fmt, io};
+use crate::fail_strig;
+use super::timeaut_heandler::TimeautHeandler;
+
+#[derive(Clone, Debug, Default)]
+pub struct DeviceHealth {
+    requests: u32,
+    failures: u32,
+    timeouts: u32,
+    consecutive_failures: u32,
+    last_error: Option<String>,
+    last_success: Option<Instant>,
+    online: bool,
+}
+
+impl DeviceHealth {
+    pub fn get_requests(&self) -> u32 { self.requests }
+    pub fn get_failures(&self) -> u32 { self.failures }
+    pub fn get_timeouts(&self) -> u32 { self.timeouts }
+    pub fn get_consecutive_failures(&self) -> u32 { self.consecutive_failures }
+    pub fn get_last_error(&self) -> Option<&str> { self.last_error.as_deref() }
+    pub fn get_last_success(&self) -> Option<Instant> { self.last_success }
+    pub fn is_online(&self) -> bool { self.online }
+
+    fn success(&mut self) {
+        self.requests += 1;
+        self.consecutive_failures = 0;
+        self.last_success = Some(Instant::now());
+        self.online = true;
+    }
+
+    fn failure<E: fmt::Display>(&mut self, err: &E, timeout: bool, max_failures: u32) {
+        self.requests += 1;
+        self.failures += 1;
+        self.consecutive_failures += 1;
+        self.last_error = Some(err.to_string());
+
+        if timeout {
+            self.timeouts += 1;
+        }
+
+        if self.consecutive_failures >= max_failures {
+            self.online = false;
+        }
+    }
+}
+
+pub struct BusDevice {
+    master: ModbusMaster,
+    actions: Vec<Acton>,
+    timeout: Option<Duration>,
+    max_failures: u32,
+    turnaround: Duration,
+    health_coil: Option<u16>,
+    health: RefCell<DeviceHealth>,
+}
+
+impl BusDevice {
+    pub fn new(id: u8, actions: Vec<Acton>) -> Self {
+        let device = Self {
+            master: ModbusMaster::new(id, ModbusProto::Rtu),
+            actions: Vec::new(),
+            timeout: None,
+            max_failures: 3,
+            turnaround: Duration::ZERO,
+            health_coil: None,
+            health: RefCell::new(DeviceHealth::default()),
+        };
+
+        device.add_actions(actions)
+    }
+
+    pub fn broadcast(actions: Vec<Acton>) -> Self {
+        Self::new(0, actions).turnaround(Duration::from_millis(100))
+    }
+
+    pub fn add_actions(mut self, actions: Vec<Acton>) -> Self {
+        if self.master.is_broadcast() && actions.iter().any(|action| action.is_read()) {
+            panic!("{}", fail_strig(&io::Error::new(io::ErrorKind::InvalidInput, "read actions can not be broadcast")));
+        }
+
+        self.actions.extend(actions);
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn max_failures(mut self, max_failures: u32) -> Self {
+        self.max_failures = max_failures.max(1);
+        self
+    }
+
+    pub fn turnaround(mut self, turnaround: Duration) -> Self {
+        self.turnaround = turnaround;
+        self
+    }
+
+    pub fn health_coil(mut self, coil: u16) -> Self {
+        self.health_coil = Some(coil);
+        self
+    }
+
+    pub fn get_id(&self) -> u8 { self.master.get_id() }
+
+    pub fn get_health(&self) -> DeviceHealth {
+        self.health.borrow().clone()
+    }
+
+    fn record(&self, result: &Result<(), ModbusErr>) {
+        let mut health = self.health.borrow_mut();
+
+        match result {
+            Ok(()) => health.success(),
+            Err(ModbusErr::Io(e)) => health.failure(e, is_timeout(e), self.max_failures),
+            Err(e) => health.failure(e, false, self.max_failures),
+        }
+    }
+
+    fn update_health_coil(&self, context: &mut dyn DataMemory) -> Result<(), rmodbus::ErrorKind> {
+        match self.health_coil {
+            Some(coil) => context.set_coil(coil, self.health.borrow().is_online()),
+            None => Ok(()),
+        }
+    }
+}
+
+pub struct ModbusRtuBus {
+    link: SerialLink,
+    devices: Vec<BusDevice>,
+    next_device: Cell<usize>,
+    retries: u32,
+    merge_gap: Option<u16>,
+}
+
+impl ModbusRtuBus {
+    pub fn new(port: &'static str, settings: serial::PortSettings, devices: Vec<BusDevice>, timeout_heandler: TimeautHeandler) -> Self {
+        let link = SerialLink::new(port, settings, timeout_heandler.get_timeout());
+
+        Self { link, devices, next_device: Cell::new(0), retries: 0, merge_gap: None }
+    }
+
+    pub fn inter_char_timeout(self, timeout: Duration) -> Self {
+        self.link.set_inter_char_timeout(timeout);
+        self
+    }
+
+    pub fn reopen_backoff(self, min: Duration, max: Duration) -> Self {
+        self.link.set_backoff(min, max);
+        self
+    }
+
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    pub fn merge_reads(mut self, gap: u16) -> Self {
+        self.merge_gap = Some(gap);
+        self
+    }
+
+    pub fn get_timing(&self) -> RtuTiming { self.link.get_timing() }
+
+    pub fn get_connection_status(&self) -> ConnectionStatus {
+        self.link.get_status()
+    }
+
+    pub fn get_health(&self, id: u8) -> Option<DeviceHealth> {
+        self.devices.iter().find(|d| d.get_id() == id).map(|d| d.get_health())
+    }
+
+    fn execute(&self, device: &BusDevice, item: &PollItem, context: &mut dyn DataMemory) -> Result<(), ModbusErr> {
+        let timeout = item.get_timeout().or(device.timeout).unwrap_or(self.link.get_timeout());
+
+        let result = item.attempt(self.retries, |item| {
+            self.link.transaction(timeout, |port| {
+                device.master.execute_item(item, context, port)
+            })
+        });
+
+        device.record(&result);
+
+        if result.is_ok() && device.master.is_broadcast() {
+            std::thread::sleep(device.turnaround);
+        }
+
+        match result {
+            Err(ModbusErr::Io(e)) if !is_timeout(&e) => Err(ModbusErr::Io(e)),
+            _ => Ok(()),
+        }
+    }
+
+    fn publish(&self, context: &mut dyn DataMemory) -> Result<(), rmodbus::ErrorKind> {
+        for device in self.devices.iter() {
+            publish_all(&device.actions, context)?;
+            device.update_health_coil(context)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn interleave<T>(due: Vec<Vec<T>>, start: usize) -> Vec<(usize, T)> {
+    let count = due.len();
+    let mut queues: Vec<_> = due.into_iter().map(|d| d.into_iter()).collect();
+    let mut order = Vec::new();
+
+    loop {
+        let len = order.len();
+
+        for k in 0..count {
+            let device = (start + k) % count;
+
+            if let Some(item) = queues[device].next() {
+                order.push((device, item));
+            }
+        }
+
+        if order.len() == len {
+            return order;
+        }
+    }
+}
+
+impl ConstProgram for ModbusRtuBus {
+    fn run(&self, context: &mut dyn DataMemory) -> std::result::Result<(), Box<dyn std::error::Error>> {
+
+        if !self.link.ensure_open() {
+            for device in self.devices.iter() {
+                mark_offline(&device.actions, context)?;
+            }
+
+            self.publish(context)?;
+            return Ok(());
+        }
+
+        let mut due = Vec::with_capacity(self.devices.len());
+
+        for device in self.devices.iter() {
+            due.push(plan(due_actions(&device.actions, context)?, self.merge_gap));
+        }
+
+        let start = self.next_device.get();
+        self.next_device.set((start + 1) % self.devices.len().max(1));
+
+        for (device, item) in interleave(due, start) {
+            let device = &self.devices[device];
+
+            if let Err(err) = self.execute(device, &item, context) {
+                self.publish(context)?;
+                return Err(Box::new(err));
+            }
+        }
+
+        self.publish(context)?;
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_bus_interleave() {
+    let due = vec![vec![0, 1, 2], vec![], vec![3], vec![4, 5]];
+
+    assert_eq!(interleave(due.clone(), 0), vec![(0, 0), (2, 3), (3, 4), (0, 1), (3, 5), (0, 2)]);
+    assert_eq!(interleave(due, 3), vec![(3, 4), (0, 0), (2, 3), (3, 5), (0, 1), (0, 2)]);
+    assert!(interleave(Vec::<Vec<usize>>::new(), 0).is_empty());
+}
+
+#[test]
+fn test_bus_device_health() {
+    use std::io;
+    use crate::memory::PlcMemory;
+
+    let device = BusDevice::new(7, Vec::new()).max_failures(2).health_coil(3);
+    let mut memory = PlcMemory::new(10, 10, 10, 10, 0);
+
+    device.record(&Ok(()));
+    device.update_health_coil(&mut memory).unwrap();
+    assert!(memory.get_coil(3).unwrap());
+
+    device.record(&Err(ModbusErr::Io(io::ErrorKind::TimedOut.into())));
+    assert!(device.get_health().is_online());
+
+    device.record(&Err(ModbusErr::Rmodbus(rmodbus::ErrorKind::FrameCRCError)));
+    device.update_health_coil(&mut memory).unwrap();
+    assert!(!memory.get_coil(3).unwrap());
+
+    let health = device.get_health();
+    assert_eq!(health.get_requests(), 3);
+    assert_eq!(health.get_failures(), 2);
+    assert_eq!(health.get_timeouts(), 1);
+    assert!(health.get_last_error().is_some());
+    assert!(BusDevice::broadcast(Vec::new()).master.is_broadcast());
+}
+
+#[test]
+fn test_bus_broadcast_rejects_reads() {
+    let write = Acton::cycle_write_holding(1, Duration::ZERO, |_, _| 5);
+    let device = BusDevice::broadcast(vec![write]);
+    assert_eq!(device.actions.len(), 1);
+
+    let read = || Acton::cycle_read_holdings(0, 2, Duration::ZERO, |_, _| {});
+    assert!(std::panic::catch_unwind(|| BusDevice::broadcast(vec![read()])).is_err());
+    assert!(std::panic::catch_unwind(|| BusDevice::broadcast(Vec::new()).add_actions(vec![read()])).is_err());
+    assert_eq!(BusDevice::new(4, vec![read()]).actions.len(), 1);
+}
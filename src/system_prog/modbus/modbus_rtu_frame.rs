@@ -0,0 +1,163 @@
+use std::io;
+use std::time::Duration;
+use super::modbus_counters::BusCounters;
+use super::modbus_pdu;
+
+const MAX_FRAME: usize = 256;
+
+pub(crate) struct RtuFrameAssembler {
+    buffer: Vec<u8>,
+    t35: Duration,
+}
+
+impl RtuFrameAssembler {
+    pub(crate) fn new(t35: Duration) -> Self {
+        Self { buffer: Vec::with_capacity(MAX_FRAME), t35 }
+    }
+
+    pub(crate) fn receive(
+        &mut self,
+        counters: &mut BusCounters,
+        mut read: impl FnMut(Duration) -> io::Result<Vec<u8>>,
+    ) -> io::Result<Vec<Vec<u8>>> {
+
+        let mut frames = Vec::new();
+
+        self.push(&read(Duration::ZERO)?, counters);
+        self.take_frames(&mut frames);
+
+        if self.buffer.is_empty() {
+            return Ok(frames);
+        }
+
+        self.push(&read(self.t35)?, counters);
+        self.take_frames(&mut frames);
+        frames.extend(self.silence(counters));
+
+        Ok(frames)
+    }
+
+    fn push(&mut self, data: &[u8], counters: &mut BusCounters) {
+        self.buffer.extend_from_slice(data);
+
+        if self.buffer.len() > MAX_FRAME * 2 {
+            counters.overrun();
+            self.buffer.clear();
+        }
+    }
+
+    fn take_frames(&mut self, frames: &mut Vec<Vec<u8>>) {
+        while let Some(len) = valid_frame_len(&self.buffer) {
+            frames.push(self.buffer.drain(..len).collect());
+        }
+    }
+
+    fn silence(&mut self, counters: &mut BusCounters) -> Option<Vec<u8>> {
+        if self.buffer.is_empty() {
+            return None;
+        }
+
+        let frame = std::mem::take(&mut self.buffer);
+
+        if frame.len() >= 4 && frame.len() <= MAX_FRAME && has_crc(&frame) {
+            return Some(frame);
+        }
+
+        match complete_len(&frame) {
+            Some(_) => counters.crc_error(),
+            None => counters.framing_error(),
+        }
+
+        None
+    }
+}
+
+fn complete_len(frame: &[u8]) -> Option<usize> {
+    match modbus_pdu::rtu_request_len(frame)? {
+        len if len >= 4 && len <= frame.len() && len <= MAX_FRAME => Some(len),
+        _ => None,
+    }
+}
+
+fn has_crc(frame: &[u8]) -> bool {
+    let len = frame.len();
+    frame[len - 2..] == modbus_pdu::crc16(&frame[..len - 2]).to_le_bytes()
+}
+
+fn valid_frame_len(frame: &[u8]) -> Option<usize> {
+    let len = complete_len(frame)?;
+
+    match has_crc(&frame[..len]) {
+        true => Some(len),
+        false => None,
+    }
+}
+
+#[test]
+fn test_rtu_frame_assembly() {
+    use std::cell::Cell;
+    use std::time::Instant;
+    use rmodbus::ModbusProto;
+    use super::modbus_rtu_timing::RtuTiming;
+
+    let timing = RtuTiming::new(9600, 11);
+    let read = modbus_pdu::wrap(ModbusProto::Rtu, 1, 0, &[3, 0, 0, 0, 2]);
+    let write = modbus_pdu::wrap(ModbusProto::Rtu, 1, 0, &[16, 0, 1, 0, 2, 4, 0, 7, 0, 8]);
+    let reply = modbus_pdu::wrap(ModbusProto::Rtu, 2, 0, &[3, 2, 0, 5]);
+    let mut corrupt = read.clone();
+    corrupt[7] ^= 0xff;
+
+    let mut line = Vec::new();
+    let mut at = Duration::ZERO;
+    for frame in [&write, &read, &reply, &read[..3], &corrupt] {
+        for byte in frame.iter() {
+            line.push((at, *byte));
+            at += timing.get_char_time();
+        }
+        at += timing.get_t35() * 4;
+    }
+
+    let start = Instant::now();
+    let next = Cell::new(0);
+    let mut port = |timeout: Duration| -> io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        let mut idle_since = start.elapsed();
+
+        loop {
+            while next.get() < line.len() && line[next.get()].0 <= start.elapsed() {
+                data.push(line[next.get()].1);
+                idle_since = line[next.get()].0;
+                next.set(next.get() + 1);
+            }
+
+            if start.elapsed() >= idle_since + timeout {
+                return Ok(data);
+            }
+
+            std::thread::sleep(Duration::from_micros(100));
+        }
+    };
+
+    let mut counters = BusCounters::default();
+    let mut assembler = RtuFrameAssembler::new(timing.get_t35());
+    let mut frames = Vec::new();
+
+    std::thread::sleep(timing.get_char_time() * 5);
+    while next.get() < line.len() {
+        frames.extend(assembler.receive(&mut counters, &mut port).unwrap());
+        std::thread::sleep(Duration::from_millis(7));
+    }
+
+    assert_eq!(frames, vec![write, read, reply]);
+    assert_eq!(counters.get_framing_errors(), 1);
+    assert_eq!(counters.get_crc_errors(), 1);
+
+    let mut pipelined = modbus_pdu::wrap(ModbusProto::Rtu, 1, 0, &[6, 0, 1, 0, 2]);
+    pipelined.extend(modbus_pdu::wrap(ModbusProto::Rtu, 1, 0, &[6, 0, 2, 0, 3]));
+    let mut chunks = vec![pipelined, Vec::new()].into_iter();
+    assert_eq!(assembler.receive(&mut counters, |_| Ok(chunks.next().unwrap())).unwrap().len(), 2);
+
+    let mut chunks = vec![vec![0u8; MAX_FRAME * 2 + 1], Vec::new()].into_iter();
+    assert!(assembler.receive(&mut counters, |_| Ok(chunks.next().unwrap())).unwrap().is_empty());
+    assert_eq!(counters.get_overruns(), 1);
+}
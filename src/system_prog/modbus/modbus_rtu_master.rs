@@ -1,68 +1,111 @@
 use super::modbus_master::ModbusMaster;
-use super::modbus_master_actions::{Acton};
+use super::modbus_master_actions::{Acton, mark_offline, publish_all};
 use super::modbus_error::ModbusErr;
+use super::modbus_poll_plan::{plan, due_actions};
+use super::modbus_connection::ConnectionStatus;
+use super::modbus_rtu_timing::RtuTiming;
+use super::modbus_serial_link::{SerialLink, is_timeout};
 use rmodbus::ModbusProto;
-use rmodbus::server::context::ModbusContext;
+use crate::memory::DataMemory;
 use crate::task::ConstProgram;
 use std::time::Duration;
-use std::{io,  result, error};
 use super::timeaut_heandler::TimeautHeandler;
-use serial::SerialPort;
-pub use serial::PortSettings;
 
-pub struct ModbusRtuMaster<const N: usize> {
-    port: &'static str,
-    settings: serial::PortSettings,
+pub struct ModbusRtuMaster {
+    link: SerialLink,
     modbus_master: ModbusMaster,
-    actions: [Acton; N],
-    timeout_heandler: TimeautHeandler,
+    actions: Vec<Acton>,
+    retries: u32,
+    merge_gap: Option<u16>,
 }
 
-impl<const N: usize> ModbusRtuMaster<N> {
-    pub fn new(id: u8, port: &'static str, settings: serial::PortSettings, actions: [Acton; N], timeout_heandler: TimeautHeandler) -> Self {
+impl ModbusRtuMaster {
+    pub fn new<const N: usize>(id: u8, port: &'static str, settings: serial::PortSettings, actions: [Acton; N], timeout_heandler: TimeautHeandler) -> Self {
         
         let modbus_master = ModbusMaster::new(id, ModbusProto::Rtu);
+        let link = SerialLink::new(port, settings, timeout_heandler.get_timeout());
 
-        Self { port, modbus_master, actions, timeout_heandler, settings }
+        Self { link, modbus_master, actions: Vec::from(actions), retries: 0, merge_gap: None }
     }
 
-    fn create_prot(&self) -> result::Result<serial::SystemPort, Box<dyn error::Error>> {
-        let mut port = serial::open(self.port)?;
+    pub fn ascii(mut self) -> Self {
+        self.modbus_master = ModbusMaster::new(self.modbus_master.get_id(), ModbusProto::Ascii);
+        self.link.set_inter_char_timeout(Duration::from_secs(1));
+        self
+    }
+
+    pub fn inter_char_timeout(self, timeout: Duration) -> Self {
+        self.link.set_inter_char_timeout(timeout);
+        self
+    }
+
+    pub fn reopen_backoff(self, min: Duration, max: Duration) -> Self {
+        self.link.set_backoff(min, max);
+        self
+    }
+
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    pub fn merge_reads(mut self, gap: u16) -> Self {
+        self.merge_gap = Some(gap);
+        self
+    }
+
+    pub fn add_actions(mut self, actions: Vec<Acton>) -> Self {
+        self.actions.extend(actions);
+        self
+    }
 
-        port.configure(&self.settings)?; 
+    pub fn get_actions(&self) -> &[Acton] { &self.actions }
 
-        port.set_timeout(self.timeout_heandler.get_timeout())?; 
+    pub fn get_timing(&self) -> RtuTiming { self.link.get_timing() }
 
-        Ok(port)
+    pub fn get_connection_status(&self) -> ConnectionStatus {
+        self.link.get_status()
     }
 }
 
-impl<const N: usize> ConstProgram for ModbusRtuMaster<N> {
-    fn run(&self, context: &mut ModbusContext) -> std::result::Result<(), Box<dyn std::error::Error>> {
+impl ConstProgram for ModbusRtuMaster {
+    fn run(&self, context: &mut dyn DataMemory) -> std::result::Result<(), Box<dyn std::error::Error>> {
 
-        let mut serial_port = self.create_prot()?;
+        if !self.link.ensure_open() {
+            mark_offline(&self.actions, context)?;
+            publish_all(&self.actions, context)?;
+            return Ok(());
+        }
 
-        std::thread::sleep(Duration::from_millis(50));
+        let mut result: std::result::Result<(), Box<dyn std::error::Error>> = Ok(());
 
-        for action in self.actions.iter() {
+        for item in plan(due_actions(&self.actions, context)?, self.merge_gap) {
 
-            if !action.need_run(context)? {
-                continue;
-            }
+            let timeout = item.get_timeout().unwrap_or(self.link.get_timeout());
 
-            let result = self.modbus_master.execute_action(action, context, &mut serial_port);
+            let action_result = item.attempt(self.retries, |item| {
+                self.link.transaction(timeout, |port| {
+                    self.modbus_master.execute_item(item, context, port)
+                })
+            });
 
-            if let Err(err) = result {
-                match err {
-                    ModbusErr::Io(ref e)
-                        if e.kind() == io::ErrorKind::TimedOut || e.kind() == io::ErrorKind::WouldBlock => {
-                            continue; //TODO
-                        }
-                    _ => return Err(Box::new(err))
+            match action_result {
+                Ok(()) => {},
+                Err(ModbusErr::Io(ref e)) if is_timeout(e) => {},
+                Err(err @ ModbusErr::Io(_)) => {
+                    publish_all(&self.actions, context)?;
+                    return Err(Box::new(err));
+                }
+                Err(err) => {
+                    if result.is_ok() {
+                        result = Err(Box::new(err));
+                    }
                 }
             }
         }
 
-        Ok(())
+        publish_all(&self.actions, context)?;
+
+        result
     }
-}
\ No newline at end of file
+}
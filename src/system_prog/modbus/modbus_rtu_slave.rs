@@ -1,18 +1,33 @@
-use std::{result, error, io, time::Duration};
-use std::cell::RefCell;
+use std::{result, error, io};
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+use std::cell::{Cell, RefCell};
 
 use crate::fail_strig;
 use crate::task::ConstProgram;
-use rmodbus::server::context::ModbusContext;
+use crate::memory::DataMemory;
 use rmodbus::ModbusProto;
 use serial::SerialPort;
-pub use serial::PortSettings;
 use super::modbus_slave::ModbusSlave;
+use super::modbus_access::AccessRule;
+use super::modbus_unit::{UnitContext, UnitWindow};
+use super::modbus_write_event::{ModbusClient, WriteHook, WriteQueue};
 use super::modbus_error::ModbusErr;
+use super::modbus_allow_list::ClientClass;
+use super::modbus_pdu;
+use super::modbus_rtu_frame::RtuFrameAssembler;
+use super::modbus_rtu_timing::RtuTiming;
+use super::modbus_counters::BusCounters;
 
 pub struct ModbusRtuSlave {
     port: RefCell<serial::SystemPort>,
+    listen: &'static str,
     modbus_slave: ModbusSlave,
+    ascii: bool,
+    inter_char_timeout: Duration,
+    buffer: RefCell<Vec<u8>>,
+    last_char: Cell<Instant>,
+    assembler: RefCell<RtuFrameAssembler>,
 }
 
 impl ModbusRtuSlave {
@@ -23,7 +38,130 @@ impl ModbusRtuSlave {
 
         let modbus_slave = ModbusSlave::new(id, ModbusProto::Rtu);
 
-        Self { port: RefCell::new(port), modbus_slave }
+        Self {
+            port: RefCell::new(port),
+            listen,
+            modbus_slave,
+            ascii: false,
+            inter_char_timeout: Duration::from_secs(1),
+            buffer: RefCell::new(Vec::new()),
+            last_char: Cell::new(Instant::now()),
+            assembler: RefCell::new(RtuFrameAssembler::new(RtuTiming::from_settings(&settings).get_t35())),
+        }
+    }
+
+    pub fn access_rules<const N: usize>(mut self, rules: [AccessRule; N]) -> Self {
+        self.modbus_slave = self.modbus_slave.access_rules(rules);
+        self
+    }
+
+    pub fn write_hooks<const N: usize>(mut self, hooks: [WriteHook; N]) -> Self {
+        self.modbus_slave = self.modbus_slave.write_hooks(hooks);
+        self
+    }
+
+    pub fn write_queue(mut self, queue: WriteQueue) -> Self {
+        self.modbus_slave = self.modbus_slave.write_queue(queue);
+        self
+    }
+
+    pub fn unit_context(mut self, id: u8, context: UnitContext) -> Self {
+        self.modbus_slave = self.modbus_slave.unit_context(id, context);
+        self
+    }
+
+    pub fn unit_window(mut self, id: u8, window: UnitWindow) -> Self {
+        self.modbus_slave = self.modbus_slave.unit_window(id, window);
+        self
+    }
+
+    pub fn ascii(mut self) -> Self {
+        self.modbus_slave = self.modbus_slave.with_proto(ModbusProto::Ascii);
+        self.ascii = true;
+        self
+    }
+
+    pub fn inter_char_timeout(mut self, timeout: Duration) -> Self {
+        self.inter_char_timeout = timeout;
+        self
+    }
+
+    pub fn get_counters(&self) -> BusCounters {
+        self.modbus_slave.get_counters()
+    }
+
+    pub fn get_event_log(&self) -> Vec<u8> {
+        self.modbus_slave.get_event_log()
+    }
+
+    pub fn clear_counters(&self) {
+        self.modbus_slave.clear_counters();
+    }
+
+    fn receive(port: &mut serial::SystemPort) -> io::Result<Vec<u8>> {
+        let mut received = Vec::new();
+        let mut chunk = [0u8; 256];
+
+        loop {
+            match port.read(&mut chunk) {
+                Ok(0) => return Ok(received),
+                Ok(n) => received.extend_from_slice(&chunk[..n]),
+                Err(ref e) if e.kind() == io::ErrorKind::TimedOut || e.kind() == io::ErrorKind::WouldBlock => return Ok(received),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn run_rtu(&self, context: &mut dyn DataMemory, client: &ModbusClient) -> result::Result<(), ModbusErr> {
+        let mut port = self.port.borrow_mut();
+        let mut assembler = self.assembler.borrow_mut();
+
+        let frames = self.modbus_slave.with_counters(|counters| assembler.receive(counters, |timeout| {
+            port.set_timeout(timeout)?;
+            let received = Self::receive(&mut port);
+            port.set_timeout(Duration::ZERO)?;
+            received
+        }))?;
+
+        for frame in frames {
+            let response = match self.modbus_slave.process(&frame, context, client, ClientClass::ReadWrite) {
+                Ok(response) => response,
+                Err(ModbusErr::Rmodbus(_)) => None,
+                Err(err) => return Err(err),
+            };
+
+            if let Some(response) = response {
+                port.write_all(&response)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn run_ascii(&self, context: &mut dyn DataMemory, client: &ModbusClient) -> result::Result<(), ModbusErr> {
+        let mut port = self.port.borrow_mut();
+        let mut buffer = self.buffer.borrow_mut();
+
+        if !buffer.is_empty() && self.last_char.get().elapsed() > self.inter_char_timeout {
+            buffer.clear();
+        }
+
+        let received = Self::receive(&mut port)?;
+
+        if !received.is_empty() {
+            buffer.extend_from_slice(&received);
+            self.last_char.set(Instant::now());
+        }
+
+        while let Some(frame) = modbus_pdu::take_ascii_frame(&mut buffer) {
+            match self.modbus_slave.process(&frame, context, client, ClientClass::ReadWrite) {
+                Ok(Some(response)) => port.write_all(&response)?,
+                Ok(None) | Err(ModbusErr::Rmodbus(_)) => {},
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
     }
 
     fn create_prot(listen: &'static str, settings: serial::PortSettings) -> serial::SystemPort {
@@ -41,15 +179,15 @@ impl ModbusRtuSlave {
 }
 
 
-impl<'a> ConstProgram for ModbusRtuSlave {
-    fn run(&self, context: &mut ModbusContext) -> result::Result<(), Box<dyn error::Error>> {
+impl ConstProgram for ModbusRtuSlave {
+    fn run(&self, context: &mut dyn DataMemory) -> result::Result<(), Box<dyn error::Error>> {
         
-        match self.modbus_slave.handler(&mut *self.port.borrow_mut(), context) {
-            Ok(_) => Ok(()),
-            Err(err) => match err {
-                ModbusErr::Io(ref e) if e.kind() == io::ErrorKind::TimedOut => return Ok(()),
-                _ => return Err(Box::new(err)),
-            }
+        let client = ModbusClient::Serial(self.listen);
+
+        if self.ascii {
+            return Ok(self.run_ascii(context, &client)?);
         }
+
+        Ok(self.run_rtu(context, &client)?)
     }
 }
\ No newline at end of file
@@ -0,0 +1,89 @@
+use std::time::{Duration, Instant};
+use serial::{PortSettings, CharSize, Parity, StopBits};
+
+const MIN_INTER_CHAR: Duration = Duration::from_millis(20);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RtuTiming {
+    char_time: Duration,
+    t15: Duration,
+    t35: Duration,
+}
+
+impl RtuTiming {
+    pub fn from_settings(settings: &PortSettings) -> Self {
+        let data_bits = match settings.char_size {
+            CharSize::Bits5 => 5,
+            CharSize::Bits6 => 6,
+            CharSize::Bits7 => 7,
+            CharSize::Bits8 => 8,
+        };
+        let parity_bits = match settings.parity {
+            Parity::ParityNone => 0,
+            Parity::ParityOdd | Parity::ParityEven => 1,
+        };
+        let stop_bits = match settings.stop_bits {
+            StopBits::Stop1 => 1,
+            StopBits::Stop2 => 2,
+        };
+
+        Self::new(settings.baud_rate.speed() as u32, 1 + data_bits + parity_bits + stop_bits)
+    }
+
+    pub fn new(baud_rate: u32, bits_per_char: u32) -> Self {
+        let char_time = Duration::from_nanos(bits_per_char as u64 * 1_000_000_000 / baud_rate.max(1) as u64);
+
+        let (t15, t35) = if baud_rate > 19200 {
+            (Duration::from_micros(750), Duration::from_micros(1750))
+        } else {
+            (char_time * 3 / 2, char_time * 7 / 2)
+        };
+
+        Self { char_time, t15, t35 }
+    }
+
+    pub fn get_char_time(&self) -> Duration { self.char_time }
+    pub fn get_t15(&self) -> Duration { self.t15 }
+    pub fn get_t35(&self) -> Duration { self.t35 }
+
+    pub fn get_inter_char_timeout(&self) -> Duration {
+        self.t35.max(MIN_INTER_CHAR)
+    }
+
+    pub fn frame_time(&self, len: usize) -> Duration {
+        self.char_time * len as u32
+    }
+
+    pub(crate) fn wait_silence(&self, last_activity: Instant) {
+        let elapsed = last_activity.elapsed();
+
+        if elapsed < self.t35 {
+            std::thread::sleep(self.t35 - elapsed);
+        }
+    }
+}
+
+#[test]
+fn test_rtu_timing() {
+    let settings = PortSettings {
+        baud_rate: serial::Baud9600,
+        char_size: CharSize::Bits8,
+        parity: Parity::ParityNone,
+        stop_bits: StopBits::Stop1,
+        flow_control: serial::FlowNone,
+    };
+
+    let timing = RtuTiming::from_settings(&settings);
+    assert_eq!(timing.get_char_time(), Duration::from_nanos(1_041_666));
+    assert_eq!(timing.get_t15(), Duration::from_nanos(1_562_499));
+    assert_eq!(timing.get_t35(), Duration::from_nanos(3_645_831));
+
+    let timing = RtuTiming::from_settings(&PortSettings { parity: Parity::ParityEven, ..settings });
+    assert_eq!(timing.get_char_time(), Duration::from_nanos(1_145_833));
+
+    let timing = RtuTiming::from_settings(&PortSettings { baud_rate: serial::Baud115200, ..settings });
+    assert_eq!(timing.get_t15(), Duration::from_micros(750));
+    assert_eq!(timing.get_t35(), Duration::from_micros(1750));
+    assert_eq!(timing.get_inter_char_timeout(), Duration::from_millis(20));
+    assert_eq!(RtuTiming::new(300, 11).get_inter_char_timeout(), Duration::from_nanos(128_333_331));
+}
@@ -0,0 +1,192 @@
+use super::modbus_error::ModbusErr;
+use super::modbus_connection::{ConnectionStatus, Reconnect};
+use super::modbus_rtu_timing::RtuTiming;
+use std::time::{Duration, Instant};
+use std::cell::{Cell, RefCell};
+use std::{io, result, error};
+use serial::SerialPort;
+
+pub(crate) struct SerialLink {
+    port: &'static str,
+    settings: serial::PortSettings,
+    timeout: Duration,
+    timing: RtuTiming,
+    serial_port: RefCell<Option<serial::SystemPort>>,
+    port_timeout: Cell<Duration>,
+    last_activity: Cell<Instant>,
+    inter_char_timeout: Cell<Duration>,
+    reconnect: RefCell<Reconnect>,
+}
+
+impl SerialLink {
+    pub(crate) fn new(port: &'static str, settings: serial::PortSettings, timeout: Duration) -> Self {
+        Self {
+            port,
+            settings,
+            timeout,
+            timing: RtuTiming::from_settings(&settings),
+            inter_char_timeout: Cell::new(RtuTiming::from_settings(&settings).get_inter_char_timeout()),
+            serial_port: RefCell::new(None),
+            port_timeout: Cell::new(timeout),
+            last_activity: Cell::new(Instant::now()),
+            reconnect: RefCell::new(Reconnect::new(Duration::from_millis(100), Duration::from_secs(30))),
+        }
+    }
+
+    pub(crate) fn set_backoff(&self, min: Duration, max: Duration) {
+        self.reconnect.borrow_mut().set_backoff(min, max);
+    }
+
+    pub(crate) fn set_inter_char_timeout(&self, timeout: Duration) {
+        self.inter_char_timeout.set(timeout);
+    }
+
+    pub(crate) fn get_timing(&self) -> RtuTiming { self.timing }
+
+    pub(crate) fn get_timeout(&self) -> Duration { self.timeout }
+
+    pub(crate) fn get_status(&self) -> ConnectionStatus {
+        self.reconnect.borrow().get_status().clone()
+    }
+
+    fn create_prot(&self) -> result::Result<serial::SystemPort, Box<dyn error::Error>> {
+        let mut port = serial::open(self.port)?;
+
+        port.configure(&self.settings)?; 
+
+        port.set_timeout(self.timeout)?; 
+
+        Ok(port)
+    }
+
+    pub(crate) fn ensure_open(&self) -> bool {
+        if self.serial_port.borrow().is_some() {
+            return true;
+        }
+
+        let mut reconnect = self.reconnect.borrow_mut();
+
+        if !reconnect.can_attempt() {
+            return false;
+        }
+
+        reconnect.connecting();
+
+        match self.create_prot() {
+            Ok(port) => {
+                *self.serial_port.borrow_mut() = Some(port);
+                self.port_timeout.set(self.timeout);
+                self.last_activity.set(Instant::now());
+                reconnect.connected();
+                true
+            },
+            Err(e) => {
+                reconnect.failed(&e);
+                false
+            },
+        }
+    }
+
+    pub(crate) fn transaction<R>(
+        &self,
+        timeout: Duration,
+        exec: impl FnOnce(&mut RtuPort) -> result::Result<R, ModbusErr>,
+    ) -> result::Result<R, ModbusErr> {
+
+        let mut serial_port = self.serial_port.borrow_mut();
+        let port = serial_port.as_mut().ok_or_else(|| io::Error::from(io::ErrorKind::NotConnected))?;
+
+        if self.port_timeout.get() != timeout {
+            port.set_timeout(timeout).map_err(io::Error::from)?;
+            self.port_timeout.set(timeout);
+        }
+
+        self.timing.wait_silence(self.last_activity.get());
+
+        let mut rtu_port = RtuPort::new(port, timeout, self.inter_char_timeout.get());
+        let result = exec(&mut rtu_port);
+        self.port_timeout.set(rtu_port.current);
+        self.last_activity.set(Instant::now());
+
+        match &result {
+            Err(ModbusErr::Io(e)) if is_timeout(e) => {
+                drain(port, self.timing.get_t35());
+                self.last_activity.set(Instant::now());
+            },
+            Err(ModbusErr::Io(e)) => {
+                self.reconnect.borrow_mut().failed(e);
+                *serial_port = None;
+            },
+            _ => {},
+        }
+
+        result
+    }
+}
+
+pub(crate) struct RtuPort<'a> {
+    port: &'a mut serial::SystemPort,
+    response_timeout: Duration,
+    inter_char_timeout: Duration,
+    current: Duration,
+}
+
+impl<'a> RtuPort<'a> {
+    fn new(port: &'a mut serial::SystemPort, response_timeout: Duration, inter_char_timeout: Duration) -> Self {
+        Self { port, response_timeout, inter_char_timeout, current: response_timeout }
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        if self.current != timeout {
+            self.port.set_timeout(timeout).map_err(io::Error::from)?;
+            self.current = timeout;
+        }
+
+        Ok(())
+    }
+}
+
+impl io::Read for RtuPort<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.port.read(buf)?;
+
+        if n > 0 {
+            self.set_timeout(self.inter_char_timeout)?;
+        }
+
+        Ok(n)
+    }
+}
+
+impl io::Write for RtuPort<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.set_timeout(self.response_timeout)?;
+        self.port.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.port.flush()
+    }
+}
+
+pub(crate) fn is_timeout(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::TimedOut || err.kind() == io::ErrorKind::WouldBlock
+}
+
+fn drain(port: &mut serial::SystemPort, silence: Duration) {
+    use io::Read;
+
+    let timeout = port.timeout();
+    if port.set_timeout(silence).is_err() {
+        return;
+    }
+
+    let mut buf = [0u8; 256];
+    while let Ok(n) = port.read(&mut buf) {
+        if n == 0 {
+            break;
+        }
+    }
+
+    let _ = port.set_timeout(timeout);
+}
@@ -1,48 +1,515 @@
 use std::{result,io};
-use rmodbus::server::context::ModbusContext;
+use std::cell::RefCell;
+use crate::memory::DataMemory;
 use rmodbus::server::ModbusFrame;
 use rmodbus::ModbusProto;
 use rmodbus::ModbusFrameBuf;
 use super::modbus_error::ModbusErr;
+use super::modbus_access::{self, AccessRule};
+use super::modbus_process;
+use super::modbus_pdu;
+use super::modbus_write_event::{ModbusClient, WriteHook, WriteQueue, WriteSnapshot};
+use super::modbus_allow_list::ClientClass;
+use rmodbus::consts::MODBUS_ERROR_ILLEGAL_DATA_ADDRESS;
+use super::modbus_unit::{UnitContext, UnitWindow, UnitMap, WindowMemory};
+use super::modbus_counters::BusCounters;
+use super::modbus_diagnostics::{self, Diagnostics, MODBUS_DIAGNOSTICS, MODBUS_GET_COMM_EVENT_COUNTER, MODBUS_GET_COMM_EVENT_LOG};
+use crate::diagnostics::AuditLog;
 
 pub struct ModbusSlave {
     id: u8,
     proto: ModbusProto,
+    rules: Vec<AccessRule>,
+    hooks: Vec<WriteHook>,
+    queue: Option<WriteQueue>,
+    audit: Option<AuditLog>,
+    units: Vec<(u8, UnitMap)>,
+    diagnostics: RefCell<Diagnostics>,
 }
 
 impl ModbusSlave {
     pub fn new (id: u8, proto: ModbusProto) -> Self {
-        Self { id, proto }
+        Self { id, proto, rules: Vec::new(), hooks: Vec::new(), queue: None, audit: None, units: Vec::new(), diagnostics: RefCell::new(Diagnostics::default()) }
+    }
+
+    pub fn access_rules<const N: usize>(mut self, rules: [AccessRule; N]) -> Self {
+        self.rules.extend(rules);
+        self
+    }
+
+    pub fn write_hooks<const N: usize>(mut self, hooks: [WriteHook; N]) -> Self {
+        self.hooks.extend(hooks);
+        self
+    }
+
+    pub fn write_queue(mut self, queue: WriteQueue) -> Self {
+        self.queue = Some(queue);
+        self
+    }
+
+    pub(crate) fn with_proto(mut self, proto: ModbusProto) -> Self {
+        self.proto = proto;
+        self
+    }
+
+    pub fn audit_log(mut self, log: AuditLog) -> Self {
+        self.audit = Some(log);
+        self
+    }
+
+    pub fn unit_context(mut self, id: u8, context: UnitContext) -> Self {
+        self.units.push((id, UnitMap::Context(context)));
+        self
+    }
+
+    pub fn unit_window(mut self, id: u8, window: UnitWindow) -> Self {
+        self.units.push((id, UnitMap::Window(window)));
+        self
+    }
+
+    pub fn get_unit_ids(&self) -> Vec<u8> {
+        std::iter::once(self.id).chain(self.units.iter().map(|(id, _)| *id)).collect()
     }
 
     pub fn handler<T: io::Read + io::Write>(
         &self,
         transport: &mut T,
-        context: &mut ModbusContext,
+        context: &mut dyn DataMemory,
+    ) -> result::Result<(), ModbusErr> {
+        self.client_handler(transport, context, &ModbusClient::Unknown)
+    }
+
+    pub fn client_handler<T: io::Read + io::Write>(
+        &self,
+        transport: &mut T,
+        context: &mut dyn DataMemory,
+        client: &ModbusClient,
     ) -> result::Result<(), ModbusErr> {
 
         loop {
-            let mut response = Vec::with_capacity(8);
             let mut buf: ModbusFrameBuf = [0; 256];
         
-            transport.read(&mut buf)?;
-        
-            let mut frame = ModbusFrame::new(self.id, &buf, self.proto, &mut response);
-        
-            frame.parse()?;
-            
-            if frame.processing_required {
-                match frame.readonly {
-                    true => frame.process_read(context),
-                    false => frame.process_write(context),
-                }?;
+            let len = transport.read(&mut buf)?;
+
+            if len == 0 {
+                return Err(ModbusErr::Io(io::ErrorKind::UnexpectedEof.into()));
             }
-        
-            if frame.response_required {
-                frame.finalize_response()?;
-                transport.write(response.as_slice())?;
+
+            if let Some(response) = self.process(&buf[..len], context, client, ClientClass::ReadWrite)? {
+                transport.write_all(response.as_slice())?;
                 break Ok(());
             }
         }
     }
+
+    pub(crate) fn process(
+        &self,
+        request: &[u8],
+        context: &mut dyn DataMemory,
+        client: &ModbusClient,
+        class: ClientClass,
+    ) -> result::Result<Option<Vec<u8>>, ModbusErr> {
+
+        let decoded;
+        let request = match self.proto {
+            ModbusProto::Ascii => {
+                decoded = modbus_pdu::from_ascii(request).inspect_err(|_| {
+                    self.diagnostics.borrow_mut().comm_error();
+                })?;
+                decoded.as_slice()
+            },
+            _ => request,
+        };
+
+        let start = match self.proto {
+            ModbusProto::TcpUdp => 6,
+            _ => 0,
+        };
+
+        let (Some(&unit), Some(&func)) = (request.get(start), request.get(start + 1)) else {
+            return Err(rmodbus::ErrorKind::FrameBroken.into());
+        };
+
+        modbus_pdu::check_request(&request[start..])?;
+
+        let addressed = match (self.proto, unit) {
+            (ModbusProto::TcpUdp, 255) => self.id,
+            _ => unit,
+        };
+
+        let broadcast = addressed == 0;
+        let route = self.units.iter().find(|(id, _)| *id == addressed && *id != self.id);
+
+        self.diagnostics.borrow_mut().get_counters_mut().bus_message();
+
+        if !broadcast && addressed != self.id && route.is_none() {
+            return Ok(None);
+        }
+
+        let listen_only = self.diagnostics.borrow().is_listen_only();
+        self.diagnostics.borrow_mut().received(broadcast);
+
+        if listen_only && !modbus_diagnostics::is_restart(&request[start + 1..]) {
+            return Ok(None);
+        }
+
+        let result = match (func, route) {
+            (MODBUS_DIAGNOSTICS | MODBUS_GET_COMM_EVENT_COUNTER | MODBUS_GET_COMM_EVENT_LOG, _) => {
+                match broadcast {
+                    true => self.process_diagnostic(unit, request).map(|_| None),
+                    false => self.process_diagnostic(unit, request),
+                }
+            },
+            (_, Some((id, map))) => self.process_unit(*id, request, context, Some(map), client, class),
+            (_, None) => self.process_unit(self.id, request, context, None, client, class),
+        };
+
+        let response = match result {
+            Ok(response) => response,
+            Err(err) => {
+                if let ModbusErr::Rmodbus(rmodbus::ErrorKind::FrameCRCError) = err {
+                    self.diagnostics.borrow_mut().comm_error();
+                }
+                return Err(err);
+            },
+        };
+
+        self.diagnostics.borrow_mut().completed(func, response.as_ref().map(|r| &r[start + 1..]));
+
+        match (self.proto, response) {
+            (ModbusProto::Ascii, Some(response)) => Ok(Some(modbus_pdu::to_ascii(&response))),
+            (_, response) => Ok(response),
+        }
+    }
+
+    fn process_diagnostic(&self, unit: u8, request: &[u8]) -> result::Result<Option<Vec<u8>>, ModbusErr> {
+        let pdu = match self.proto {
+            ModbusProto::TcpUdp => &request[7..],
+            ModbusProto::Rtu => {
+                let len = request.len();
+                if len < 4 || modbus_pdu::crc16(&request[..len - 2]).to_le_bytes() != request[len - 2..] {
+                    return Err(rmodbus::ErrorKind::FrameCRCError.into());
+                }
+                &request[1..len - 2]
+            },
+            ModbusProto::Ascii => {
+                let len = request.len();
+                if len < 3 || modbus_pdu::lrc(&request[..len - 1]) != request[len - 1] {
+                    return Err(rmodbus::ErrorKind::FrameCRCError.into());
+                }
+                &request[1..len - 1]
+            },
+        };
+
+        let Some(response) = self.diagnostics.borrow_mut().process(pdu) else {
+            return Ok(None);
+        };
+
+        match self.proto {
+            ModbusProto::TcpUdp => {
+                let tr_id = u16::from_be_bytes([request[0], request[1]]);
+                Ok(Some(modbus_pdu::wrap(ModbusProto::TcpUdp, unit, tr_id, &response)))
+            },
+            ModbusProto::Rtu => Ok(Some(modbus_pdu::wrap(ModbusProto::Rtu, unit, 0, &response))),
+            ModbusProto::Ascii => {
+                let mut frame = vec![unit];
+                frame.extend_from_slice(&response);
+                frame.push(modbus_pdu::lrc(&frame));
+                Ok(Some(frame))
+            },
+        }
+    }
+
+    pub fn get_counters(&self) -> BusCounters {
+        self.diagnostics.borrow().get_counters()
+    }
+
+    pub fn get_event_count(&self) -> u16 {
+        self.diagnostics.borrow().get_event_count()
+    }
+
+    pub fn get_event_log(&self) -> Vec<u8> {
+        self.diagnostics.borrow().get_events()
+    }
+
+    pub fn is_listen_only(&self) -> bool {
+        self.diagnostics.borrow().is_listen_only()
+    }
+
+    pub fn clear_counters(&self) {
+        self.diagnostics.borrow_mut().clear();
+    }
+
+    pub(crate) fn with_counters<R>(&self, f: impl FnOnce(&mut BusCounters) -> R) -> R {
+        f(self.diagnostics.borrow_mut().get_counters_mut())
+    }
+
+    fn process_unit(
+        &self,
+        id: u8,
+        request: &[u8],
+        context: &mut dyn DataMemory,
+        unit: Option<&UnitMap>,
+        client: &ModbusClient,
+        class: ClientClass,
+    ) -> result::Result<Option<Vec<u8>>, ModbusErr> {
+
+        let mut buf: ModbusFrameBuf = [0; 256];
+        let len = request.len().min(buf.len());
+        buf[..len].copy_from_slice(&request[..len]);
+
+        if self.proto == ModbusProto::TcpUdp && buf[6] == 255 {
+            buf[6] = id;
+        }
+
+        let mut response = Vec::with_capacity(8);
+        let mut frame = ModbusFrame::new(id, &buf, self.proto, &mut response);
+    
+        frame.parse()?;
+
+        if frame.processing_required && !frame.readonly && class == ClientClass::ReadOnly {
+            frame.error = MODBUS_ERROR_ILLEGAL_DATA_ADDRESS;
+            frame.processing_required = false;
+        }
+
+        let main_reg = match unit {
+            None => Some(frame.reg),
+            Some(UnitMap::Window(window)) => window.main_reg(frame.func, frame.reg),
+            Some(UnitMap::Context(_)) => None,
+        };
+
+        if frame.processing_required && !frame.readonly {
+            let data = &buf[frame.frame_start..];
+            let check = match main_reg {
+                Some(reg) => modbus_access::check_write(&self.rules, context, frame.func, reg, frame.count, data),
+                None => Ok(()),
+            };
+
+            if let Err(code) = check {
+                frame.error = code;
+                frame.processing_required = false;
+            }
+        }
+        
+        if frame.processing_required {
+            match unit {
+                None => self.execute(&mut frame, &buf, context, None, client)?,
+                Some(UnitMap::Window(window)) => self.execute(&mut frame, &buf, context, Some(*window), client)?,
+                Some(UnitMap::Context(unit)) => execute_plain(&mut frame, &buf, &mut *unit.get_memory_mut())?,
+            }
+        }
+    
+        if !frame.readonly {
+            self.audit_write(client, frame.func, main_reg.unwrap_or(frame.reg), frame.count, frame.error);
+        }
+
+        if !frame.response_required {
+            return Ok(None);
+        }
+
+        frame.finalize_response()?;
+
+        if self.proto == ModbusProto::TcpUdp {
+            response[6] = request[6];
+        }
+
+        Ok(Some(response))
+    }
+
+    fn execute(
+        &self,
+        frame: &mut ModbusFrame<Vec<u8>>,
+        buf: &ModbusFrameBuf,
+        context: &mut dyn DataMemory,
+        window: Option<UnitWindow>,
+        client: &ModbusClient,
+    ) -> result::Result<(), ModbusErr> {
+
+        let reg = match window {
+            Some(window) => window.main_reg(frame.func, frame.reg),
+            None => Some(frame.reg),
+        };
+
+        let snapshot = match (frame.readonly || self.hooks.is_empty() && self.queue.is_none(), reg) {
+            (false, Some(reg)) => WriteSnapshot::new(context, frame.func, reg, frame.count),
+            _ => None,
+        };
+
+        match window {
+            Some(window) => execute_plain(frame, buf, &mut WindowMemory::new(context, window))?,
+            None => execute_plain(frame, buf, context)?,
+        }
+
+        if let (Some(snapshot), 0) = (snapshot, frame.error) {
+            snapshot.notify(context, client, &self.hooks, self.queue.as_ref());
+        }
+
+        Ok(())
+    }
+
+    fn audit_write(&self, client: &ModbusClient, func: u8, reg: u16, count: u16, error: u8) {
+        let Some(log) = &self.audit else { return };
+
+        let result = match error {
+            0 => "ok".to_string(),
+            code => format!("exception {:#04x}", code),
+        };
+
+        log.record("modbus", format!(
+            "write fc {} regs {}..{} from {}: {}",
+            func, reg, reg as u32 + count.max(1) as u32, client, result,
+        ));
+    }
+}
+
+fn execute_plain(
+    frame: &mut ModbusFrame<Vec<u8>>,
+    buf: &ModbusFrameBuf,
+    context: &mut dyn DataMemory,
+) -> result::Result<(), ModbusErr> {
+
+    match frame.readonly {
+        true => modbus_process::process_read(frame, buf, context)?,
+        false => modbus_process::process_write(frame, buf, context)?,
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_slave_ascii() {
+    use super::modbus_master::ModbusMaster;
+    use super::modbus_mock::{SimDevice, Fault};
+    use super::modbus_error::ExceptionCode;
+
+    let master = ModbusMaster::new(0x11, ModbusProto::Ascii);
+    let mut device = SimDevice::new(0x11, ModbusProto::Ascii)
+        .fault(Fault::CrcError)
+        .fault(Fault::Exception(ExceptionCode::SlaveDeviceFailure));
+    device.get_context_mut().set_holding(0x6b, 0xae41).unwrap();
+
+    assert!(matches!(master.read_holdings(&mut device, 0x6b, 1), Err(ModbusErr::Rmodbus(rmodbus::ErrorKind::FrameCRCError))));
+    assert!(matches!(
+        master.read_holdings(&mut device, 0x6b, 1),
+        Err(ModbusErr::Exception { function: 3, code: ExceptionCode::SlaveDeviceFailure })
+    ));
+    assert_eq!(master.read_holdings(&mut device, 0x6b, 1).unwrap(), vec![0xae41]);
+
+    master.write_multipl_holding(&mut device, 1, vec![7, 8]).unwrap();
+    assert_eq!(device.get_context().get_holding(2).unwrap(), 8);
+
+    let slave = ModbusSlave::new(0x11, ModbusProto::Ascii);
+    let mut context = rmodbus::server::context::ModbusContext::new();
+    let response = slave.process(b":1103006B00037E\r\n", &mut context, &ModbusClient::Unknown, ClientClass::ReadWrite);
+    assert_eq!(response.unwrap().unwrap(), b":110306000000000000E6\r\n".to_vec());
+    assert!(slave.process(b":1103006B00037F\r\n", &mut context, &ModbusClient::Unknown, ClientClass::ReadWrite).is_err());
+
+    for bytes in [249u8, 248, 247] {
+        let mut frame = vec![0x11, 16, 0, 0, 0, 124, bytes];
+        frame.extend(vec![0u8; bytes as usize]);
+        frame.push(modbus_pdu::lrc(&frame));
+        let text = modbus_pdu::to_ascii(&frame);
+        assert!(slave.process(&text, &mut context, &ModbusClient::Unknown, ClientClass::ReadWrite).is_err());
+    }
+
+    let mut buffer = vec![b':'; 1];
+    buffer.extend(vec![b'0'; 600]);
+    assert!(modbus_pdu::take_ascii_frame(&mut buffer).is_none());
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn test_slave_virtual_units() {
+    use crate::memory::PlcMemory;
+    use super::modbus_access::Access;
+
+    let device = UnitContext::new(0, 0, 0, 10);
+    let queue = WriteQueue::new(8);
+    let log = AuditLog::new(8);
+    let slave = ModbusSlave::new(1, ModbusProto::TcpUdp)
+        .unit_context(2, device.clone())
+        .unit_window(3, UnitWindow::new().holdings(100, 4))
+        .access_rules([AccessRule::holdings(102, 1, Access::ReadOnly)])
+        .write_queue(queue.clone())
+        .audit_log(log.clone());
+    let mut context = PlcMemory::new(10, 10, 10, 200, 0);
+    context.set_holding(101, 55).unwrap();
+    device.get_memory_mut().set_holding(0, 9).unwrap();
+
+    let mut request = |frame: &[u8]| {
+        slave.process(frame, &mut context, &ModbusClient::Unknown, ClientClass::ReadWrite).unwrap()
+    };
+
+    assert_eq!(request(&[0, 1, 0, 0, 0, 6, 2, 3, 0, 0, 0, 1]).unwrap(), vec![0, 1, 0, 0, 0, 5, 2, 3, 2, 0, 9]);
+    assert_eq!(request(&[0, 2, 0, 0, 0, 6, 3, 3, 0, 1, 0, 1]).unwrap(), vec![0, 2, 0, 0, 0, 5, 3, 3, 2, 0, 55]);
+    assert_eq!(request(&[0, 3, 0, 0, 0, 6, 3, 3, 0, 3, 0, 2]).unwrap(), vec![0, 3, 0, 0, 0, 3, 3, 0x83, 2]);
+    assert_eq!(request(&[0, 4, 0, 0, 0, 6, 3, 6, 0, 3, 0, 7]).unwrap(), vec![0, 4, 0, 0, 0, 6, 3, 6, 0, 3, 0, 7]);
+    assert_eq!(request(&[0, 5, 0, 0, 0, 6, 3, 6, 0, 2, 0, 7]).unwrap(), vec![0, 5, 0, 0, 0, 3, 3, 0x86, 2]);
+    assert_eq!(request(&[0, 7, 0, 0, 0, 6, 2, 6, 0, 2, 0, 7]).unwrap(), vec![0, 7, 0, 0, 0, 6, 2, 6, 0, 2, 0, 7]);
+    assert!(request(&[0, 6, 0, 0, 0, 6, 4, 3, 0, 0, 0, 1]).is_none());
+
+    assert_eq!(context.get_holding(103).unwrap(), 7);
+    assert_eq!(context.get_holding(102).unwrap(), 0);
+    assert_eq!(device.get_memory().get_holding(2).unwrap(), 7);
+    assert_eq!(slave.get_unit_ids(), vec![1, 2, 3]);
+
+    let events = queue.drain();
+    assert_eq!(events.len(), 1);
+    assert_eq!((events[0].get_reg(), events[0].get_old(), events[0].get_new()), (103, 0, 7));
+    assert!(log.entries()[0].get_message().starts_with("write fc 6 regs 103..104"));
+}
+
+#[test]
+fn test_slave_diagnostics() {
+    let slave = ModbusSlave::new(1, ModbusProto::Rtu);
+    let mut context = rmodbus::server::context::ModbusContext::new();
+
+    let mut request = |pdu: &[u8]| {
+        let frame = modbus_pdu::wrap(ModbusProto::Rtu, 1, 0, pdu);
+        slave.process(&frame, &mut context, &ModbusClient::Unknown, ClientClass::ReadWrite)
+    };
+
+    assert_eq!(request(&[3, 0, 0, 0, 1]).unwrap(), Some(modbus_pdu::wrap(ModbusProto::Rtu, 1, 0, &[3, 2, 0, 0])));
+    assert_eq!(request(&[3, 0xff, 0xff, 0, 2]).unwrap(), Some(modbus_pdu::wrap(ModbusProto::Rtu, 1, 0, &[0x83, 2])));
+    assert_eq!(request(&[8, 0, 0x0e, 0, 0]).unwrap(), Some(modbus_pdu::wrap(ModbusProto::Rtu, 1, 0, &[8, 0, 0x0e, 0, 3])));
+    assert_eq!(request(&[11]).unwrap(), Some(modbus_pdu::wrap(ModbusProto::Rtu, 1, 0, &[11, 0, 0, 0, 2])));
+    assert_eq!(request(&[8, 0, 4, 0, 0]).unwrap(), None);
+    assert_eq!(request(&[3, 0, 0, 0, 1]).unwrap(), None);
+    assert!(slave.is_listen_only());
+    assert_eq!(request(&[8, 0, 1, 0, 0]).unwrap(), None);
+    assert_eq!(request(&[8, 0, 0x0b, 0, 0]).unwrap(), Some(modbus_pdu::wrap(ModbusProto::Rtu, 1, 0, &[8, 0, 0x0b, 0, 1])));
+
+    let mut broken = modbus_pdu::wrap(ModbusProto::Rtu, 1, 0, &[8, 0, 0, 0, 0]);
+    broken[5] ^= 0xff;
+    assert!(slave.process(&broken, &mut context, &ModbusClient::Unknown, ClientClass::ReadWrite).is_err());
+    assert!(slave.process(&modbus_pdu::wrap(ModbusProto::Rtu, 2, 0, &[11]), &mut context, &ModbusClient::Unknown, ClientClass::ReadWrite).unwrap().is_none());
+
+    let counters = slave.get_counters();
+    assert_eq!(counters.get_bus_messages(), 3);
+    assert_eq!(counters.get_crc_errors(), 1);
+    assert_eq!(counters.get_slave_messages(), 2);
+    assert_eq!(slave.get_event_log()[0], 0x82);
+}
+
+#[test]
+fn test_slave_tcp_unit_ff() {
+    let slave = ModbusSlave::new(1, ModbusProto::TcpUdp);
+    let mut context = rmodbus::server::context::ModbusContext::new();
+
+    let mut request = |unit: u8, pdu: &[u8]| {
+        let frame = modbus_pdu::wrap(ModbusProto::TcpUdp, unit, 7, pdu);
+        slave.process(&frame, &mut context, &ModbusClient::Unknown, ClientClass::ReadWrite).unwrap()
+    };
+
+    assert_eq!(request(255, &[8, 0, 0, 0x12, 0x34]), Some(modbus_pdu::wrap(ModbusProto::TcpUdp, 255, 7, &[8, 0, 0, 0x12, 0x34])));
+    assert_eq!(request(255, &[11]), Some(modbus_pdu::wrap(ModbusProto::TcpUdp, 255, 7, &[11, 0, 0, 0, 1])));
+    assert_eq!(request(255, &[6, 0, 2, 0, 9]), Some(modbus_pdu::wrap(ModbusProto::TcpUdp, 255, 7, &[6, 0, 2, 0, 9])));
+    assert_eq!(request(255, &[3, 0, 2, 0, 1]), Some(modbus_pdu::wrap(ModbusProto::TcpUdp, 255, 7, &[3, 2, 0, 9])));
+    assert_eq!(request(0, &[6, 0, 3, 0, 4]), None);
+    assert_eq!(request(0, &[11]), None);
+    assert_eq!(request(1, &[3, 0, 3, 0, 1]), Some(modbus_pdu::wrap(ModbusProto::TcpUdp, 1, 7, &[3, 2, 0, 4])));
+
+    let rtu = ModbusSlave::new(1, ModbusProto::Rtu);
+    let frame = modbus_pdu::wrap(ModbusProto::Rtu, 255, 0, &[11]);
+    assert!(rtu.process(&frame, &mut context, &ModbusClient::Unknown, ClientClass::ReadWrite).unwrap().is_none());
 }
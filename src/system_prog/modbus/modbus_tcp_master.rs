@@ -1,54 +1,296 @@
 use super::modbus_master::ModbusMaster;
-use super::modbus_master_actions::{Acton};
-use super::modbus_error::ModbusErr;
+use super::modbus_master_actions::Acton;
+use super::modbus_poll_driver::{PollDriver, PollLink};
+use super::modbus_connection::ConnectionStatus;
 use rmodbus::ModbusProto;
-use rmodbus::server::context::ModbusContext;
+use crate::memory::DataMemory;
 use crate::task::ConstProgram;
 use std::{net, time, io};
+use std::net::ToSocketAddrs;
 use super::timeaut_heandler::TimeautHeandler;
+use super::modbus_tls::TlsConfig;
 
-pub struct ModbusTcpMaster<const N: usize> {
+struct TcpLink {
     socket: &'static str,
-    modbus_master: ModbusMaster,
-    actions: [Acton; N],
-    timeout_heandler: TimeautHeandler,
+    rtu_framing: bool,
+    tls: Option<TlsConfig>,
 }
 
-impl<const N: usize> ModbusTcpMaster<N> {
-    pub fn new(id: u8, socket: &'static str, actions: [Acton; N], timeout_heandler: TimeautHeandler) -> Self {
+enum TcpConn {
+    Plain(net::TcpStream),
+    Tls(Box<rustls::StreamOwned<rustls::ClientConnection, net::TcpStream>>),
+}
+
+impl TcpConn {
+    fn socket(&self) -> &net::TcpStream {
+        match self {
+            Self::Plain(stream) => stream,
+            Self::Tls(stream) => stream.get_ref(),
+        }
+    }
+}
+
+impl io::Read for TcpConn {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(stream) => stream.read(buf),
+            Self::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl io::Write for TcpConn {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(stream) => stream.write(buf),
+            Self::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(stream) => stream.flush(),
+            Self::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+impl PollLink for TcpLink {
+    type Conn = TcpConn;
+    type Stream<'a> = &'a mut TcpConn;
+
+    fn open(&self, timeout: time::Duration) -> io::Result<TcpConn> {
+        let stream = connect(self.socket, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+
+        match &self.tls {
+            Some(tls) => Ok(TcpConn::Tls(Box::new(tls.connect(self.socket, stream)?))),
+            None => Ok(TcpConn::Plain(stream)),
+        }
+    }
+
+    fn stream<'a>(&'a self, conn: &'a mut TcpConn, timeout: time::Duration) -> io::Result<&'a mut TcpConn> {
+        if self.rtu_framing {
+            discard_input(conn)?;
+        }
+
+        conn.socket().set_read_timeout(Some(timeout))?;
+        Ok(conn)
+    }
+}
+
+pub struct ModbusTcpMaster {
+    driver: PollDriver<TcpLink>,
+}
+
+impl ModbusTcpMaster {
+    pub fn new<const N: usize>(id: u8, socket: &'static str, actions: [Acton; N], timeout_heandler: TimeautHeandler) -> Self {
         
         let modbus_master = ModbusMaster::new(id, ModbusProto::TcpUdp);
+        let link = TcpLink { socket, rtu_framing: false, tls: None };
 
-        Self { socket, modbus_master, actions, timeout_heandler }
+        Self { driver: PollDriver::new(link, modbus_master, Vec::from(actions), timeout_heandler) }
+    }
+
+    pub fn rtu_framing(mut self) -> Self {
+        self.driver = self.driver.proto(ModbusProto::Rtu);
+        self.driver.link_mut().rtu_framing = true;
+        self
+    }
+
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.driver.link_mut().tls = Some(tls);
+        self
+    }
+
+    pub fn reconnect_backoff(mut self, min: time::Duration, max: time::Duration) -> Self {
+        self.driver = self.driver.reconnect_backoff(min, max);
+        self
+    }
+
+    pub fn status_coil(mut self, coil: u16) -> Self {
+        self.driver = self.driver.status_coil(coil);
+        self
+    }
+
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.driver = self.driver.retries(retries);
+        self
     }
-}
 
-impl<const N: usize> ConstProgram for ModbusTcpMaster<N> {
-    fn run(&self, context: &mut ModbusContext) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    pub fn merge_reads(mut self, gap: u16) -> Self {
+        self.driver = self.driver.merge_reads(gap);
+        self
+    }
+
+    pub fn add_actions(mut self, actions: Vec<Acton>) -> Self {
+        self.driver = self.driver.add_actions(actions);
+        self
+    }
+
+    pub fn get_actions(&self) -> &[Acton] { self.driver.get_actions() }
+
+    pub fn get_connection_status(&self) -> ConnectionStatus {
+        self.driver.get_connection_status()
+    }
+}
 
-        let mut stream = net::TcpStream::connect(self.socket)?;
-        stream.set_write_timeout(Some(time::Duration::from_micros(25)))?;
-        stream.set_read_timeout(Some(self.timeout_heandler.get_timeout()))?;
+pub(crate) fn connect(socket: &str, timeout: time::Duration) -> io::Result<net::TcpStream> {
+    let mut last_err = io::Error::new(io::ErrorKind::InvalidInput, "socket address not resolved");
 
-        for action in self.actions.iter() {
+    for addr in socket.to_socket_addrs()? {
+        match net::TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => {
+                stream.set_nodelay(true)?;
+                stream.set_write_timeout(Some(timeout))?;
+                return Ok(stream);
+            },
+            Err(e) => last_err = e,
+        }
+    }
 
-            if !action.need_run(context)? {
-                continue;
-            }
+    Err(last_err)
+}
 
-            let result = self.modbus_master.execute_action(action, context, &mut stream);
+fn discard_input(conn: &mut TcpConn) -> io::Result<()> {
+    let mut buf = [0u8; 256];
+    conn.socket().set_nonblocking(true)?;
 
-            if let Err(err) = result {
-                match err {
-                    ModbusErr::Io(ref e)
-                        if e.kind() == io::ErrorKind::TimedOut || e.kind() == io::ErrorKind::WouldBlock => {
-                            continue; //TODO
-                        }
-                    _ => return Err(Box::new(err))
-                }
-            }
+    let result = loop {
+        match io::Read::read(conn, &mut buf) {
+            Ok(0) => break Err(io::ErrorKind::ConnectionAborted.into()),
+            Ok(_) => {},
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break Ok(()),
+            Err(e) => break Err(e),
         }
+    };
 
-        Ok(())
+    conn.socket().set_nonblocking(false)?;
+    result
+}
+
+impl ConstProgram for ModbusTcpMaster {
+    fn run(&self, context: &mut dyn DataMemory) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        self.driver.run(context)
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_tcp_master_reconnect() {
+    use std::io::{Read, Write};
+    use crate::memory::PlcMemory;
+    use super::modbus_connection::ConnectionState;
+
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr: &'static str = Box::leak(listener.local_addr().unwrap().to_string().into_boxed_str());
+
+    let serve = |listener: &net::TcpListener, value: u8| {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0u8; 12];
+        stream.read_exact(&mut request).unwrap();
+        stream.write_all(&[request[0], request[1], 0, 0, 0, 5, request[6], 3, 2, 0, value]).unwrap();
+    };
+
+    let master = ModbusTcpMaster::new(1, addr, [
+        Acton::cycle_read_holdings(0, 1, time::Duration::ZERO, |ctx, data| ctx.set_holdings_bulk(0, &data).unwrap()),
+    ], TimeautHeandler::new(time::Duration::from_millis(500)))
+        .reconnect_backoff(time::Duration::from_millis(1), time::Duration::from_millis(4))
+        .status_coil(5);
+    let mut memory = PlcMemory::new(10, 10, 10, 10, 0);
+
+    std::thread::scope(|scope| {
+        let server = scope.spawn(|| serve(&listener, 7));
+        master.run(&mut memory).unwrap();
+        server.join().unwrap();
+    });
+
+    assert!(master.get_connection_status().is_connected());
+    assert!(memory.get_coil(5).unwrap());
+    assert_eq!(memory.get_holding(0).unwrap(), 7);
+
+    assert!(master.run(&mut memory).is_err());
+    assert_eq!(master.get_connection_status().get_state(), ConnectionState::Failed);
+    assert!(master.get_connection_status().get_last_error().is_some());
+    assert!(!memory.get_coil(5).unwrap());
+
+    std::thread::sleep(time::Duration::from_millis(5));
+
+    std::thread::scope(|scope| {
+        let server = scope.spawn(|| serve(&listener, 9));
+        master.run(&mut memory).unwrap();
+        server.join().unwrap();
+    });
+
+    assert!(master.get_connection_status().is_connected());
+    assert_eq!(master.get_connection_status().get_reconnect_count(), 1);
+    assert!(memory.get_coil(5).unwrap());
+    assert_eq!(memory.get_holding(0).unwrap(), 9);
+}
+
+#[test]
+fn test_tcp_master_rtu_framing() {
+    use std::io::{Read, Write};
+    use crate::memory::PlcMemory;
+    use super::modbus_pdu;
+
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr: &'static str = Box::leak(listener.local_addr().unwrap().to_string().into_boxed_str());
+
+    let master = ModbusTcpMaster::new(3, addr, [
+        Acton::cycle_read_holdings(5, 2, time::Duration::ZERO, |ctx, data| ctx.set_holdings_bulk(0, &data).unwrap()),
+    ], TimeautHeandler::new(time::Duration::from_millis(500)))
+        .rtu_framing();
+    let mut memory = PlcMemory::new(10, 10, 10, 10, 0);
+
+    let handle = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0u8; 8];
+        stream.read_exact(&mut request).unwrap();
+        assert_eq!(request.to_vec(), modbus_pdu::wrap(ModbusProto::Rtu, 3, 0, &[3, 0, 5, 0, 2]));
+        stream.write_all(&modbus_pdu::wrap(ModbusProto::Rtu, 3, 0, &[3, 4, 0, 1, 0, 2])).unwrap();
+    });
+
+    master.run(&mut memory).unwrap();
+    handle.join().unwrap();
+
+    assert_eq!(memory.get_holding(0).unwrap(), 1);
+    assert_eq!(memory.get_holding(1).unwrap(), 2);
+}
+
+#[test]
+fn test_tcp_master_tls() {
+    use std::io::{Read, Write};
+    use crate::memory::PlcMemory;
+    use super::modbus_pdu;
+    use super::modbus_tls::{self, TestCa};
+
+    let ca = TestCa::new();
+    let server = ca.issue(None);
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr: &'static str = Box::leak(listener.local_addr().unwrap().to_string().into_boxed_str());
+
+    let master = ModbusTcpMaster::new(3, addr, [
+        Acton::cycle_read_holdings(5, 2, time::Duration::ZERO, |ctx, data| ctx.set_holdings_bulk(0, &data).unwrap()),
+    ], TimeautHeandler::new(time::Duration::from_millis(500)))
+        .tls(ca.issue(Some("scada")));
+    let mut memory = PlcMemory::new(10, 10, 10, 10, 0);
+
+    let handle = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut stream = rustls::StreamOwned::new(server.accept().unwrap(), stream);
+
+        let mut request = [0u8; 12];
+        stream.read_exact(&mut request).unwrap();
+        assert_eq!(request.to_vec(), modbus_pdu::wrap(ModbusProto::TcpUdp, 3, u16::from_be_bytes([request[0], request[1]]), &[3, 0, 5, 0, 2]));
+        stream.write_all(&modbus_pdu::wrap(ModbusProto::TcpUdp, 3, u16::from_be_bytes([request[0], request[1]]), &[3, 4, 0, 1, 0, 2])).unwrap();
+
+        modbus_tls::peer_role(stream.conn.peer_certificates())
+    });
+
+    master.run(&mut memory).unwrap();
+
+    assert_eq!(handle.join().unwrap(), Some("scada".to_string()));
+    assert!(master.get_connection_status().is_connected());
+    assert_eq!(memory.get_holding(0).unwrap(), 1);
+    assert_eq!(memory.get_holding(1).unwrap(), 2);
+}
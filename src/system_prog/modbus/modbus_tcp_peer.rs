@@ -0,0 +1,299 @@
+use std::net::{TcpListener, TcpStream, SocketAddr, Shutdown};
+use std::cell::RefCell;
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+use std::{result, io};
+use rmodbus::ModbusProto;
+use crate::fail_strig;
+use crate::diagnostics::AuditLog;
+use super::modbus_error::ModbusErr;
+use super::modbus_allow_list::{AllowList, ClientClass};
+use super::modbus_pdu;
+use super::modbus_tls::{self, TlsConfig};
+
+pub(crate) struct TcpPeer {
+    pub(crate) stream: TcpStream,
+    pub(crate) addr: SocketAddr,
+    pub(crate) class: ClientClass,
+    pub(crate) buffer: Vec<u8>,
+    pub(crate) last_activity: Instant,
+    tls: Option<rustls::ServerConnection>,
+    authorized: bool,
+}
+
+impl TcpPeer {
+    pub(crate) fn new(stream: TcpStream, addr: SocketAddr, class: ClientClass) -> Self {
+        Self { stream, addr, class, buffer: Vec::new(), last_activity: Instant::now(), tls: None, authorized: true }
+    }
+
+    fn tls(mut self, conn: rustls::ServerConnection) -> Self {
+        self.tls = Some(conn);
+        self.authorized = false;
+        self
+    }
+
+    pub(crate) fn send(&mut self, data: &[u8]) -> io::Result<()> {
+        match &mut self.tls {
+            Some(tls) => {
+                tls.writer().write_all(data)?;
+                self.flush_tls()
+            },
+            None => self.stream.write_all(data),
+        }
+    }
+
+    fn flush_tls(&mut self) -> io::Result<()> {
+        if let Some(tls) = &mut self.tls {
+            while tls.wants_write() {
+                tls.write_tls(&mut self.stream)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn receive(&mut self) -> io::Result<bool> {
+        if self.tls.is_some() {
+            return self.receive_tls();
+        }
+
+        let mut buf = [0u8; 512];
+
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => return Ok(false),
+                Ok(n) => {
+                    self.buffer.extend_from_slice(&buf[..n]);
+                    self.last_activity = Instant::now();
+                },
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(true),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn receive_tls(&mut self) -> io::Result<bool> {
+        let Self { stream, tls: Some(tls), buffer, last_activity, .. } = self else {
+            return Ok(true);
+        };
+
+        let open = loop {
+            match tls.read_tls(stream) {
+                Ok(0) => break false,
+                Ok(_) => {
+                    let state = tls.process_new_packets().map_err(|e| {
+                        let _ = tls.write_tls(stream);
+                        modbus_tls::tls_io(e)
+                    })?;
+
+                    let start = buffer.len();
+                    buffer.resize(start + state.plaintext_bytes_to_read(), 0);
+                    tls.reader().read_exact(&mut buffer[start..])?;
+                    *last_activity = Instant::now();
+                },
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break true,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => return Err(e),
+            }
+        };
+
+        self.flush_tls()?;
+        Ok(open)
+    }
+
+    fn authorize(&mut self, tls: &TlsConfig) -> Option<Result<String, String>> {
+        let conn = self.tls.as_ref()?;
+
+        if self.authorized || conn.is_handshaking() {
+            return None;
+        }
+
+        let role = modbus_tls::peer_role(conn.peer_certificates());
+        let name = role.clone().unwrap_or_else(|| "none".to_string());
+
+        let Some(class) = tls.classify(role.as_deref()) else {
+            return Some(Err(name));
+        };
+
+        if class == ClientClass::ReadOnly {
+            self.class = ClientClass::ReadOnly;
+        }
+
+        self.authorized = true;
+        Some(Ok(name))
+    }
+
+    pub(crate) fn next_frame(&mut self, framing: ModbusProto) -> result::Result<Option<Vec<u8>>, ModbusErr> {
+        if framing == ModbusProto::Rtu {
+            return self.next_rtu_frame();
+        }
+
+        if self.buffer.len() < 7 {
+            return Ok(None);
+        }
+
+        let len = u16::from_be_bytes([self.buffer[4], self.buffer[5]]) as usize;
+
+        if self.buffer[2..4] != [0, 0] || !(2..=254).contains(&len) {
+            return Err(rmodbus::ErrorKind::FrameBroken.into());
+        }
+
+        if self.buffer.len() < len + 6 {
+            return Ok(None);
+        }
+
+        Ok(Some(self.buffer.drain(..len + 6).collect()))
+    }
+
+    fn next_rtu_frame(&mut self) -> result::Result<Option<Vec<u8>>, ModbusErr> {
+        match modbus_pdu::rtu_request_len(&self.buffer) {
+            Some(len) if len > 256 => Err(rmodbus::ErrorKind::FrameBroken.into()),
+            Some(len) if self.buffer.len() >= len => Ok(Some(self.buffer.drain(..len).collect())),
+            None if self.buffer.len() > 256 => Err(rmodbus::ErrorKind::FrameBroken.into()),
+            _ => Ok(None),
+        }
+    }
+}
+
+pub(crate) struct TcpServer {
+    listener: TcpListener,
+    source: &'static str,
+    clients: RefCell<Vec<TcpPeer>>,
+    max_connections: usize,
+    idle_timeout: Duration,
+    allow_list: Option<AllowList>,
+    audit: Option<AuditLog>,
+    tls: Option<TlsConfig>,
+}
+
+impl TcpServer {
+    pub(crate) fn new(listen: &'static str, source: &'static str) -> Self {
+        let listener = TcpListener::bind(listen)
+            .unwrap_or_else(|e| panic!("{}", fail_strig(&e)));
+        listener.set_nonblocking(true)
+            .unwrap_or_else(|e| panic!("{}", fail_strig(&e)));
+
+        Self {
+            listener,
+            source,
+            clients: RefCell::new(Vec::new()),
+            max_connections: 8,
+            idle_timeout: Duration::from_secs(60),
+            allow_list: None,
+            audit: None,
+            tls: None,
+        }
+    }
+
+    pub(crate) fn max_connections(mut self, max_connections: usize) -> Self {
+        self.max_connections = max_connections;
+        self
+    }
+
+    pub(crate) fn idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    pub(crate) fn allow_list(mut self, allow_list: AllowList) -> Self {
+        self.allow_list = Some(allow_list);
+        self
+    }
+
+    pub(crate) fn audit_log(mut self, log: AuditLog) -> Self {
+        self.audit = Some(log);
+        self
+    }
+
+    pub(crate) fn tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    pub(crate) fn get_local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub(crate) fn get_clients(&self) -> Vec<SocketAddr> {
+        self.clients.borrow().iter().map(|c| c.addr).collect()
+    }
+
+    fn accept(&self) -> io::Result<()> {
+        loop {
+            let (stream, addr) = match self.listener.accept() {
+                Ok(v) => v,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e),
+            };
+
+            let mut clients = self.clients.borrow_mut();
+
+            let class = match &self.allow_list {
+                Some(list) => list.classify(addr.ip()),
+                None => Some(ClientClass::ReadWrite),
+            };
+
+            let Some(class) = class else {
+                self.audit(format!("reject {}: not in allow list", addr));
+                let _ = stream.shutdown(Shutdown::Both);
+                continue;
+            };
+
+            if clients.len() >= self.max_connections {
+                self.audit(format!("reject {}: connection limit", addr));
+                let _ = stream.shutdown(Shutdown::Both);
+                continue;
+            }
+
+            stream.set_nonblocking(true)?;
+            stream.set_nodelay(true)?;
+
+            self.audit(format!("connect {} ({:?})", addr, class));
+
+            match &self.tls {
+                Some(tls) => clients.push(TcpPeer::new(stream, addr, class).tls(tls.accept()?)),
+                None => clients.push(TcpPeer::new(stream, addr, class)),
+            }
+        }
+    }
+
+    fn audit(&self, message: String) {
+        if let Some(log) = &self.audit {
+            log.record(self.source, message);
+        }
+    }
+
+    pub(crate) fn run(&self, mut serve: impl FnMut(&mut TcpPeer) -> result::Result<(), ModbusErr>) -> io::Result<()> {
+
+        self.accept()?;
+
+        self.clients.borrow_mut().retain_mut(|client| {
+            let result = match client.receive() {
+                Ok(true) => match self.tls.as_ref().and_then(|tls| client.authorize(tls)) {
+                    Some(Ok(role)) => {
+                        self.audit(format!("authorize {}: role {} ({:?})", client.addr, role, client.class));
+                        serve(client)
+                    },
+                    Some(Err(role)) => Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("role {} not allowed", role)).into()),
+                    None => serve(client),
+                },
+                Ok(false) => Err(io::Error::from(io::ErrorKind::ConnectionAborted).into()),
+                Err(e) => Err(e.into()),
+            };
+
+            let reason = match result {
+                Ok(()) if client.last_activity.elapsed() < self.idle_timeout => return true,
+                Ok(()) => "idle timeout".to_string(),
+                Err(ModbusErr::Io(ref e)) if e.kind() == io::ErrorKind::ConnectionAborted => "closed".to_string(),
+                Err(e) => e.to_string(),
+            };
+
+            self.audit(format!("disconnect {}: {}", client.addr, reason));
+            let _ = client.stream.shutdown(Shutdown::Both);
+            false
+        });
+
+        Ok(())
+    }
+}
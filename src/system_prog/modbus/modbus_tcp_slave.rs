@@ -1,47 +1,367 @@
-use std::net::TcpListener;
+use std::net::SocketAddr;
+use std::time::Duration;
 use crate::task::ConstProgram;
-use rmodbus::server::context::ModbusContext;
+use crate::memory::DataMemory;
 use rmodbus::ModbusProto;
 use std::{result, error, io};
-use crate::fail_strig;
 use super::modbus_slave::{ModbusSlave};
+use super::modbus_access::AccessRule;
+use super::modbus_error::ModbusErr;
+use super::modbus_tcp_peer::{TcpPeer, TcpServer};
+use super::modbus_counters::BusCounters;
+use super::modbus_unit::{UnitContext, UnitWindow};
+use super::modbus_write_event::{ModbusClient, WriteHook, WriteQueue};
+use super::modbus_allow_list::AllowList;
+use super::modbus_tls::TlsConfig;
+use crate::diagnostics::AuditLog;
+
 pub struct ModbusTcpSlave {
-    listener: TcpListener,
+    server: TcpServer,
     modbus_slave: ModbusSlave,
+    framing: ModbusProto,
 }
 
 impl ModbusTcpSlave {
 
     pub fn new(id: u8, socket: &'static str) -> Self {
 
-        let listener = Self::create_listener(socket);
+        let server = TcpServer::new(socket, "modbus_tcp");
         let modbus_slave = ModbusSlave::new(id, ModbusProto::TcpUdp);
 
-        Self { listener, modbus_slave }
+        Self { server, modbus_slave, framing: ModbusProto::TcpUdp }
+    }
+
+    pub fn access_rules<const N: usize>(mut self, rules: [AccessRule; N]) -> Self {
+        self.modbus_slave = self.modbus_slave.access_rules(rules);
+        self
+    }
+
+    pub fn write_hooks<const N: usize>(mut self, hooks: [WriteHook; N]) -> Self {
+        self.modbus_slave = self.modbus_slave.write_hooks(hooks);
+        self
+    }
+
+    pub fn write_queue(mut self, queue: WriteQueue) -> Self {
+        self.modbus_slave = self.modbus_slave.write_queue(queue);
+        self
+    }
+
+    pub fn unit_context(mut self, id: u8, context: UnitContext) -> Self {
+        self.modbus_slave = self.modbus_slave.unit_context(id, context);
+        self
+    }
+
+    pub fn unit_window(mut self, id: u8, window: UnitWindow) -> Self {
+        self.modbus_slave = self.modbus_slave.unit_window(id, window);
+        self
     }
 
-    fn create_listener(listen: &'static str) -> TcpListener {
-        let listener = TcpListener::bind(listen)
-            .unwrap_or_else(|e| panic!("{}", fail_strig(&e)));
-        listener.set_nonblocking(true)
-            .unwrap_or_else(|e| panic!("{}", fail_strig(&e)));
+    pub fn rtu_framing(mut self) -> Self {
+        self.modbus_slave = self.modbus_slave.with_proto(ModbusProto::Rtu);
+        self.framing = ModbusProto::Rtu;
+        self
+    }
+
+    pub fn max_connections(mut self, max_connections: usize) -> Self {
+        self.server = self.server.max_connections(max_connections);
+        self
+    }
+
+    pub fn idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.server = self.server.idle_timeout(idle_timeout);
+        self
+    }
+
+    pub fn allow_list(mut self, allow_list: AllowList) -> Self {
+        self.server = self.server.allow_list(allow_list);
+        self
+    }
+
+    pub fn audit_log(mut self, log: AuditLog) -> Self {
+        self.modbus_slave = self.modbus_slave.audit_log(log.clone());
+        self.server = self.server.audit_log(log);
+        self
+    }
+
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.server = self.server.tls(tls);
+        self
+    }
 
-        listener
+    pub fn get_local_addr(&self) -> io::Result<SocketAddr> {
+        self.server.get_local_addr()
+    }
+
+    pub fn get_counters(&self) -> BusCounters {
+        self.modbus_slave.get_counters()
+    }
+
+    pub fn get_event_log(&self) -> Vec<u8> {
+        self.modbus_slave.get_event_log()
+    }
+
+    pub fn clear_counters(&self) {
+        self.modbus_slave.clear_counters();
+    }
+
+    pub fn get_clients(&self) -> Vec<SocketAddr> {
+        self.server.get_clients()
+    }
+
+    fn serve(&self, client: &mut TcpPeer, context: &mut dyn DataMemory) -> result::Result<(), ModbusErr> {
+        let modbus_client = ModbusClient::Tcp(client.addr);
+
+        while let Some(request) = client.next_frame(self.framing)? {
+            if let Some(response) = self.modbus_slave.process(&request, context, &modbus_client, client.class)? {
+                client.send(&response)?;
+            }
+        }
+
+        Ok(())
     }
 }
 
 
-impl<'a> ConstProgram for ModbusTcpSlave {
-    fn run(&self, context: &mut ModbusContext) -> result::Result<(), Box<dyn error::Error>> {
-        
-        let mut stream = match self.listener.accept() {
-            Ok((stream, _)) => stream,
-            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
-            Err(e) => return Err(Box::new(e)),
-        };
+impl ConstProgram for ModbusTcpSlave {
+    fn run(&self, context: &mut dyn DataMemory) -> result::Result<(), Box<dyn error::Error>> {
+
+        self.server.run(|client| self.serve(client, context))?;
 
-        self.modbus_slave.handler(&mut stream, context)?;
-        
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_tcp_slave_persistent_clients() {
+    use std::net::TcpStream;
+    use std::io::{Read, Write};
+    use crate::memory::PlcMemory;
+
+    let slave = ModbusTcpSlave::new(1, "127.0.0.1:0")
+        .max_connections(2)
+        .idle_timeout(Duration::from_millis(200));
+    let addr = slave.get_local_addr().unwrap();
+    let mut memory = PlcMemory::new(10, 10, 10, 10, 0);
+    memory.set_holding(3, 33).unwrap();
+
+    let mut first = TcpStream::connect(addr).unwrap();
+    let mut second = TcpStream::connect(addr).unwrap();
+    let mut third = TcpStream::connect(addr).unwrap();
+    first.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+    third.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+
+    // two pipelined requests: tr id 5 read holding 3, tr id 6 write holding 4
+    first.write_all(&[0, 5, 0, 0, 0, 6, 1, 3, 0, 3, 0, 1, 0, 6, 0, 0, 0, 6, 1, 6, 0, 4, 0, 44]).unwrap();
+    second.write_all(&[0, 9, 0, 0, 0, 6, 1, 3]).unwrap();
+    std::thread::sleep(Duration::from_millis(20));
+
+    slave.run(&mut memory).unwrap();
+    assert_eq!(slave.get_clients().len(), 2);
+    assert_eq!(memory.get_holding(4).unwrap(), 44);
+
+    let mut response = [0u8; 23];
+    first.read_exact(&mut response).unwrap();
+    assert_eq!(response[..11], [0, 5, 0, 0, 0, 5, 1, 3, 2, 0, 33]);
+    assert_eq!(response[11..], [0, 6, 0, 0, 0, 6, 1, 6, 0, 4, 0, 44]);
+    assert_eq!(third.read(&mut response).unwrap_or(0), 0);
+
+    second.write_all(&[0, 3, 0, 1]).unwrap();
+    std::thread::sleep(Duration::from_millis(20));
+    slave.run(&mut memory).unwrap();
+
+    let mut response = [0u8; 11];
+    second.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+    second.read_exact(&mut response).unwrap();
+    assert_eq!(response, [0, 9, 0, 0, 0, 5, 1, 3, 2, 0, 33]);
+
+    drop(second);
+    std::thread::sleep(Duration::from_millis(20));
+    slave.run(&mut memory).unwrap();
+    assert_eq!(slave.get_clients().len(), 1);
+
+    std::thread::sleep(Duration::from_millis(250));
+    slave.run(&mut memory).unwrap();
+    assert!(slave.get_clients().is_empty());
+}
+
+#[test]
+fn test_tcp_slave_allow_list_and_audit() {
+    use std::net::TcpStream;
+    use std::io::{Read, Write};
+    use crate::memory::PlcMemory;
+    use super::modbus_allow_list::IpNet;
+
+    let log = AuditLog::new(16);
+    let slave = ModbusTcpSlave::new(1, "127.0.0.1:0")
+        .allow_list(AllowList::new().read_only(IpNet::parse("127.0.0.0/8").unwrap()))
+        .audit_log(log.clone());
+    let mut memory = PlcMemory::new(10, 10, 10, 10, 0);
+
+    let mut client = TcpStream::connect(slave.get_local_addr().unwrap()).unwrap();
+    client.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+    // tr id 1 write holding 2 = 7, tr id 2 read holding 2
+    client.write_all(&[0, 1, 0, 0, 0, 6, 1, 6, 0, 2, 0, 7, 0, 2, 0, 0, 0, 6, 1, 3, 0, 2, 0, 1]).unwrap();
+    std::thread::sleep(Duration::from_millis(20));
+    slave.run(&mut memory).unwrap();
+
+    let mut response = [0u8; 20];
+    client.read_exact(&mut response).unwrap();
+    assert_eq!(response[..9], [0, 1, 0, 0, 0, 3, 1, 0x86, 2]);
+    assert_eq!(response[9..], [0, 2, 0, 0, 0, 5, 1, 3, 2, 0, 0]);
+    assert_eq!(memory.get_holding(2).unwrap(), 0);
+
+    drop(client);
+    std::thread::sleep(Duration::from_millis(20));
+    slave.run(&mut memory).unwrap();
+
+    let messages: Vec<String> = log.entries().iter().map(|e| e.get_message().to_string()).collect();
+    assert_eq!(messages.len(), 3);
+    assert!(messages[0].starts_with("connect 127.0.0.1:") && messages[0].ends_with("(ReadOnly)"));
+    assert!(messages[1].starts_with("write fc 6 regs 2..3 from 127.0.0.1:") && messages[1].ends_with("exception 0x02"));
+    assert!(messages[2].starts_with("disconnect 127.0.0.1:") && messages[2].ends_with("closed"));
+
+    let slave = ModbusTcpSlave::new(1, "127.0.0.1:0")
+        .allow_list(AllowList::new().read_write(IpNet::parse("10.0.0.0/8").unwrap()))
+        .audit_log(log.clone());
+    let mut client = TcpStream::connect(slave.get_local_addr().unwrap()).unwrap();
+    client.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+    slave.run(&mut memory).unwrap();
+
+    assert!(slave.get_clients().is_empty());
+    assert_eq!(client.read(&mut response).unwrap_or(0), 0);
+    assert!(log.entries().last().unwrap().get_message().ends_with("not in allow list"));
+}
+
+#[test]
+fn test_tcp_slave_rtu_framing() {
+    use std::net::TcpStream;
+    use std::io::{Read, Write};
+    use crate::memory::PlcMemory;
+    use super::modbus_pdu;
+
+    let slave = ModbusTcpSlave::new(1, "127.0.0.1:0").rtu_framing();
+    let mut memory = PlcMemory::new(20, 10, 10, 10, 0);
+    memory.set_coil(10, true).unwrap();
+
+    let mut client = TcpStream::connect(slave.get_local_addr().unwrap()).unwrap();
+    client.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+
+    let read = modbus_pdu::wrap(ModbusProto::Rtu, 1, 0, &[1, 0, 10, 0, 10]);
+    let write = modbus_pdu::wrap(ModbusProto::Rtu, 1, 0, &[16, 0, 1, 0, 1, 2, 0, 9]);
+    client.write_all(&read).unwrap();
+    client.write_all(&write[..5]).unwrap();
+    std::thread::sleep(Duration::from_millis(20));
+    slave.run(&mut memory).unwrap();
+
+    let mut response = [0u8; 7];
+    client.read_exact(&mut response).unwrap();
+    assert_eq!(response.to_vec(), modbus_pdu::wrap(ModbusProto::Rtu, 1, 0, &[1, 2, 1, 0]));
+
+    client.write_all(&write[5..]).unwrap();
+    std::thread::sleep(Duration::from_millis(20));
+    slave.run(&mut memory).unwrap();
+
+    let mut response = [0u8; 8];
+    client.read_exact(&mut response).unwrap();
+    assert_eq!(response.to_vec(), modbus_pdu::wrap(ModbusProto::Rtu, 1, 0, &[16, 0, 1, 0, 1]));
+    assert_eq!(memory.get_holding(1).unwrap(), 9);
+
+    let mut pipelined = modbus_pdu::wrap(ModbusProto::Rtu, 1, 0, &[0x65, 1, 2]);
+    pipelined.extend(&read);
+    client.write_all(&pipelined).unwrap();
+    std::thread::sleep(Duration::from_millis(20));
+    slave.run(&mut memory).unwrap();
+
+    let mut response = [0u8; 12];
+    client.read_exact(&mut response).unwrap();
+    assert_eq!(response[..5].to_vec(), modbus_pdu::wrap(ModbusProto::Rtu, 1, 0, &[0xe5, 1]));
+    assert_eq!(response[5..].to_vec(), modbus_pdu::wrap(ModbusProto::Rtu, 1, 0, &[1, 2, 1, 0]));
+}
+
+#[test]
+fn test_tcp_slave_rtu_oversized() {
+    use std::net::TcpStream;
+    use std::io::{Read, Write};
+    use crate::memory::PlcMemory;
+    use super::modbus_pdu;
+
+    let slave = ModbusTcpSlave::new(1, "127.0.0.1:0").rtu_framing();
+    let mut memory = PlcMemory::new(10, 10, 10, 200, 0);
+
+    for bytes in [249u8, 247] {
+        let mut pdu = vec![16, 0, 0, 0, bytes / 2, bytes];
+        pdu.extend(vec![0u8; bytes as usize]);
+        let frame = modbus_pdu::wrap(ModbusProto::Rtu, 1, 0, &pdu);
+
+        let mut client = TcpStream::connect(slave.get_local_addr().unwrap()).unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        client.write_all(&frame).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        slave.run(&mut memory).unwrap();
+
+        let mut response = Vec::new();
+        assert_eq!(client.read_to_end(&mut response).unwrap(), 0);
+    }
+
+    let mut context = rmodbus::server::context::ModbusContext::new();
+    let mut pdu = vec![16, 0, 0, 0, 124, 249];
+    pdu.extend(vec![0u8; 249]);
+    let frame = modbus_pdu::wrap(ModbusProto::Rtu, 1, 0, &pdu);
+    let slave = ModbusSlave::new(1, ModbusProto::Rtu);
+    assert!(slave.process(&frame, &mut context, &ModbusClient::Unknown, super::modbus_allow_list::ClientClass::ReadWrite).is_err());
+}
+
+#[test]
+fn test_tcp_slave_tls() {
+    use std::net::TcpStream;
+    use std::io::{Read, Write};
+    use crate::memory::PlcMemory;
+    use super::modbus_pdu;
+    use super::modbus_tls::TestCa;
+    use super::modbus_allow_list::ClientClass;
+
+    let ca = TestCa::new();
+    let log = AuditLog::new(16);
+    let slave = ModbusTcpSlave::new(1, "127.0.0.1:0")
+        .tls(ca.issue(None).role("operator", ClientClass::ReadWrite).role("viewer", ClientClass::ReadOnly))
+        .audit_log(log.clone());
+    let mut memory = PlcMemory::new(10, 10, 10, 10, 0);
+    let addr = slave.get_local_addr().unwrap().to_string();
+
+    let clients = [
+        ca.issue(Some("operator")),
+        ca.issue(Some("viewer")),
+        ca.issue(Some("guest")),
+        TestCa::new().issue_trusting(Some("operator"), &ca),
+    ];
+
+    let handle = std::thread::spawn(move || {
+        clients.map(|tls| -> io::Result<Vec<u8>> {
+            let mut stream = tls.connect(&addr, TcpStream::connect(&addr)?)?;
+            stream.write_all(&modbus_pdu::wrap(ModbusProto::TcpUdp, 1, 1, &[6, 0, 2, 0, 7]))?;
+
+            let mut response = vec![0u8; 6];
+            stream.read_exact(&mut response)?;
+            response.resize(6 + u16::from_be_bytes([response[4], response[5]]) as usize, 0);
+            stream.read_exact(&mut response[6..])?;
+            Ok(response)
+        })
+    });
+
+    while !handle.is_finished() {
+        slave.run(&mut memory).unwrap();
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    let [operator, viewer, guest, foreign] = handle.join().unwrap();
+    assert_eq!(operator.unwrap(), modbus_pdu::wrap(ModbusProto::TcpUdp, 1, 1, &[6, 0, 2, 0, 7]));
+    assert_eq!(viewer.unwrap(), modbus_pdu::wrap(ModbusProto::TcpUdp, 1, 1, &[0x86, 2]));
+    assert!(guest.is_err());
+    assert!(foreign.is_err());
+    assert_eq!(memory.get_holding(2).unwrap(), 7);
+
+    let messages: Vec<String> = log.entries().iter().map(|e| e.get_message().to_string()).collect();
+    assert!(messages.iter().any(|m| m.ends_with("role operator (ReadWrite)")));
+    assert!(messages.iter().any(|m| m.ends_with("role viewer (ReadOnly)")));
+    assert!(messages.iter().any(|m| m.ends_with("role guest not allowed")));
+}
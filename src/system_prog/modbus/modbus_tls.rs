@@ -0,0 +1,182 @@
+use std::io;
+use std::net::TcpStream;
+use std::sync::Arc;
+use rustls::{ClientConfig, ClientConnection, RootCertStore, ServerConfig, ServerConnection, StreamOwned};
+use rustls::crypto::ring;
+use rustls::pki_types::{CertificateDer, ServerName};
+use rustls::server::WebPkiClientVerifier;
+use x509_parser::der_parser::der::parse_der_utf8string;
+use super::modbus_allow_list::ClientClass;
+use super::modbus_tls_error::TlsErr;
+
+pub const MODBUS_TLS_PORT: u16 = 802;
+
+const ROLE_OID: &str = "1.3.6.1.4.1.50316.802.1";
+
+#[derive(Clone)]
+pub struct TlsConfig {
+    server: Arc<ServerConfig>,
+    client: Arc<ClientConfig>,
+    server_name: Option<String>,
+    roles: Vec<(String, ClientClass)>,
+}
+
+impl TlsConfig {
+    pub fn from_pem(cert: &[u8], key: &[u8], ca: &[u8]) -> Result<Self, TlsErr> {
+        let certs = rustls_pemfile::certs(&mut &cert[..]).collect::<Result<Vec<_>, _>>()?;
+        let key = rustls_pemfile::private_key(&mut &key[..])?
+            .ok_or_else(|| TlsErr::Invalid("no private key".to_string()))?;
+
+        let mut roots = RootCertStore::empty();
+        for ca in rustls_pemfile::certs(&mut &ca[..]) {
+            roots.add(ca?)?;
+        }
+
+        if certs.is_empty() || roots.is_empty() {
+            return Err(TlsErr::Invalid("certificate or ca missing".to_string()));
+        }
+
+        let provider = Arc::new(ring::default_provider());
+        let roots = Arc::new(roots);
+
+        let verifier = WebPkiClientVerifier::builder_with_provider(roots.clone(), provider.clone()).build()?;
+
+        let server = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()?
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(certs.clone(), key.clone_key())?;
+
+        let client = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots)
+            .with_client_auth_cert(certs, key)?;
+
+        Ok(Self {
+            server: Arc::new(server),
+            client: Arc::new(client),
+            server_name: None,
+            roles: Vec::new(),
+        })
+    }
+
+    pub fn from_files(cert: &str, key: &str, ca: &str) -> Result<Self, TlsErr> {
+        Self::from_pem(&std::fs::read(cert)?, &std::fs::read(key)?, &std::fs::read(ca)?)
+    }
+
+    pub fn role(mut self, role: &str, class: ClientClass) -> Self {
+        self.roles.push((role.to_string(), class));
+        self
+    }
+
+    pub fn server_name(mut self, name: &str) -> Self {
+        self.server_name = Some(name.to_string());
+        self
+    }
+
+    pub fn get_roles(&self) -> &[(String, ClientClass)] { &self.roles }
+
+    pub(crate) fn classify(&self, role: Option<&str>) -> Option<ClientClass> {
+        match (self.roles.is_empty(), role) {
+            (true, _) => Some(ClientClass::ReadWrite),
+            (false, Some(role)) => self.roles.iter().find(|(r, _)| r == role).map(|(_, class)| *class),
+            (false, None) => None,
+        }
+    }
+
+    pub(crate) fn accept(&self) -> io::Result<ServerConnection> {
+        ServerConnection::new(self.server.clone()).map_err(tls_io)
+    }
+
+    pub(crate) fn connect(&self, socket: &str, mut stream: TcpStream) -> io::Result<StreamOwned<ClientConnection, TcpStream>> {
+        let host = self.server_name.as_deref().unwrap_or_else(|| host(socket));
+        let name = ServerName::try_from(host.to_string())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        let mut conn = ClientConnection::new(self.client.clone(), name).map_err(tls_io)?;
+
+        while conn.is_handshaking() {
+            conn.complete_io(&mut stream)?;
+        }
+
+        Ok(StreamOwned::new(conn, stream))
+    }
+}
+
+pub(crate) fn peer_role(certs: Option<&[CertificateDer]>) -> Option<String> {
+    let (_, cert) = x509_parser::parse_x509_certificate(certs?.first()?).ok()?;
+    let ext = cert.extensions().iter().find(|ext| ext.oid.to_id_string() == ROLE_OID)?;
+    let (_, role) = parse_der_utf8string(ext.value).ok()?;
+
+    role.as_str().ok().map(str::to_string)
+}
+
+pub(crate) fn tls_io(err: rustls::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+fn host(socket: &str) -> &str {
+    let host = socket.rsplit_once(':').map_or(socket, |(host, _)| host);
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+#[cfg(test)]
+pub(crate) struct TestCa {
+    key: rcgen::KeyPair,
+    cert: rcgen::Certificate,
+}
+
+#[cfg(test)]
+impl TestCa {
+    pub(crate) fn new() -> Self {
+        let key = rcgen::KeyPair::generate().unwrap();
+        let mut params = rcgen::CertificateParams::new(Vec::new()).unwrap();
+        params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        let cert = params.self_signed(&key).unwrap();
+
+        Self { key, cert }
+    }
+
+    pub(crate) fn issue(&self, role: Option<&str>) -> TlsConfig {
+        self.issue_trusting(role, self)
+    }
+
+    pub(crate) fn issue_trusting(&self, role: Option<&str>, trust: &TestCa) -> TlsConfig {
+        let key = rcgen::KeyPair::generate().unwrap();
+        let mut params = rcgen::CertificateParams::new(vec!["localhost".to_string(), "127.0.0.1".to_string()]).unwrap();
+        params.extended_key_usages = vec![
+            rcgen::ExtendedKeyUsagePurpose::ServerAuth,
+            rcgen::ExtendedKeyUsagePurpose::ClientAuth,
+        ];
+
+        if let Some(role) = role {
+            let mut value = vec![0x0c, role.len() as u8];
+            value.extend_from_slice(role.as_bytes());
+            params.custom_extensions.push(rcgen::CustomExtension::from_oid_content(&[1, 3, 6, 1, 4, 1, 50316, 802, 1], value));
+        }
+
+        let cert = params.signed_by(&key, &self.cert, &self.key).unwrap();
+
+        TlsConfig::from_pem(cert.pem().as_bytes(), key.serialize_pem().as_bytes(), trust.cert.pem().as_bytes()).unwrap()
+    }
+}
+
+#[test]
+fn test_tls_roles() {
+    let ca = TestCa::new();
+    let config = ca.issue(Some("operator"));
+    let cert = config.client.client_auth_cert_resolver.resolve(&[], &[rustls::SignatureScheme::ECDSA_NISTP256_SHA256]).unwrap();
+
+    assert_eq!(peer_role(Some(&cert.cert)), Some("operator".to_string()));
+    assert_eq!(peer_role(None), None);
+
+    let config = config.role("operator", ClientClass::ReadWrite).role("viewer", ClientClass::ReadOnly);
+    assert_eq!(config.classify(Some("viewer")), Some(ClientClass::ReadOnly));
+    assert_eq!(config.classify(Some("operator")), Some(ClientClass::ReadWrite));
+    assert_eq!(config.classify(Some("guest")), None);
+    assert_eq!(config.classify(None), None);
+    assert_eq!(ca.issue(None).classify(None), Some(ClientClass::ReadWrite));
+
+    assert_eq!(host("127.0.0.1:802"), "127.0.0.1");
+    assert_eq!(host("[::1]:802"), "::1");
+    assert!(TlsConfig::from_pem(b"", b"", b"").is_err());
+}
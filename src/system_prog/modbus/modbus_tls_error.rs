@@ -0,0 +1,40 @@
+use std::{io, fmt, error};
+
+#[derive(Debug)]
+pub enum TlsErr {
+    Io(io::Error),
+    Tls(rustls::Error),
+    Verifier(rustls::server::VerifierBuilderError),
+    Invalid(String),
+}
+
+impl From<io::Error> for TlsErr {
+    fn from(err: io::Error) -> TlsErr {
+        TlsErr::Io(err)
+    }
+}
+
+impl From<rustls::Error> for TlsErr {
+    fn from(err: rustls::Error) -> TlsErr {
+        TlsErr::Tls(err)
+    }
+}
+
+impl From<rustls::server::VerifierBuilderError> for TlsErr {
+    fn from(err: rustls::server::VerifierBuilderError) -> TlsErr {
+        TlsErr::Verifier(err)
+    }
+}
+
+impl fmt::Display for TlsErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => e.fmt(f),
+            Self::Tls(e) => e.fmt(f),
+            Self::Verifier(e) => e.fmt(f),
+            Self::Invalid(e) => write!(f, "invalid tls config: {}", e),
+        }
+    }
+}
+
+impl error::Error for TlsErr {}
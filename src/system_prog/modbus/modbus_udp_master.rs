@@ -0,0 +1,122 @@
+use super::modbus_master::ModbusMaster;
+use super::modbus_master_actions::Acton;
+use super::modbus_poll_driver::{PollDriver, PollLink};
+use super::modbus_connection::ConnectionStatus;
+use super::modbus_udp_transport::UdpTransport;
+use rmodbus::ModbusProto;
+use crate::memory::DataMemory;
+use crate::task::ConstProgram;
+use std::{net, time, io};
+use super::timeaut_heandler::TimeautHeandler;
+
+struct UdpLink {
+    socket: &'static str,
+}
+
+impl PollLink for UdpLink {
+    type Conn = net::UdpSocket;
+    type Stream<'a> = UdpTransport<'a>;
+
+    fn open(&self, timeout: time::Duration) -> io::Result<net::UdpSocket> {
+        let bind = match net::ToSocketAddrs::to_socket_addrs(self.socket)?.next() {
+            Some(net::SocketAddr::V6(_)) => "[::]:0",
+            _ => "0.0.0.0:0",
+        };
+
+        let udp = net::UdpSocket::bind(bind)?;
+        udp.connect(self.socket)?;
+        udp.set_write_timeout(Some(timeout))?;
+
+        Ok(udp)
+    }
+
+    fn stream<'a>(&'a self, conn: &'a mut net::UdpSocket, timeout: time::Duration) -> io::Result<UdpTransport<'a>> {
+        conn.set_read_timeout(Some(timeout))?;
+        Ok(UdpTransport::new(conn))
+    }
+}
+
+pub struct ModbusUdpMaster {
+    driver: PollDriver<UdpLink>,
+}
+
+impl ModbusUdpMaster {
+    pub fn new<const N: usize>(id: u8, socket: &'static str, actions: [Acton; N], timeout_heandler: TimeautHeandler) -> Self {
+
+        let modbus_master = ModbusMaster::new(id, ModbusProto::TcpUdp);
+
+        Self { driver: PollDriver::new(UdpLink { socket }, modbus_master, Vec::from(actions), timeout_heandler) }
+    }
+
+    pub fn reconnect_backoff(mut self, min: time::Duration, max: time::Duration) -> Self {
+        self.driver = self.driver.reconnect_backoff(min, max);
+        self
+    }
+
+    pub fn status_coil(mut self, coil: u16) -> Self {
+        self.driver = self.driver.status_coil(coil);
+        self
+    }
+
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.driver = self.driver.retries(retries);
+        self
+    }
+
+    pub fn merge_reads(mut self, gap: u16) -> Self {
+        self.driver = self.driver.merge_reads(gap);
+        self
+    }
+
+    pub fn add_actions(mut self, actions: Vec<Acton>) -> Self {
+        self.driver = self.driver.add_actions(actions);
+        self
+    }
+
+    pub fn get_actions(&self) -> &[Acton] { self.driver.get_actions() }
+
+    pub fn get_connection_status(&self) -> ConnectionStatus {
+        self.driver.get_connection_status()
+    }
+}
+
+impl ConstProgram for ModbusUdpMaster {
+    fn run(&self, context: &mut dyn DataMemory) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        self.driver.run(context)
+    }
+}
+
+#[test]
+fn test_udp_master() {
+    use std::time::Duration;
+    use crate::memory::PlcMemory;
+    use super::modbus_master_actions::Quality;
+
+    let device = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr: &'static str = Box::leak(device.local_addr().unwrap().to_string().into_boxed_str());
+
+    let master = ModbusUdpMaster::new(1, addr, [
+        Acton::cycle_read_inputs(4, 1, Duration::ZERO, |ctx, data| ctx.set_holding(0, data[0]).unwrap()),
+    ], TimeautHeandler::new(Duration::from_millis(500)))
+        .status_coil(2);
+    let mut memory = PlcMemory::new(10, 10, 10, 10, 0);
+
+    let handle = std::thread::spawn(move || {
+        let mut buf = [0u8; 260];
+        let (len, peer) = device.recv_from(&mut buf).unwrap();
+        assert_eq!(buf[6..len], [1, 4, 0, 4, 0, 1]);
+
+        let stale = [buf[0], buf[1].wrapping_sub(1), 0, 0, 0, 5, 1, 4, 2, 0, 1];
+        let reply = [buf[0], buf[1], 0, 0, 0, 5, 1, 4, 2, 0x04, 0xd2];
+        device.send_to(&stale, peer).unwrap();
+        device.send_to(&reply, peer).unwrap();
+    });
+
+    master.run(&mut memory).unwrap();
+    handle.join().unwrap();
+
+    assert_eq!(memory.get_holding(0).unwrap(), 1234);
+    assert!(memory.get_coil(2).unwrap());
+    assert_eq!(master.get_actions()[0].get_quality(), Quality::Fresh);
+    assert!(master.get_connection_status().is_connected());
+}
@@ -0,0 +1,165 @@
+use std::net::{UdpSocket, SocketAddr};
+use crate::task::ConstProgram;
+use crate::memory::DataMemory;
+use rmodbus::ModbusProto;
+use std::{result, error, io};
+use crate::fail_strig;
+use super::modbus_slave::ModbusSlave;
+use super::modbus_access::AccessRule;
+use super::modbus_counters::BusCounters;
+use super::modbus_unit::{UnitContext, UnitWindow};
+use super::modbus_write_event::{ModbusClient, WriteHook, WriteQueue};
+use super::modbus_allow_list::{AllowList, ClientClass};
+use crate::diagnostics::AuditLog;
+
+pub struct ModbusUdpSlave {
+    socket: UdpSocket,
+    modbus_slave: ModbusSlave,
+    allow_list: Option<AllowList>,
+    audit: Option<AuditLog>,
+}
+
+impl ModbusUdpSlave {
+
+    pub fn new(id: u8, socket: &'static str) -> Self {
+
+        let socket = Self::create_socket(socket);
+        let modbus_slave = ModbusSlave::new(id, ModbusProto::TcpUdp);
+
+        Self { socket, modbus_slave, allow_list: None, audit: None }
+    }
+
+    pub fn access_rules<const N: usize>(mut self, rules: [AccessRule; N]) -> Self {
+        self.modbus_slave = self.modbus_slave.access_rules(rules);
+        self
+    }
+
+    pub fn write_hooks<const N: usize>(mut self, hooks: [WriteHook; N]) -> Self {
+        self.modbus_slave = self.modbus_slave.write_hooks(hooks);
+        self
+    }
+
+    pub fn write_queue(mut self, queue: WriteQueue) -> Self {
+        self.modbus_slave = self.modbus_slave.write_queue(queue);
+        self
+    }
+
+    pub fn unit_context(mut self, id: u8, context: UnitContext) -> Self {
+        self.modbus_slave = self.modbus_slave.unit_context(id, context);
+        self
+    }
+
+    pub fn unit_window(mut self, id: u8, window: UnitWindow) -> Self {
+        self.modbus_slave = self.modbus_slave.unit_window(id, window);
+        self
+    }
+
+    pub fn allow_list(mut self, allow_list: AllowList) -> Self {
+        self.allow_list = Some(allow_list);
+        self
+    }
+
+    pub fn audit_log(mut self, log: AuditLog) -> Self {
+        self.modbus_slave = self.modbus_slave.audit_log(log.clone());
+        self.audit = Some(log);
+        self
+    }
+
+    pub fn get_local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    pub fn get_counters(&self) -> BusCounters {
+        self.modbus_slave.get_counters()
+    }
+
+    pub fn get_event_log(&self) -> Vec<u8> {
+        self.modbus_slave.get_event_log()
+    }
+
+    pub fn clear_counters(&self) {
+        self.modbus_slave.clear_counters();
+    }
+
+    fn create_socket(listen: &'static str) -> UdpSocket {
+        let socket = UdpSocket::bind(listen)
+            .unwrap_or_else(|e| panic!("{}", fail_strig(&e)));
+        socket.set_nonblocking(true)
+            .unwrap_or_else(|e| panic!("{}", fail_strig(&e)));
+
+        socket
+    }
+
+    fn classify(&self, addr: SocketAddr) -> Option<ClientClass> {
+        let class = match &self.allow_list {
+            Some(list) => list.classify(addr.ip()),
+            None => Some(ClientClass::ReadWrite),
+        };
+
+        if let (None, Some(log)) = (class, &self.audit) {
+            log.record("modbus_udp", format!("reject {}: not in allow list", addr));
+        }
+
+        class
+    }
+}
+
+
+impl ConstProgram for ModbusUdpSlave {
+    fn run(&self, context: &mut dyn DataMemory) -> result::Result<(), Box<dyn error::Error>> {
+
+        let mut buf = [0u8; 260];
+
+        loop {
+            let (len, addr) = match self.socket.recv_from(&mut buf) {
+                Ok(v) => v,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(ref e) if e.kind() == io::ErrorKind::ConnectionReset => continue,
+                Err(e) => return Err(Box::new(e)),
+            };
+
+            let Some(class) = self.classify(addr) else { continue };
+
+            if len < 8 || u16::from_be_bytes([buf[4], buf[5]]) as usize + 6 != len {
+                continue;
+            }
+
+            let response = match self.modbus_slave.process(&buf[..len], context, &ModbusClient::Udp(addr), class) {
+                Ok(Some(v)) => v,
+                Ok(None) | Err(_) => continue,
+            };
+
+            self.socket.send_to(&response, addr)?;
+        }
+    }
+}
+
+#[test]
+fn test_udp_slave() {
+    use std::time::Duration;
+    use crate::memory::PlcMemory;
+    use super::modbus_allow_list::IpNet;
+
+    let slave = ModbusUdpSlave::new(1, "127.0.0.1:0")
+        .allow_list(AllowList::new().read_only(IpNet::parse("127.0.0.1").unwrap()));
+    let mut memory = PlcMemory::new(10, 10, 10, 10, 0);
+    memory.set_input(4, 1234).unwrap();
+
+    let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+    client.connect(slave.get_local_addr().unwrap()).unwrap();
+    client.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+
+    // tr id 7 read input 4, malformed length, tr id 8 write holding 1
+    client.send(&[0, 7, 0, 0, 0, 6, 1, 4, 0, 4, 0, 1]).unwrap();
+    client.send(&[0, 9, 0, 0, 0, 9, 1, 4, 0, 4, 0, 1]).unwrap();
+    client.send(&[0, 8, 0, 0, 0, 6, 1, 6, 0, 1, 0, 5]).unwrap();
+    std::thread::sleep(Duration::from_millis(20));
+    slave.run(&mut memory).unwrap();
+
+    let mut buf = [0u8; 260];
+    let len = client.recv(&mut buf).unwrap();
+    assert_eq!(buf[..len], [0, 7, 0, 0, 0, 5, 1, 4, 2, 0x04, 0xd2]);
+    let len = client.recv(&mut buf).unwrap();
+    assert_eq!(buf[..len], [0, 8, 0, 0, 0, 3, 1, 0x86, 2]);
+    assert_eq!(memory.get_holding(1).unwrap(), 0);
+}
@@ -0,0 +1,33 @@
+use std::{io, net::UdpSocket, collections::VecDeque};
+
+pub(crate) struct UdpTransport<'a> {
+    socket: &'a UdpSocket,
+    datagram: VecDeque<u8>,
+}
+
+impl<'a> UdpTransport<'a> {
+    pub(crate) fn new(socket: &'a UdpSocket) -> Self {
+        Self { socket, datagram: VecDeque::new() }
+    }
+}
+
+impl io::Read for UdpTransport<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.datagram.is_empty() {
+            let mut datagram = [0u8; 260];
+            let len = self.socket.recv(&mut datagram)?;
+            self.datagram.extend(&datagram[..len]);
+        }
+
+        self.datagram.read(buf)
+    }
+}
+
+impl io::Write for UdpTransport<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.datagram.clear();
+        self.socket.send(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> { Ok(()) }
+}
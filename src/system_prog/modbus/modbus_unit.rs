@@ -0,0 +1,156 @@
+use std::rc::Rc;
+use std::cell::{Ref, RefCell, RefMut};
+use rmodbus::ErrorKind;
+use rmodbus::consts::{MODBUS_SET_COIL, MODBUS_SET_HOLDING, MODBUS_SET_COILS_BULK, MODBUS_SET_HOLDINGS_BULK};
+use crate::memory::{DataMemory, MemoryArea, PlcMemory};
+
+#[derive(Clone)]
+pub struct UnitContext {
+    memory: Rc<RefCell<PlcMemory>>,
+}
+
+impl UnitContext {
+    pub fn new(coils: usize, discretes: usize, inputs: usize, holdings: usize) -> Self {
+        Self { memory: Rc::new(RefCell::new(PlcMemory::new(coils, discretes, inputs, holdings, 0))) }
+    }
+
+    pub fn get_memory(&self) -> Ref<'_, PlcMemory> { self.memory.borrow() }
+
+    pub fn get_memory_mut(&self) -> RefMut<'_, PlcMemory> { self.memory.borrow_mut() }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct UnitWindow {
+    coils: (u16, u16),
+    discretes: (u16, u16),
+    inputs: (u16, u16),
+    holdings: (u16, u16),
+}
+
+impl UnitWindow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn coils(mut self, offset: u16, count: u16) -> Self {
+        self.coils = (offset, count);
+        self
+    }
+
+    pub fn discretes(mut self, offset: u16, count: u16) -> Self {
+        self.discretes = (offset, count);
+        self
+    }
+
+    pub fn inputs(mut self, offset: u16, count: u16) -> Self {
+        self.inputs = (offset, count);
+        self
+    }
+
+    pub fn holdings(mut self, offset: u16, count: u16) -> Self {
+        self.holdings = (offset, count);
+        self
+    }
+
+    pub(crate) fn main_reg(&self, func: u8, reg: u16) -> Option<u16> {
+        let area = match func {
+            MODBUS_SET_COIL | MODBUS_SET_COILS_BULK => MemoryArea::Coils,
+            MODBUS_SET_HOLDING | MODBUS_SET_HOLDINGS_BULK => MemoryArea::Holdings,
+            _ => return None,
+        };
+
+        self.translate(area, reg).ok()
+    }
+
+    fn get_range(&self, area: MemoryArea) -> (u16, u16) {
+        match area {
+            MemoryArea::Coils => self.coils,
+            MemoryArea::Discretes => self.discretes,
+            MemoryArea::Inputs => self.inputs,
+            MemoryArea::Holdings => self.holdings,
+            MemoryArea::Markers => (0, 0),
+        }
+    }
+
+    fn translate(&self, area: MemoryArea, reg: u16) -> Result<u16, ErrorKind> {
+        let (offset, count) = self.get_range(area);
+
+        match reg < count {
+            true => offset.checked_add(reg).ok_or(ErrorKind::OOBContext),
+            false => Err(ErrorKind::OOBContext),
+        }
+    }
+}
+
+pub(crate) enum UnitMap {
+    Context(UnitContext),
+    Window(UnitWindow),
+}
+
+pub(crate) struct WindowMemory<'a> {
+    memory: &'a mut dyn DataMemory,
+    window: UnitWindow,
+}
+
+impl<'a> WindowMemory<'a> {
+    pub(crate) fn new(memory: &'a mut dyn DataMemory, window: UnitWindow) -> Self {
+        Self { memory, window }
+    }
+}
+
+impl DataMemory for WindowMemory<'_> {
+    fn get_size(&self, area: MemoryArea) -> usize {
+        let (offset, count) = self.window.get_range(area);
+        (count as usize).min(self.memory.get_size(area).saturating_sub(offset as usize))
+    }
+
+    fn get_coil(&self, reg: u16) -> Result<bool, ErrorKind> {
+        self.memory.get_coil(self.window.translate(MemoryArea::Coils, reg)?)
+    }
+
+    fn get_discrete(&self, reg: u16) -> Result<bool, ErrorKind> {
+        self.memory.get_discrete(self.window.translate(MemoryArea::Discretes, reg)?)
+    }
+
+    fn get_input(&self, reg: u16) -> Result<u16, ErrorKind> {
+        self.memory.get_input(self.window.translate(MemoryArea::Inputs, reg)?)
+    }
+
+    fn get_holding(&self, reg: u16) -> Result<u16, ErrorKind> {
+        self.memory.get_holding(self.window.translate(MemoryArea::Holdings, reg)?)
+    }
+
+    fn set_coil(&mut self, reg: u16, value: bool) -> Result<(), ErrorKind> {
+        self.memory.set_coil(self.window.translate(MemoryArea::Coils, reg)?, value)
+    }
+
+    fn set_discrete(&mut self, reg: u16, value: bool) -> Result<(), ErrorKind> {
+        self.memory.set_discrete(self.window.translate(MemoryArea::Discretes, reg)?, value)
+    }
+
+    fn set_input(&mut self, reg: u16, value: u16) -> Result<(), ErrorKind> {
+        self.memory.set_input(self.window.translate(MemoryArea::Inputs, reg)?, value)
+    }
+
+    fn set_holding(&mut self, reg: u16, value: u16) -> Result<(), ErrorKind> {
+        self.memory.set_holding(self.window.translate(MemoryArea::Holdings, reg)?, value)
+    }
+}
+
+#[test]
+fn test_window_memory() {
+    let mut memory = PlcMemory::new(10, 10, 10, 100, 0);
+    memory.set_holding(40, 7).unwrap();
+
+    let window = UnitWindow::new().holdings(40, 10).coils(8, 4);
+    let mut view = WindowMemory::new(&mut memory, window);
+
+    assert_eq!(view.get_holding(0).unwrap(), 7);
+    assert_eq!(view.get_holding(10), Err(ErrorKind::OOBContext));
+    assert_eq!(view.get_input(0), Err(ErrorKind::OOBContext));
+    assert_eq!(view.get_size(MemoryArea::Coils), 2);
+
+    view.set_holdings_bulk(8, &[1, 2]).unwrap();
+    assert_eq!(view.set_holdings_bulk(9, &[1, 2]), Err(ErrorKind::OOBContext));
+    assert_eq!(memory.get_holding(49).unwrap(), 2);
+}
@@ -0,0 +1,200 @@
+use std::{fmt, net, rc::Rc, cell::RefCell, collections::VecDeque};
+use crate::memory::DataMemory;
+use rmodbus::consts::{MODBUS_SET_COIL, MODBUS_SET_HOLDING, MODBUS_SET_COILS_BULK, MODBUS_SET_HOLDINGS_BULK};
+use super::modbus_access::AccessArea;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ModbusClient {
+    Tcp(net::SocketAddr),
+    Udp(net::SocketAddr),
+    Serial(&'static str),
+    Unknown,
+}
+
+impl fmt::Display for ModbusClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(addr) | Self::Udp(addr) => write!(f, "{}", addr),
+            Self::Serial(port) => write!(f, "{}", port),
+            Self::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WriteEvent {
+    client: ModbusClient,
+    area: AccessArea,
+    reg: u16,
+    old: u16,
+    new: u16,
+}
+
+impl WriteEvent {
+    pub fn get_client(&self) -> &ModbusClient { &self.client }
+    pub fn get_area(&self) -> AccessArea { self.area }
+    pub fn get_reg(&self) -> u16 { self.reg }
+    pub fn get_old(&self) -> u16 { self.old }
+    pub fn get_new(&self) -> u16 { self.new }
+    pub fn is_changed(&self) -> bool { self.old != self.new }
+}
+
+pub struct WriteHook {
+    area: AccessArea,
+    offset: u16,
+    count: u16,
+    handler: fn(&mut dyn DataMemory, &WriteEvent),
+}
+
+impl WriteHook {
+    pub fn coils(offset: u16, count: u16, handler: fn(&mut dyn DataMemory, &WriteEvent)) -> Self {
+        Self { area: AccessArea::Coils, offset, count, handler }
+    }
+
+    pub fn holdings(offset: u16, count: u16, handler: fn(&mut dyn DataMemory, &WriteEvent)) -> Self {
+        Self { area: AccessArea::Holdings, offset, count, handler }
+    }
+
+    fn contains(&self, event: &WriteEvent) -> bool {
+        self.area == event.area
+            && event.reg >= self.offset
+            && (event.reg as u32) < self.offset as u32 + self.count as u32
+    }
+}
+
+#[derive(Clone)]
+pub struct WriteQueue {
+    events: Rc<RefCell<VecDeque<WriteEvent>>>,
+    capacity: usize,
+}
+
+impl WriteQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self { events: Rc::new(RefCell::new(VecDeque::with_capacity(capacity))), capacity }
+    }
+
+    pub fn pop(&self) -> Option<WriteEvent> {
+        self.events.borrow_mut().pop_front()
+    }
+
+    pub fn drain(&self) -> Vec<WriteEvent> {
+        self.events.borrow_mut().drain(..).collect()
+    }
+
+    pub fn len(&self) -> usize { self.events.borrow().len() }
+
+    pub fn is_empty(&self) -> bool { self.events.borrow().is_empty() }
+
+    fn push(&self, event: WriteEvent) {
+        let mut events = self.events.borrow_mut();
+
+        if events.len() >= self.capacity {
+            events.pop_front();
+        }
+
+        events.push_back(event);
+    }
+}
+
+pub(crate) struct WriteSnapshot {
+    area: AccessArea,
+    reg: u16,
+    old: Vec<u16>,
+}
+
+impl WriteSnapshot {
+    pub(crate) fn new(context: &dyn DataMemory, func: u8, reg: u16, count: u16) -> Option<Self> {
+        let area = match func {
+            MODBUS_SET_COIL | MODBUS_SET_COILS_BULK => AccessArea::Coils,
+            MODBUS_SET_HOLDING | MODBUS_SET_HOLDINGS_BULK => AccessArea::Holdings,
+            _ => return None,
+        };
+
+        let old = read_area(context, area, reg, count)?;
+
+        Some(Self { area, reg, old })
+    }
+
+    pub(crate) fn notify(
+        self,
+        context: &mut dyn DataMemory,
+        client: &ModbusClient,
+        hooks: &[WriteHook],
+        queue: Option<&WriteQueue>,
+    ) {
+        let new = match read_area(context, self.area, self.reg, self.old.len() as u16) {
+            Some(v) => v,
+            None => return,
+        };
+
+        for (i, (old, new)) in self.old.into_iter().zip(new).enumerate() {
+            let event = WriteEvent {
+                client: client.clone(),
+                area: self.area,
+                reg: self.reg + i as u16,
+                old,
+                new,
+            };
+
+            for hook in hooks.iter().filter(|h| h.contains(&event)) {
+                (hook.handler)(context, &event);
+            }
+
+            if let Some(queue) = queue {
+                queue.push(event);
+            }
+        }
+    }
+}
+
+fn read_area(context: &dyn DataMemory, area: AccessArea, reg: u16, count: u16) -> Option<Vec<u16>> {
+    let mut result = Vec::with_capacity(count as usize);
+
+    for i in 0..count {
+        let value = match area {
+            AccessArea::Coils => context.get_coil(reg.checked_add(i)?).ok()? as u16,
+            AccessArea::Holdings => context.get_holding(reg.checked_add(i)?).ok()?,
+        };
+        result.push(value);
+    }
+
+    Some(result)
+}
+
+#[test]
+fn test_write_snapshot() {
+    use rmodbus::server::context::ModbusContext;
+
+    fn on_write(context: &mut dyn DataMemory, event: &WriteEvent) {
+        let calls = context.get_holding(100).unwrap();
+        context.set_holding(100, calls + 1).unwrap();
+        context.set_holding(101, event.get_new()).unwrap();
+    }
+
+    let mut context = ModbusContext::new();
+    let queue = WriteQueue::new(2);
+    let hooks = [WriteHook::holdings(11, 1, on_write)];
+
+    context.set_holding(10, 1).unwrap();
+    let snapshot = WriteSnapshot::new(&context, MODBUS_SET_HOLDINGS_BULK, 10, 3).unwrap();
+    context.set_holdings_bulk(10, &[5, 6, 7]).unwrap();
+    snapshot.notify(&mut context, &ModbusClient::Serial("/dev/ttyUSB0"), &hooks, Some(&queue));
+
+    assert_eq!(context.get_holding(100).unwrap(), 1);
+    assert_eq!(context.get_holding(101).unwrap(), 6);
+
+    let events = queue.drain();
+    assert_eq!(events.len(), 2);
+    assert_eq!((events[0].get_reg(), events[0].get_old(), events[0].get_new()), (11, 0, 6));
+    assert_eq!((events[1].get_reg(), events[1].get_old(), events[1].get_new()), (12, 0, 7));
+    assert_eq!(events[1].get_client(), &ModbusClient::Serial("/dev/ttyUSB0"));
+    assert!(queue.is_empty());
+
+    let snapshot = WriteSnapshot::new(&context, MODBUS_SET_COIL, 3, 1).unwrap();
+    context.set_coil(3, true).unwrap();
+    snapshot.notify(&mut context, &ModbusClient::Unknown, &hooks, Some(&queue));
+
+    let event = queue.pop().unwrap();
+    assert_eq!(event.get_area(), AccessArea::Coils);
+    assert!(event.is_changed());
+}
@@ -0,0 +1,7 @@
+mod recipe_error;
+mod recipe_data;
+mod recipe_manager;
+
+pub use recipe_error::RecipeErr;
+pub use recipe_data::{Recipe, RecipeField};
+pub use recipe_manager::{RecipeManager, RecipeRegisters, RecipeCommand, RecipeStatus};
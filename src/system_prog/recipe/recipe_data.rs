@@ -0,0 +1,134 @@
+use std::{fs, io, path};
+use serde::{Serialize, Deserialize};
+use crate::memory::{DataMemory, MemoryArea};
+use super::recipe_error::RecipeErr;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecipeField {
+    name: String,
+    address: u16,
+    value: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max: Option<u16>,
+}
+
+impl RecipeField {
+    pub fn new(name: &str, address: u16, value: u16) -> Self {
+        Self { name: name.to_string(), address, value, min: None, max: None }
+    }
+
+    pub fn new_limited(name: &str, address: u16, value: u16, min: u16, max: u16) -> Self {
+        Self { name: name.to_string(), address, value, min: Some(min), max: Some(max) }
+    }
+
+    pub fn get_name(&self) -> &str { &self.name }
+    pub fn get_address(&self) -> u16 { self.address }
+    pub fn get_value(&self) -> u16 { self.value }
+    pub fn get_min(&self) -> Option<u16> { self.min }
+    pub fn get_max(&self) -> Option<u16> { self.max }
+
+    pub fn in_limits(&self, value: u16) -> bool {
+        let above_min = self.min.is_none_or(|min| value >= min);
+        let below_max = self.max.is_none_or(|max| value <= max);
+
+        above_min && below_max
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recipe {
+    name: String,
+    fields: Vec<RecipeField>,
+}
+
+impl Recipe {
+    pub fn new(name: &str, fields: Vec<RecipeField>) -> Self {
+        Self { name: name.to_string(), fields }
+    }
+
+    pub fn from_file<P: AsRef<path::Path>>(path: P) -> Result<Self, RecipeErr> {
+        let file = fs::File::open(path)?;
+        let recipe = serde_yaml::from_reader(io::BufReader::new(file))?;
+
+        Ok(recipe)
+    }
+
+    pub fn to_file<P: AsRef<path::Path>>(&self, path: P) -> Result<(), RecipeErr> {
+        let file = fs::File::create(path)?;
+        serde_yaml::to_writer(io::BufWriter::new(file), self)?;
+
+        Ok(())
+    }
+
+    pub fn get_name(&self) -> &str { &self.name }
+    pub fn get_fields(&self) -> &[RecipeField] { &self.fields }
+
+    pub fn validate(&self) -> Result<(), RecipeErr> {
+        match self.fields.iter().find(|i| !i.in_limits(i.value)) {
+            Some(field) => Err(RecipeErr::OutOfLimits(field.name.clone(), field.address)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn load(&self, context: &mut dyn DataMemory) -> Result<(), RecipeErr> {
+        self.validate()?;
+
+        let size = context.get_size(MemoryArea::Holdings);
+
+        if let Some(field) = self.fields.iter().find(|i| i.address as usize >= size) {
+            return Err(RecipeErr::OutOfRange(field.name.clone(), field.address));
+        }
+
+        for field in self.fields.iter() {
+            context.set_holding(field.address, field.value)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn save(&mut self, context: &dyn DataMemory) -> Result<(), RecipeErr> {
+        let mut values = Vec::with_capacity(self.fields.len());
+
+        for field in self.fields.iter() {
+            let value = context.get_holding(field.address)?;
+            if !field.in_limits(value) {
+                return Err(RecipeErr::OutOfLimits(field.name.clone(), field.address));
+            }
+            values.push(value);
+        }
+
+        for (field, value) in self.fields.iter_mut().zip(values) {
+            field.value = value;
+        }
+
+        Ok(())
+    }
+
+    pub fn compare(&self, context: &dyn DataMemory) -> Result<Vec<&RecipeField>, RecipeErr> {
+        let mut result = Vec::new();
+
+        for field in self.fields.iter() {
+            if context.get_holding(field.address)? != field.value {
+                result.push(field);
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+#[test]
+fn test_recipe_load_out_of_range() {
+    use crate::memory::PlcMemory;
+
+    let recipe = Recipe::new("bread", vec![
+        RecipeField::new("temperature", 1, 180),
+        RecipeField::new("time", 20, 40),
+    ]);
+    let mut context = PlcMemory::new(0, 0, 0, 10, 0);
+
+    assert!(matches!(recipe.load(&mut context), Err(RecipeErr::OutOfRange(_, 20))));
+    assert_eq!(context.get_holding(1).unwrap(), 0);
+}
@@ -0,0 +1,59 @@
+use std::{io, fmt, error};
+
+#[derive(Debug)]
+pub enum RecipeErr {
+    Io(io::Error),
+    Yaml(serde_yaml::Error),
+    Rmodbus(rmodbus::ErrorKind),
+    NotFound(u16),
+    OutOfLimits(String, u16),
+    OutOfRange(String, u16),
+    Mismatch(String, u16),
+    UnknownCommand(u16),
+}
+
+impl RecipeErr {
+    pub fn get_field(&self) -> Option<(&str, u16)> {
+        match self {
+            Self::OutOfLimits(field, address) => Some((field, *address)),
+            Self::OutOfRange(field, address) => Some((field, *address)),
+            Self::Mismatch(field, address) => Some((field, *address)),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for RecipeErr {
+    fn from(err: io::Error) -> RecipeErr {
+        RecipeErr::Io(err)
+    }
+}
+
+impl From<serde_yaml::Error> for RecipeErr {
+    fn from(err: serde_yaml::Error) -> RecipeErr {
+        RecipeErr::Yaml(err)
+    }
+}
+
+impl From<rmodbus::ErrorKind> for RecipeErr {
+    fn from(err: rmodbus::ErrorKind) -> RecipeErr {
+        RecipeErr::Rmodbus(err)
+    }
+}
+
+impl fmt::Display for RecipeErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => e.fmt(f),
+            Self::Yaml(e) => e.fmt(f),
+            Self::Rmodbus(e) => e.fmt(f),
+            Self::NotFound(n) => write!(f, "recipe {} not found", n),
+            Self::OutOfLimits(field, address) => write!(f, "recipe field {} ({}) out of limits", field, address),
+            Self::OutOfRange(field, address) => write!(f, "recipe field {} ({}) out of memory range", field, address),
+            Self::Mismatch(field, address) => write!(f, "recipe field {} ({}) differs from context", field, address),
+            Self::UnknownCommand(c) => write!(f, "unknown recipe command {}", c),
+        }
+    }
+}
+
+impl error::Error for RecipeErr {}
@@ -0,0 +1,270 @@
+use std::{io, path, result, error};
+use crate::memory::DataMemory;
+use crate::task::MutProgram;
+use super::recipe_data::Recipe;
+use super::recipe_error::RecipeErr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecipeCommand {
+    Load = 1,
+    Save = 2,
+    Compare = 3,
+    Validate = 4,
+}
+
+impl RecipeCommand {
+    fn from_reg(value: u16) -> Result<Option<Self>, RecipeErr> {
+        match value {
+            0 => Ok(None),
+            1 => Ok(Some(Self::Load)),
+            2 => Ok(Some(Self::Save)),
+            3 => Ok(Some(Self::Compare)),
+            4 => Ok(Some(Self::Validate)),
+            _ => Err(RecipeErr::UnknownCommand(value)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecipeStatus {
+    Idle = 0,
+    Ok = 1,
+    NotFound = 2,
+    FileError = 3,
+    OutOfLimits = 4,
+    Mismatch = 5,
+    AddressError = 6,
+    UnknownCommand = 7,
+}
+
+impl RecipeStatus {
+    fn from_result(result: &Result<(), RecipeErr>) -> Self {
+        match result {
+            Ok(_) => Self::Ok,
+            Err(RecipeErr::NotFound(_)) => Self::NotFound,
+            Err(RecipeErr::Io(_)) | Err(RecipeErr::Yaml(_)) => Self::FileError,
+            Err(RecipeErr::OutOfLimits(..)) => Self::OutOfLimits,
+            Err(RecipeErr::Mismatch(..)) => Self::Mismatch,
+            Err(RecipeErr::Rmodbus(_)) | Err(RecipeErr::OutOfRange(..)) => Self::AddressError,
+            Err(RecipeErr::UnknownCommand(_)) => Self::UnknownCommand,
+        }
+    }
+}
+
+/// Holding registers used by the recipe manager:
+/// `command` takes a `RecipeCommand` code and is reset to 0 once executed,
+/// `recipe` selects the recipe number, `status` reports a `RecipeStatus` code
+/// and `field` reports the 1-based position of the failing field in the recipe,
+/// or 0 when the result is not tied to a field.
+#[derive(Clone, Copy)]
+pub struct RecipeRegisters {
+    command: u16,
+    recipe: u16,
+    status: u16,
+    field: u16,
+}
+
+impl RecipeRegisters {
+    pub fn new(command: u16, recipe: u16, status: u16, field: u16) -> Self {
+        Self { command, recipe, status, field }
+    }
+}
+
+pub struct RecipeManager {
+    dir: path::PathBuf,
+    registers: RecipeRegisters,
+    fronts: Vec<(u16, RecipeCommand, bool)>,
+    status: RecipeStatus,
+}
+
+impl RecipeManager {
+    pub fn new(dir: &'static str, registers: RecipeRegisters) -> Self {
+        Self {
+            dir: path::PathBuf::from(dir),
+            registers,
+            fronts: Vec::new(),
+            status: RecipeStatus::Idle,
+        }
+    }
+
+    pub fn front_coil(mut self, coil: u16, command: RecipeCommand) -> Self {
+        self.fronts.push((coil, command, false));
+        self
+    }
+
+    pub fn get_status(&self) -> RecipeStatus { self.status }
+
+    pub fn path(&self, number: u16) -> path::PathBuf {
+        self.dir.join(format!("{}.yaml", number))
+    }
+
+    pub fn read(&self, number: u16) -> Result<Recipe, RecipeErr> {
+        Recipe::from_file(self.path(number)).map_err(|e| match e {
+            RecipeErr::Io(ref err) if err.kind() == io::ErrorKind::NotFound => RecipeErr::NotFound(number),
+            _ => e,
+        })
+    }
+
+    pub fn execute(
+        &self,
+        context: &mut dyn DataMemory,
+        command: RecipeCommand,
+        number: u16,
+    ) -> Result<(), RecipeErr> {
+        let mut recipe = self.read(number)?;
+        self.apply(context, command, number, &mut recipe)
+    }
+
+    fn apply(
+        &self,
+        context: &mut dyn DataMemory,
+        command: RecipeCommand,
+        number: u16,
+        recipe: &mut Recipe,
+    ) -> Result<(), RecipeErr> {
+        match command {
+            RecipeCommand::Load => recipe.load(context),
+            RecipeCommand::Save => {
+                recipe.save(context)?;
+                recipe.to_file(self.path(number))
+            },
+            RecipeCommand::Compare => match recipe.compare(context)?.first() {
+                Some(field) => Err(RecipeErr::Mismatch(field.get_name().to_string(), field.get_address())),
+                None => Ok(()),
+            },
+            RecipeCommand::Validate => recipe.validate(),
+        }
+    }
+
+    fn command(&self, context: &mut dyn DataMemory, command: RecipeCommand) -> (Result<(), RecipeErr>, u16) {
+        let recipe = context.get_holding(self.registers.recipe)
+            .map_err(RecipeErr::from)
+            .and_then(|number| Ok((number, self.read(number)?)));
+
+        let (number, mut recipe) = match recipe {
+            Ok(recipe) => recipe,
+            Err(e) => return (Err(e), 0),
+        };
+
+        let result = self.apply(context, command, number, &mut recipe);
+
+        let field = match result {
+            Err(ref e) => e.get_field()
+                .and_then(|(name, _)| recipe.get_fields().iter().position(|field| field.get_name() == name))
+                .map_or(0, |index| index as u16 + 1),
+            Ok(_) => 0,
+        };
+
+        (result, field)
+    }
+
+    fn set_status(
+        &mut self,
+        context: &mut dyn DataMemory,
+        result: Result<(), RecipeErr>,
+        field: u16,
+    ) -> result::Result<(), Box<dyn error::Error>> {
+        self.status = RecipeStatus::from_result(&result);
+
+        context.set_holding(self.registers.status, self.status as u16)?;
+        context.set_holding(self.registers.field, field)?;
+
+        Ok(())
+    }
+}
+
+impl MutProgram for RecipeManager {
+    fn run(&mut self, context: &mut dyn DataMemory) -> result::Result<(), Box<dyn error::Error>> {
+
+        let value = context.get_holding(self.registers.command)?;
+
+        let result = match RecipeCommand::from_reg(value) {
+            Ok(Some(command)) => Some(self.command(context, command)),
+            Ok(None) => None,
+            Err(e) => Some((Err(e), 0)),
+        };
+
+        if let Some((result, field)) = result {
+            context.set_holding(self.registers.command, 0)?;
+            self.set_status(context, result, field)?;
+        }
+
+        for i in 0..self.fronts.len() {
+            let (coil, command, prev) = self.fronts[i];
+            let bit = context.get_coil(coil)?;
+            self.fronts[i].2 = bit;
+
+            if bit && !prev {
+                let (result, field) = self.command(context, command);
+                self.set_status(context, result, field)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_recipe_manager() {
+    use rmodbus::server::context::ModbusContext;
+    use super::recipe_data::RecipeField;
+
+    let dir = std::env::temp_dir().join(format!("plc_recipe_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let dir: &'static str = Box::leak(dir.to_str().unwrap().to_string().into_boxed_str());
+
+    let recipe = Recipe::new("bread", vec![
+        RecipeField::new("temperature", 10, 180),
+        RecipeField::new_limited("time", 11, 40, 10, 60),
+    ]);
+    recipe.to_file(format!("{}/1.yaml", dir)).unwrap();
+
+    let mut context = ModbusContext::new();
+    let mut manager = RecipeManager::new(dir, RecipeRegisters::new(0, 1, 2, 3))
+        .front_coil(5, RecipeCommand::Compare);
+
+    context.set_holding(1, 1).unwrap();
+    context.set_holding(0, RecipeCommand::Load as u16).unwrap();
+    manager.run(&mut context).unwrap();
+
+    assert_eq!(context.get_holding(0).unwrap(), 0);
+    assert_eq!(context.get_holding(2).unwrap(), RecipeStatus::Ok as u16);
+    assert_eq!(context.get_holding(10).unwrap(), 180);
+    assert_eq!(context.get_holding(11).unwrap(), 40);
+
+    context.set_holding(11, 45).unwrap();
+    context.set_coil(5, true).unwrap();
+    manager.run(&mut context).unwrap();
+
+    assert_eq!(context.get_holding(2).unwrap(), RecipeStatus::Mismatch as u16);
+    assert_eq!(context.get_holding(3).unwrap(), 2);
+
+    context.set_holding(0, RecipeCommand::Save as u16).unwrap();
+    manager.run(&mut context).unwrap();
+
+    assert_eq!(manager.get_status(), RecipeStatus::Ok);
+    assert_eq!(manager.read(1).unwrap().get_fields()[1].get_value(), 45);
+
+    context.set_holding(11, 70).unwrap();
+    context.set_holding(0, RecipeCommand::Save as u16).unwrap();
+    manager.run(&mut context).unwrap();
+
+    assert_eq!(manager.get_status(), RecipeStatus::OutOfLimits);
+    assert_eq!(context.get_holding(3).unwrap(), 2);
+
+    context.set_holding(11, 45).unwrap();
+    context.set_holding(10, 0).unwrap();
+    context.set_holding(0, RecipeCommand::Compare as u16).unwrap();
+    manager.run(&mut context).unwrap();
+
+    assert_eq!(manager.get_status(), RecipeStatus::Mismatch);
+    assert_eq!(context.get_holding(3).unwrap(), 1);
+
+    context.set_holding(1, 2).unwrap();
+    context.set_holding(0, RecipeCommand::Load as u16).unwrap();
+    manager.run(&mut context).unwrap();
+
+    assert_eq!(manager.get_status(), RecipeStatus::NotFound);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -2,16 +2,16 @@ mod task_errors;
 
 use std::time::{Duration, Instant};
 use std::{error, result, cmp};
-use rmodbus::server::context::{ModbusContext};
+use crate::memory::DataMemory;
 use task_errors::{TaskTimeOutError};
 use super::pls_std::BitWord;
 
 pub trait MutProgram {
-    fn run(&mut self, context: &mut ModbusContext) -> result::Result<(), Box<dyn error::Error>>;
+    fn run(&mut self, context: &mut dyn DataMemory) -> result::Result<(), Box<dyn error::Error>>;
 }
 
 pub trait ConstProgram {
-    fn run(&self, context: &mut ModbusContext) -> result::Result<(), Box<dyn error::Error>>;
+    fn run(&self, context: &mut dyn DataMemory) -> result::Result<(), Box<dyn error::Error>>;
 }
 
 pub enum Program<'a> {
@@ -138,7 +138,7 @@ impl<'a> Task<'a> {
 
     pub fn run(
         &mut self,
-        context: &mut ModbusContext,
+        context: &mut dyn DataMemory,
     ) -> result::Result<u8, Box<dyn error::Error>> {
 
         if self.next_program == 0 {
@@ -162,7 +162,7 @@ impl<'a> Task<'a> {
         Ok(self.next_program)
     }
 
-    pub fn need_run(&mut self, context: &ModbusContext) -> result::Result<bool, Box<dyn error::Error>> {
+    pub fn need_run(&mut self, context: &dyn DataMemory) -> result::Result<bool, Box<dyn error::Error>> {
         match &mut self.event {
             Event::Cycle((t, i)) => Ok(*t <= i.elapsed()),
             Event::BitFront((addr, b)) => {
@@ -221,22 +221,7 @@ impl<'a> cmp::PartialEq for Task<'a> {
 
 impl<'a> cmp::PartialOrd for Task<'a> {
     fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
-
-        let event_comparete = self.get_event()
-            .partial_cmp(&other.get_event());
-            
-        if let Some(i) = event_comparete {
-            return match i {
-                cmp::Ordering::Equal => {
-                    let first = self.get_priority();
-                    let second = other.get_priority();
-                    first.partial_cmp(&second)
-                },
-                _ => Some(i),    
-            };
-        }
-
-        event_comparete
+        Some(self.cmp(other))
     }
 }
 
@@ -244,11 +229,22 @@ impl<'a> cmp::Eq for Task<'a> {}
 
 impl<'a>  cmp::Ord for Task<'a> {
     fn cmp(&self, other: &Self) -> cmp::Ordering {
-        self.partial_cmp(other).unwrap()
+
+        let event_comparete = self.get_event()
+            .partial_cmp(&other.get_event())
+            .unwrap();
+            
+        match event_comparete {
+            cmp::Ordering::Equal => {
+                let first = self.get_priority();
+                let second = other.get_priority();
+                first.cmp(&second)
+            },
+            _ => event_comparete,    
+        }
     }
 }
 
-
 #[test]
 fn test_task_sort() {
     